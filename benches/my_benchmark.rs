@@ -1,4 +1,4 @@
-use criterion::{BenchmarkId, criterion_group, criterion_main, Criterion};
+use criterion::{BenchmarkId, criterion_group, criterion_main, Criterion, Throughput};
 use trees::bstree::BinarySearchTree;
 use trees::base::QueryableTree;
 use trees::avltree::AVLTree;
@@ -11,82 +11,90 @@ const TREE_SIZE: [i32; 5] = [10_000, 40_000, 70_000, 100_000, 130_000];
 // const TREE_SIZE: [i32; 5] = [100, 400, 700, 1000, 1300];
 
 
-fn benchmark_bst(tree_size: i32) {
+// Build phase: insert every value of `data` into a new tree
+
+fn build_bst(data: impl Iterator<Item = i32>) -> BinarySearchTree<i32> {
     let mut bst = BinarySearchTree::new();
-    for v in 0..tree_size {
+    for v in data {
         bst.insert(v);
     }
+    bst
+}
+
+fn build_avl(data: impl Iterator<Item = i32>) -> AVLTree<i32> {
+    let mut avl = AVLTree::new();
+    for v in data {
+        avl.insert(v);
+    }
+    avl
+}
+
+fn build_rbt(data: impl Iterator<Item = i32>) -> RedBlackTree<i32> {
+    let mut rbt = RedBlackTree::new();
+    for v in data {
+        rbt.insert(v);
+    }
+    rbt
+}
+
+// Query phase: look up the smallest tenth of the values
+
+fn query_tree<N, Q>(tree: &Q, tree_size: i32)
+where
+    N: trees::base::QueryableTreeNode<i32>,
+    Q: QueryableTree<i32, N>,
+{
     for v in 0..tree_size / 10 {
-        bst.contains(v);
+        tree.contains(v);
     }
 }
 
-fn benchmark_bst_insert_delete(tree_size: i32) {
+/// Return `0..tree_size` shuffled, and a tenth of it to delete
+fn shuffled_data(tree_size: i32) -> (Vec<i32>, Vec<i32>) {
     let seed = [0u8; 32];
     let mut rng: StdRng = SeedableRng::from_seed(seed);
     let mut data: Vec<i32> = (0..tree_size).collect();
     data.shuffle(&mut rng);
-    let sample = data.iter().choose_multiple(&mut rng, (tree_size / 10) as usize);
+    let sample = data.iter().copied().choose_multiple(&mut rng, (tree_size / 10) as usize);
+    (data, sample)
+}
 
-    let mut bst = BinarySearchTree::new();
-    for v in &data {
-        bst.insert(*v);
-    }
+fn benchmark_bst(tree_size: i32) {
+    let bst = build_bst(0..tree_size);
+    query_tree(&bst, tree_size);
+}
+
+fn benchmark_bst_insert_delete(tree_size: i32) {
+    let (data, sample) = shuffled_data(tree_size);
+    let mut bst = build_bst(data.into_iter());
     for v in sample.iter() {
-        bst.delete(**v);
+        bst.delete(*v);
     }
 }
 
 fn benchmark_avl(tree_size: i32) {
-    let mut avl = AVLTree::new();
-    for v in 0..tree_size {
-        avl.insert(v);
-    }
-    for v in 0..tree_size / 10 {
-        avl.contains(v);
-    }
+    let avl = build_avl(0..tree_size);
+    query_tree(&avl, tree_size);
 }
 
 fn benchmark_avl_insert_delete(tree_size: i32) {
-    let seed = [0u8; 32];
-    let mut rng: StdRng = SeedableRng::from_seed(seed);
-    let mut data: Vec<i32> = (0..tree_size).collect();
-    data.shuffle(&mut rng);
-    let sample = data.iter().choose_multiple(&mut rng, (tree_size / 10) as usize);
-
-    let mut avl = AVLTree::new();
-    for v in &data {
-        avl.insert(*v);
-    }
+    let (data, sample) = shuffled_data(tree_size);
+    let mut avl = build_avl(data.into_iter());
     for v in sample.iter() {
-        avl.delete(**v);
+        avl.delete(*v);
     }
 }
 
 fn benchmark_rbt(tree_size: i32) {
-    let mut rbt = RedBlackTree::new();
-    for v in 0..tree_size {
-        rbt.insert(v);
-    }
-    for v in 0..tree_size / 10 {
-        rbt.contains(v);
-    }
+    let rbt = build_rbt(0..tree_size);
+    query_tree(&rbt, tree_size);
 }
 
 fn benchmark_rbt_insert_delete(tree_size: i32) {
-    let seed = [0u8; 32];
-    let mut rng: StdRng = SeedableRng::from_seed(seed);
-    let mut data: Vec<i32> = (0..tree_size).collect();
-    data.shuffle(&mut rng);
-    let sample = data.iter().choose_multiple(&mut rng, (tree_size / 10) as usize);
-
-    let mut rbt = RedBlackTree::new();
-    for v in &data {
-        rbt.insert(*v);
-    }
-
+    let (data, sample) = shuffled_data(tree_size);
+    let mut rbt = build_rbt(data.into_iter());
     for v in sample.iter() {
-        rbt.delete(**v);
+        rbt.delete(*v);
     }
 }
 
@@ -150,10 +158,36 @@ fn bench_compare_insert_delete(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_insert_throughput(c: &mut Criterion) {
+    // Only the insert phase is timed: the shuffled input is prepared once per
+    // size and dropping the tree is left out, so the reported throughput is
+    // the time per insert.
+    let mut group = c.benchmark_group("Insert_throughput");
+    group.sample_size(10);
+    for (idx, size) in TREE_SIZE.iter().enumerate() {
+        let (data, _) = shuffled_data(*size);
+        group.throughput(Throughput::Elements(*size as u64));
+        group.bench_with_input(
+            BenchmarkId::new("BST", idx), &data,
+            |b, d| b.iter_with_large_drop(|| build_bst(d.iter().copied()))
+        );
+        group.bench_with_input(
+            BenchmarkId::new("AVL", idx), &data,
+            |b, d| b.iter_with_large_drop(|| build_avl(d.iter().copied()))
+        );
+        group.bench_with_input(
+            BenchmarkId::new("RBT", idx), &data,
+            |b, d| b.iter_with_large_drop(|| build_rbt(d.iter().copied()))
+        );
+    }
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_compare_all,
     bench_compare,
     bench_compare_insert_delete,
+    bench_insert_throughput,
 );
 criterion_main!(benches);
@@ -8,42 +8,64 @@
 //! use trees::base::QueryableTree;
 //! ```
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::fmt;
+use std::iter::FromIterator;
+use std::collections::BTreeMap;
 
 use std::cmp::{Ord};
 
-use crate::base::{QueryableTreeNode, QueryableTree};
+use crate::base::{self, QueryableTreeNode, QueryableTree, TreeOp, IntoIter};
+use crate::num::{self, Integer};
 
 type RcRefAVLTNode<T> = Rc<RefCell<AVLTreeNode<T>>>;
 type AVLNodeLink<T> = Option<RcRefAVLTNode<T>>;
 
 /// Node struct for [AVLTree](struct.AVLTree.html) struct
-pub struct AVLTreeNode<T: Ord + Copy + fmt::Debug> {
+pub struct AVLTreeNode<T: Ord + Clone + fmt::Debug> {
     /// Data stored in the node
     pub data: T,
     left: AVLNodeLink<T>,
     right: AVLNodeLink<T>,
     height: usize,
+    size: usize,
+    sum: Cell<Option<T>>,
 }
 
 /// An implementation of [AVL Tree](https://en.wikipedia.org/wiki/AVL_tree)
-pub struct AVLTree<T: Ord + Copy + fmt::Debug> {root: AVLNodeLink<T>}
+pub struct AVLTree<T: Ord + Clone + fmt::Debug> {
+    root: AVLNodeLink<T>,
+    history: Option<Vec<T>>,
+}
 
-impl <T: Ord + Copy + fmt::Debug> QueryableTreeNode<T> for AVLTreeNode<T> {
+impl<T: Ord + Clone + fmt::Debug> Drop for AVLTree<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl <T: Ord + Clone + fmt::Debug> QueryableTreeNode<T> for AVLTreeNode<T> {
     fn get_left(&self) -> &AVLNodeLink<T> { return &self.left; }
     fn get_right(&self) -> &AVLNodeLink<T> { return &self.right; }
-    fn get_data(&self) -> T { return self.data; }
+    fn get_left_mut(&mut self) -> &mut AVLNodeLink<T> { &mut self.left }
+    fn get_right_mut(&mut self) -> &mut AVLNodeLink<T> { &mut self.right }
+    fn get_data(&self) -> T { return self.data.clone(); }
+    fn len(&self) -> usize { self.size }
+    fn get_sum_cache(&self) -> Option<&Cell<Option<T>>> { Some(&self.sum) }
 }
 
-impl <T: Ord + Copy + fmt::Debug> QueryableTree<T, AVLTreeNode<T>> for AVLTree<T> {
+impl <T: Ord + Clone + fmt::Debug> QueryableTree<T, AVLTreeNode<T>> for AVLTree<T> {
     fn get_root(&self) -> &AVLNodeLink<T> {
         &self.root
     }
+
+    fn is_valid(&self) -> bool {
+        self.is_bst() && self.is_balanced()
+    }
 }
 
-impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
+impl<T: Ord + Clone + fmt::Debug> AVLTreeNode<T> {
     /// Create an new node, which will be called by [AVLTree](struct.AVLTree.html)
     fn new(data:T) -> AVLNodeLink<T>{
         Some(Rc::new(RefCell::new(Self {
@@ -51,9 +73,42 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
             left: None,
             right: None,
             height: 1,
+            size: 1,
+            sum: Cell::new(None),
         })))
     }
 
+    /// Build a height-balanced subtree from sorted and deduplicated values
+    fn from_sorted(values: &[T]) -> AVLNodeLink<T> {
+        if values.is_empty() {
+            return None;
+        }
+        let mid = values.len() / 2;
+        let node = Self::new(values[mid].clone());
+        if let Some(n) = node.as_ref() {
+            let left = Self::from_sorted(&values[..mid]);
+            let right = Self::from_sorted(&values[mid + 1..]);
+            let height = Self::_max(Self::_get_height(left.clone()), Self::_get_height(right.clone())) + 1;
+            n.borrow_mut().left = left;
+            n.borrow_mut().right = right;
+            n.borrow_mut().height = height;
+            n.borrow_mut().size = values.len();
+        }
+        node
+    }
+
+    /// Copy the subtree rooted at `node` into freshly allocated nodes
+    fn deep_clone(node: &RcRefAVLTNode<T>) -> RcRefAVLTNode<T> {
+        base::clone_subtree(node, |node, _| Self {
+            data: node.data.clone(),
+            left: None,
+            right: None,
+            height: node.height,
+            size: node.size,
+            sum: Cell::new(None),
+        })
+    }
+
     #[inline]
     fn _max(a: usize, b: usize) -> usize {
         if a > b {
@@ -63,30 +118,23 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
         }
     }
 
-    fn _is_balanced(&self) -> bool {
-        let left_height = self.get_left().as_ref().map(
-            |n| n.borrow().height()
-        ).unwrap_or(0);
-        let right_height = self.get_right().as_ref().map(
-            |n| n.borrow().height()
-        ).unwrap_or(0);
-        let delta_height = left_height as i64 - right_height as i64;
-        return if delta_height.abs() > 1 {
-            println!("{:?} {:?}", left_height, right_height);
-            false
-        } else {
-            let left_balanced = self.get_left().as_ref().map(
-                |n| n.borrow()._is_balanced()
-            ).unwrap_or(true);
-            let right_balanced = self.get_right().as_ref().map(
-                |n| n.borrow()._is_balanced()
-            ).unwrap_or(true);
-            if left_balanced && right_balanced {
-                true
-            } else {
-                false
-            }
+    /// Return the height of the subtree rooted at the current node, or
+    /// `None` if the heights of the two subtrees of any node in it differ
+    /// by more than one or a cached height is wrong
+    fn checked_height(&self) -> Option<usize> {
+        let left_height = match self.left.as_ref() {
+            Some(l) => l.borrow().checked_height()?,
+            None => 0,
+        };
+        let right_height = match self.right.as_ref() {
+            Some(r) => r.borrow().checked_height()?,
+            None => 0,
+        };
+        let height = Self::_max(left_height, right_height) + 1;
+        if (left_height as i64 - right_height as i64).abs() > 1 || height != self.height {
+            return None;
         }
+        Some(height)
     }
 
     fn _get_delta_height(n: &RcRefAVLTNode<T>) -> i64 {
@@ -104,6 +152,13 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
     fn _get_height(node: Option<RcRefAVLTNode<T>>) -> usize {
         node.map_or(0, |n| n.borrow().height)
     }
+
+    fn _update_size(n: &RcRefAVLTNode<T>) {
+        let left_size = n.borrow().left.as_ref().map_or(0, |l| l.borrow().size);
+        let right_size = n.borrow().right.as_ref().map_or(0, |r| r.borrow().size);
+        n.borrow_mut().size = left_size + right_size + 1;
+        n.borrow().sum.set(None);
+    }
     #[allow(unused_mut)]
     fn _lr_rotate(mut root: RcRefAVLTNode<T>) -> RcRefAVLTNode<T> {
         let left = root.borrow().left.clone().take().unwrap();
@@ -124,11 +179,13 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
             Self::_get_left_height(&root),
             Self::_get_right_height(&root)
         ) + 1;
+        Self::_update_size(&root);
         new_root.borrow_mut().right = Some(root);
         new_root.borrow_mut().height = Self::_max(
             Self::_get_left_height(&new_root),
             Self::_get_right_height(&new_root)
         ) + 1;
+        Self::_update_size(&new_root);
         return new_root
     }
     #[allow(unused_mut)]
@@ -139,27 +196,31 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
             Self::_get_left_height(&root),
             Self::_get_right_height(&root)
         ) + 1;
+        Self::_update_size(&root);
         new_root.borrow_mut().left = Some(root);
         new_root.borrow_mut().height = Self::_max(
             Self::_get_left_height(&new_root),
             Self::_get_right_height(&new_root)
         ) + 1;
+        Self::_update_size(&new_root);
         return new_root
     }
     #[allow(unused_mut)]
-    /// Insert a node, which will be called by [AVLTree](struct.AVLTree.html)
-    fn insert(node: AVLNodeLink<T>, data: T) -> AVLNodeLink<T> {
+    /// Insert a node, which will be called by [AVLTree](struct.AVLTree.html).
+    /// Every single rotation done while re-balancing adds one to `rotations`,
+    /// a double rotation adds two
+    fn insert(node: AVLNodeLink<T>, data: T, rotations: &mut u64) -> AVLNodeLink<T> {
         // insert the node
         let ret_node = match node {
-            None => AVLTreeNode::new(data).unwrap(),
+            None => AVLTreeNode::new(data.clone()).unwrap(),
             Some(mut n) => {
-                let node_data = n.borrow().data;
+                let node_data = n.borrow().data.clone();
                 if data < node_data  {
                     let left = n.borrow().left.clone();
-                    n.borrow_mut().left = Self::insert(left, data);
+                    n.borrow_mut().left = Self::insert(left, data.clone(), rotations);
                 } else if data > node_data {
                     let right = n.borrow().right.clone();
-                    n.borrow_mut().right = Self::insert(right, data);
+                    n.borrow_mut().right = Self::insert(right, data.clone(), rotations);
                 }
                 // else: data == node, nothing happens
                 n
@@ -169,14 +230,18 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
         let delta_height = Self::_get_delta_height(&ret_node);
         let ret_node = if delta_height == 2 {
             if data < ret_node.borrow().left.clone().unwrap().borrow().data {
+                *rotations += 1;
                 Self::_right_rotate(ret_node)
             } else {
+                *rotations += 2;
                 Self::_lr_rotate(ret_node)
             }
         } else if delta_height == -2 {
             if data < ret_node.borrow().right.clone().unwrap().borrow().data {
+                *rotations += 2;
                 Self::_rl_rotate(ret_node)
             } else {
+                *rotations += 1;
                 Self::_left_rotate(ret_node)
             }
         } else {
@@ -187,16 +252,20 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
             Self::_get_left_height(&ret_node),
             Self::_get_right_height(&ret_node)
         ) + 1;
+        Self::_update_size(&ret_node);
         Some(ret_node)
     }
     #[allow(unused_variables)]
-    /// Delete a node, which will be called by [AVLTree](struct.AVLTree.html)
-    fn delete(node: AVLNodeLink<T>, data: T) -> AVLNodeLink<T> {
+    /// Delete a node, which will be called by [AVLTree](struct.AVLTree.html).
+    /// The stored value which was removed is put in `removed`. Every single
+    /// rotation done while re-balancing adds one to `rotations`, a double
+    /// rotation adds two
+    fn delete(node: AVLNodeLink<T>, data: T, removed: &mut Option<T>, rotations: &mut u64) -> AVLNodeLink<T> {
         // delete the node
         let ret_node = match node {
             None => node,
             Some(n) => {
-                let node_data = n.borrow().data;
+                let node_data = n.borrow().data.clone();
                 // found the node which contains the same data
                 if node_data == data {
                     let left = n.borrow().left.clone();
@@ -204,15 +273,16 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
                     let ret = match (left.clone(), right.clone()) {
                         (Some(l), Some(r)) => {
                             let min_val = r.borrow().min();
-                            n.borrow_mut().data = min_val;
+                            n.borrow_mut().data = min_val.clone();
                             let right = n.borrow().right.clone().take();
-                            n.borrow_mut().right = Self::delete(right, min_val);
+                            n.borrow_mut().right = Self::delete(right, min_val, &mut None, rotations);
                             Some(n)
                         }
                         (Some(l), _) => Some(l),
                         (_, Some(r)) => Some(r),
                         (_, None) => None,
                     };
+                    *removed = Some(node_data);
                     ret
                 }
                 // go left
@@ -222,7 +292,7 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
                         return Some(n)
                     } else {
                         let left = n.borrow().left.clone().take();
-                        n.borrow_mut().left = Self::delete(left, data);
+                        n.borrow_mut().left = Self::delete(left, data, removed, rotations);
                     }
                     Some(n)
                 }
@@ -233,7 +303,7 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
                         return Some(n)
                     } else {
                         let right = n.borrow().right.clone().take();
-                        n.borrow_mut().right = Self::delete(right, data);
+                        n.borrow_mut().right = Self::delete(right, data, removed, rotations);
                     }
                     Some(n)
                 }
@@ -247,15 +317,19 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
                 let ret_n = if delta_height == 2 {
                     if Self::_get_left_height(&n.borrow().left.clone().unwrap())
                         >= Self::_get_right_height(&n.borrow().left.clone().unwrap()) {
+                        *rotations += 1;
                         Self::_right_rotate(n)
                     } else {
+                        *rotations += 2;
                         Self::_lr_rotate(n)
                     }
                 } else if delta_height == -2 {
                     if Self::_get_right_height(&n.borrow().right.clone().unwrap())
                         >= Self::_get_left_height(&n.borrow().right.clone().unwrap()) {
+                        *rotations += 1;
                         Self::_left_rotate(n)
                     } else {
+                        *rotations += 2;
                         Self::_rl_rotate(n)
                     }
                 } else {
@@ -266,13 +340,14 @@ impl<T: Ord + Copy + fmt::Debug> AVLTreeNode<T> {
                     Self::_get_left_height(&ret_n),
                     Self::_get_right_height(&ret_n)
                 ) + 1;
+                Self::_update_size(&ret_n);
                 Some(ret_n)
             }
         }
     }
 }
 
-impl<T: Ord + Copy + fmt::Debug> AVLTree<T> {
+impl<T: Ord + Clone + fmt::Debug> AVLTree<T> {
     /// Create a new AVL Tree
     ///
     /// # Example
@@ -283,10 +358,44 @@ impl<T: Ord + Copy + fmt::Debug> AVLTree<T> {
     /// let mut avl: AVLTree<i64> = AVLTree::new();
     /// ```
     pub fn new() -> Self {
-        Self { root: None }
+        Self { root: None, history: None }
+    }
+
+    /// Create a new AVL Tree which records every value passed to
+    /// [insert](#method.insert), see [insertion_history](#method.insertion_history)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    ///
+    /// let mut avl: AVLTree<i32> = AVLTree::with_history();
+    /// ```
+    pub fn with_history() -> Self {
+        Self { root: None, history: Some(vec![]) }
+    }
+
+    /// Build a balanced tree from values in strictly increasing order in O(n),
+    /// taking the middle value as the root of every subtree, so the height is
+    /// the smallest possible.
+    ///
+    /// The slice must be sorted and deduplicated, which is not checked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let avl = AVLTree::from_sorted(&[1, 2, 3, 4, 5, 6, 7]);
+    /// println!("{}", avl.height());  // 3
+    /// ```
+    pub fn from_sorted(values: &[T]) -> Self {
+        Self { root: AVLTreeNode::from_sorted(values), history: None }
     }
 
-    /// Insert a new value to the tree
+    /// Insert a new value to the tree, returning `true` if it was added and
+    /// `false` if an equal value was already present
     ///
     /// # Example
     ///
@@ -294,16 +403,68 @@ impl<T: Ord + Copy + fmt::Debug> AVLTree<T> {
     /// use trees::avltree::AVLTree;
     ///
     /// let mut avl = AVLTree::new();
-    /// avl.insert(1);
+    /// assert!(avl.insert(1));
+    /// assert!(!avl.insert(1));
     /// ```
-    pub fn insert(&mut self, val: T){
+    pub fn insert(&mut self, val: T) -> bool {
+        let len = self.len();
+        self.insert_counting(val);
+        self.len() > len
+    }
+
+    /// Insert `val` and return how many rotations re-balancing took, a
+    /// double rotation counting as two
+    fn insert_counting(&mut self, val: T) -> u64 {
+        if let Some(history) = self.history.as_mut() {
+            history.push(val.clone());
+        }
+        let mut rotations = 0;
         match self.root.take() {
-            Some(r) => self.root = AVLTreeNode::insert(Some(r), val),
+            Some(r) => self.root = AVLTreeNode::insert(Some(r), val, &mut rotations),
             None => self.root = AVLTreeNode::new(val),
         }
+        rotations
     }
 
-    /// Delete a value from the tree
+    /// Insert every value yielded by `iter` as it arrives, calling
+    /// `progress` with the number of items processed so far after each one,
+    /// and return the total number of items processed.
+    ///
+    /// Each item goes through the normal [insert](#method.insert), so the
+    /// tree stays balanced after every step. The input is consumed one item
+    /// at a time and never buffered, so apart from the tree itself memory use
+    /// does not grow with the length of the stream. Duplicates are counted
+    /// as processed even though they do not change the tree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// let mut seen = 0;
+    /// let total = avl.insert_stream((0..100).map(|v| v % 10), |n| seen = n);
+    /// assert_eq!(total, 100);
+    /// assert_eq!(seen, 100);
+    /// assert_eq!(avl.len(), 10);
+    /// ```
+    pub fn insert_stream<I, F>(&mut self, iter: I, mut progress: F) -> usize
+    where
+        I: IntoIterator<Item = T>,
+        F: FnMut(usize),
+    {
+        let mut processed = 0;
+        for val in iter {
+            self.insert(val);
+            processed += 1;
+            progress(processed);
+        }
+        processed
+    }
+
+    /// Delete a value from the tree, returning the stored value which was
+    /// removed or `None` if there was no equal value
     ///
     /// # Example
     ///
@@ -312,23 +473,468 @@ impl<T: Ord + Copy + fmt::Debug> AVLTree<T> {
     ///
     /// let mut avl = AVLTree::new();
     /// avl.insert(1);
-    /// avl.delete(1);
+    /// assert_eq!(avl.delete(1), Some(1));
+    /// assert_eq!(avl.delete(1), None);
     /// ```
-    pub fn delete(&mut self, val:T){
-        match self.root.take() {
-            Some(node) => self.root = AVLTreeNode::delete(Some(node), val),
-            None => return
+    pub fn delete(&mut self, val: T) -> Option<T> {
+        self.delete_counting(val).0
+    }
+
+    /// Delete `val` and return the stored value which was removed, together
+    /// with how many rotations re-balancing took, a double rotation counting
+    /// as two
+    fn delete_counting(&mut self, val: T) -> (Option<T>, u64) {
+        let mut removed = None;
+        let mut rotations = 0;
+        if let Some(node) = self.root.take() {
+            self.root = AVLTreeNode::delete(Some(node), val, &mut removed, &mut rotations);
+        }
+        (removed, rotations)
+    }
+
+    /// Map every value in the tree to the number of rotations deleting it
+    /// would take, a double rotation counting as two. Each delete is done on
+    /// a copy, the tree itself is left untouched.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    ///
+    /// let avl: AVLTree<i32> = vec![2, 1, 3, 4].into_iter().collect();
+    /// let map = avl.delete_rotation_map();
+    /// println!("{:?}", map);  // {1: 1, 2: 0, 3: 0, 4: 0}
+    /// ```
+    pub fn delete_rotation_map(&self) -> BTreeMap<T, u64> {
+        self.to_vec().into_iter().map(|val| {
+            let (_, rotations) = self.clone().delete_counting(val.clone());
+            (val, rotations)
+        }).collect()
+    }
+
+    /// Remove every value from the tree, keeping the tree itself for reuse.
+    /// The nodes are freed one at a time, so clearing a degenerate tree
+    /// can't overflow the stack. A recorded insertion history is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// avl.insert(1);
+    /// avl.insert(2);
+    /// avl.clear();
+    /// assert!(avl.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        base::drop_subtree::<T, _>(self.root.take());
+    }
+
+    /// Consume the tree and return its values in ascending order, like
+    /// [BinarySearchTree::into_sorted_vec](../bstree/struct.BinarySearchTree.html#method.into_sorted_vec)
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.into_iter().collect()
+    }
+
+    /// Swap the two children of every node, like
+    /// [BinarySearchTree::mirror](../bstree/struct.BinarySearchTree.html#method.mirror).
+    /// The heights of the nodes stay as they were.
+    pub fn mirror(&mut self) {
+        base::mirror_subtree::<T, _>(&self.root);
+    }
+
+    /// Remove the smallest value from the tree and return it, or `None` if
+    /// the tree is empty.
+    ///
+    /// The value goes through the normal [delete](#method.delete), so the
+    /// tree stays balanced.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// avl.insert(2);
+    /// avl.insert(1);
+    /// assert_eq!(avl.pop_min(), Some(1));
+    /// assert_eq!(avl.pop_min(), Some(2));
+    /// assert_eq!(avl.pop_min(), None);
+    /// ```
+    pub fn pop_min(&mut self) -> Option<T> {
+        let min = self.min()?;
+        self.delete(min.clone());
+        Some(min)
+    }
+
+    /// Insert `val` and pop the smallest value once the tree holds more
+    /// than `window`, like
+    /// [BinarySearchTree::insert_windowed](../bstree/struct.BinarySearchTree.html#method.insert_windowed)
+    pub fn insert_windowed(&mut self, val: T, window: usize) -> Option<T> {
+        self.insert(val);
+        if self.len() > window {
+            self.pop_min()
+        } else {
+            None
+        }
+    }
+
+    /// Remove the largest value from the tree and return it, or `None` if
+    /// the tree is empty.
+    ///
+    /// Like [pop_min](#method.pop_min), this goes through the normal
+    /// [delete](#method.delete).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// avl.insert(1);
+    /// avl.insert(2);
+    /// assert_eq!(avl.pop_max(), Some(2));
+    /// assert_eq!(avl.pop_max(), Some(1));
+    /// assert_eq!(avl.pop_max(), None);
+    /// ```
+    pub fn pop_max(&mut self) -> Option<T> {
+        let max = self.max()?;
+        self.delete(max.clone());
+        Some(max)
+    }
+
+    /// Move every value not less than `key` into a new tree and return it,
+    /// keeping the smaller values. Both halves are rebuilt balanced from
+    /// their sorted values. The returned tree doesn't record any history.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut avl: AVLTree<i32> = (1..=6).collect();
+    /// let upper = avl.split_off(4);
+    /// println!("{:?}", avl.to_vec());  // [1, 2, 3]
+    /// println!("{:?}", upper.to_vec());  // [4, 5, 6]
+    /// ```
+    pub fn split_off(&mut self, key: T) -> Self {
+        let mut lower = self.to_vec();
+        let upper = lower.split_off(lower.partition_point(|v| *v < key));
+        self.clear();
+        self.root = AVLTreeNode::from_sorted(&lower);
+        Self::from_sorted(&upper)
+    }
+
+    /// Return the values passed to [insert](#method.insert) in call order,
+    /// or `None` if the tree was not created by
+    /// [with_history](#method.with_history).
+    ///
+    /// Every call is recorded, including values which were already in the
+    /// tree and therefore did not change it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    ///
+    /// let mut avl = AVLTree::with_history();
+    /// avl.insert(2);
+    /// avl.insert(1);
+    /// avl.insert(2);
+    /// assert_eq!(avl.insertion_history(), Some(&[2, 1, 2][..]));
+    /// assert_eq!(AVLTree::<i32>::new().insertion_history(), None);
+    /// ```
+    pub fn insertion_history(&self) -> Option<&[T]> {
+        self.history.as_deref()
+    }
+
+    /// Delete every value for which `pred` returns `true`, and return how
+    /// many values were deleted
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// for v in 0..10 {
+    ///     avl.insert(v);
+    /// }
+    /// assert_eq!(avl.remove_where(|v| v % 3 == 0), 4);
+    /// assert_eq!(avl.to_vec(), vec![1, 2, 4, 5, 7, 8]);
+    /// ```
+    pub fn remove_where<F: Fn(&T) -> bool>(&mut self, pred: F) -> usize {
+        let values: Vec<T> = self.iter().collect();
+        let survivors: Vec<T> = values.iter().filter(|v| !pred(v)).cloned().collect();
+        let removed = values.len() - survivors.len();
+        if removed > 0 {
+            self.clear();
+            self.root = AVLTreeNode::from_sorted(&survivors);
+        }
+        removed
+    }
+
+    /// Apply `ops` in order and return whether the tree was still
+    /// [valid](../base/trait.QueryableTree.html#method.is_valid) after every
+    /// step. Stops at the first step which leaves the tree invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::TreeOp;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// assert!(avl.apply_ops(&[TreeOp::Insert(2), TreeOp::Insert(1), TreeOp::Delete(2)]));
+    /// ```
+    pub fn apply_ops(&mut self, ops: &[TreeOp<T>]) -> bool {
+        for op in ops {
+            match op {
+                TreeOp::Insert(val) => { self.insert(val.clone()); }
+                TreeOp::Delete(val) => { self.delete(val.clone()); }
+            }
+            if !self.is_valid() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Return true if the heights of the two subtrees of every node differ
+    /// by at most one, and the height cached in every node is correct. The
+    /// whole tree is checked in a single pass.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// for v in 0..100 {
+    ///     avl.insert(v);
+    /// }
+    /// for v in (0..100).step_by(3) {
+    ///     avl.delete(v);
+    /// }
+    /// assert!(avl.is_balanced());
+    /// ```
+    pub fn is_balanced(&self) -> bool {
+        self.root.as_ref().is_none_or(|root| root.borrow().checked_height().is_some())
+    }
+}
+
+impl<T: Integer> AVLTree<T> {
+    /// Insert every integer from `lo` to `hi`, both included. Nothing is
+    /// inserted if `lo > hi`.
+    ///
+    /// The range is merged with the values already in the tree and the whole
+    /// tree is rebuilt height-balanced, which takes `O(n + k)` time for `n`
+    /// values and `k` new ones with a single rebuild instead of `k`
+    /// rebalancing inserts.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// avl.insert(0);
+    /// avl.insert_range(3, 5);
+    /// assert_eq!(avl.to_vec(), vec![0, 3, 4, 5]);
+    /// ```
+    pub fn insert_range(&mut self, lo: T, hi: T) {
+        let block = num::inclusive_range(lo, hi);
+        if block.is_empty() {
+            return;
         }
+        if let Some(history) = self.history.as_mut() {
+            history.extend(block.iter().copied());
+        }
+        let values = base::merge_sorted(self.iter(), block, T::cmp);
+        self.clear();
+        self.root = AVLTreeNode::from_sorted(&values);
+    }
+}
+
+/// Return true if inserting the values of `order` one after another into
+/// an empty [AVLTree](struct.AVLTree.html) never triggers a rotation, i.e.
+/// every prefix of the order already builds a balanced tree by itself.
+///
+/// # Example
+///
+/// ```
+/// use trees::avltree::is_rotation_free_order;
+///
+/// assert!(is_rotation_free_order(&[2, 1, 3]));
+/// assert!(!is_rotation_free_order(&[1, 2, 3]));
+/// ```
+pub fn is_rotation_free_order<T: Ord + Clone + fmt::Debug>(order: &[T]) -> bool {
+    let mut avl = AVLTree::new();
+    order.iter().all(|val| avl.insert_counting(val.clone()) == 0)
+}
+
+/// Content equality with a [BinarySearchTree](../bstree/struct.BinarySearchTree.html)
+impl<T: Ord + Clone + fmt::Debug> PartialEq<crate::bstree::BinarySearchTree<T>> for AVLTree<T> {
+    fn eq(&self, other: &crate::bstree::BinarySearchTree<T>) -> bool {
+        base::same_values(self, other)
+    }
+}
+
+/// Trees are equal when they hold the same values, even if they were
+/// balanced into different shapes, see
+/// [BinarySearchTree](../bstree/struct.BinarySearchTree.html).
+///
+/// # Example
+///
+/// ```
+/// use trees::prelude::*;
+///
+/// let a: AVLTree<i32> = vec![1, 2, 3].into_iter().collect();
+/// let b: AVLTree<i32> = vec![3, 2, 1].into_iter().collect();
+/// let avl: AVLTree<i32> = vec![2, 1, 3].into_iter().collect();
+/// assert!(a == b);
+/// assert!(a == avl);
+/// ```
+impl<T: Ord + Clone + fmt::Debug> PartialEq<AVLTree<T>> for AVLTree<T> {
+    fn eq(&self, other: &AVLTree<T>) -> bool {
+        base::same_values(self, other)
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> Eq for AVLTree<T> {}
+
+/// Content equality with a [RedBlackTree](../rbtree/struct.RedBlackTree.html)
+impl<T: Ord + Clone + fmt::Debug> PartialEq<crate::rbtree::RedBlackTree<T>> for AVLTree<T> {
+    fn eq(&self, other: &crate::rbtree::RedBlackTree<T>) -> bool {
+        base::same_values(self, other)
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> Default for AVLTree<T> {
+    /// Create an empty tree, same as [new](#method.new)
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    fn _is_balanced(&self) -> bool {
-        match self.get_root() {
-            Some(node) => node.borrow()._is_balanced(),
-            None => true
+impl<T: Ord + Clone + fmt::Debug> Clone for AVLTree<T> {
+    /// Copy the tree into freshly allocated nodes, so changing the copy
+    /// leaves the original alone
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// avl.insert(1);
+    /// let mut copy = avl.clone();
+    /// copy.insert(2);
+    /// assert_eq!(avl.to_vec(), vec![1]);
+    /// ```
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.as_ref().map(AVLTreeNode::deep_clone),
+            history: self.history.clone(),
         }
     }
 }
 
+impl<T: Ord + Clone + fmt::Debug> IntoIterator for AVLTree<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T, AVLTreeNode<T>>;
+
+    /// Consume the tree and iterate its values in ascending order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// avl.insert(2);
+    /// avl.insert(1);
+    /// let values: Vec<_> = avl.into_iter().collect();
+    /// assert_eq!(values, vec![1, 2]);
+    /// ```
+    fn into_iter(mut self) -> Self::IntoIter {
+        IntoIter::new(self.root.take())
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> FromIterator<T> for AVLTree<T> {
+    /// Create a tree by inserting the values one by one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let avl: AVLTree<i32> = vec![5, 3, 8].into_iter().collect();
+    /// assert_eq!(avl.to_vec(), vec![3, 5, 8]);
+    /// ```
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> Extend<T> for AVLTree<T> {
+    /// Insert the values one by one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut avl = AVLTree::new();
+    /// avl.insert(4);
+    /// avl.extend(vec![1, 2, 3]);
+    /// assert_eq!(avl.to_vec(), vec![1, 2, 3, 4]);
+    /// ```
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for val in iter {
+            self.insert(val);
+        }
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> fmt::Display for AVLTree<T> {
+    /// Format the values in ascending order within braces, like a
+    /// [BinarySearchTree](../bstree/struct.BinarySearchTree.html)
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        base::fmt_values(self.iter(), f)
+    }
+}
+
+/// Serialized as the sequence of values in inorder
+#[cfg(feature = "serde")]
+impl<T: Ord + Clone + fmt::Debug + serde::Serialize> serde::Serialize for AVLTree<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// Deserialized from a sequence of values, which is rebuilt into a
+/// height-balanced tree
+#[cfg(feature = "serde")]
+impl<'de, T: Ord + Clone + fmt::Debug + serde::Deserialize<'de>> serde::Deserialize<'de> for AVLTree<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut values = Vec::<T>::deserialize(deserializer)?;
+        values.sort();
+        values.dedup();
+        Ok(Self { root: AVLTreeNode::from_sorted(&values), history: None })
+    }
+}
 
 #[cfg(test)]
 mod test {
@@ -366,11 +972,11 @@ mod test {
         let tree_size = 1000;
         for v in 0..tree_size {
             tree.insert(v);
-            assert!(tree._is_balanced());
+            assert!(tree.is_balanced());
         }
         for (i, v) in (0..tree_size).enumerate() {
             tree.delete(v);
-            assert!(tree._is_balanced());
+            assert!(tree.is_balanced());
             assert_eq!(tree.len(), tree_size - i - 1);
         }
     }
@@ -381,11 +987,11 @@ mod test {
         let tree_size = 1000;
         for v in (0..tree_size).rev() {
             tree.insert(v);
-            assert!(tree._is_balanced());
+            assert!(tree.is_balanced());
         }
         for (i, v) in (0..tree_size).rev().enumerate() {
             tree.delete(v);
-            assert!(tree._is_balanced());
+            assert!(tree.is_balanced());
             assert_eq!(tree.len(), tree_size - i - 1);
         }
     }
@@ -401,36 +1007,229 @@ mod test {
 
         for v in x.iter() {
             tree.insert(*v);
-            assert!(tree._is_balanced());
+            assert!(tree.is_balanced());
         }
         assert_eq!(tree.len(), tree_size);
-        assert!(tree._is_balanced());
+        assert!(tree.is_balanced());
         for (i, v) in x.iter().enumerate() {
             tree.delete(*v);
             assert_eq!(tree.len(), tree_size - i - 1);
-            assert!(tree._is_balanced());
+            assert!(tree.is_balanced());
         }
     }
 
+    #[test]
+    fn range_sum_avl() {
+        let mut tree: AVLTree<i64> = (1..=3).collect();
+        let root = tree.root.clone().unwrap();
+        assert_eq!(root.borrow().sum.get(), None);
+        assert_eq!(tree.range_sum(1, 3), 6);
+        assert_eq!(root.borrow().sum.get(), None);
+        assert_eq!(tree.prefix_sum_up_to(3), 6);
+        assert_eq!(root.borrow().left.as_ref().unwrap().borrow().sum.get(), Some(1));
+        assert_eq!(tree.root.as_ref().unwrap().borrow().sum(), 6);
+        assert_eq!(root.borrow().sum.get(), Some(6));
+
+        // 4 and 5 rotate 3 below 4, the sums on the way are recomputed
+        tree.insert(4);
+        assert_eq!(root.borrow().sum.get(), None);
+        tree.insert(5);
+        assert_eq!(tree.root.as_ref().unwrap().borrow().sum(), 15);
+        assert_eq!(tree.range_sum(3, 5), 12);
+        assert_eq!(tree.range_sum(2, 4), 9);
+
+        tree.delete(1);
+        tree.delete(2);
+        assert!(tree.is_balanced());
+        assert_eq!(tree.range_sum(0, 100), 12);
+        assert_eq!(tree.range_sum(4, 4), 4);
+        assert_eq!(tree.range_sum(6, 100), 0);
+    }
+
+    #[test]
+    fn is_balanced_avl() {
+        let mut tree = AVLTree::new();
+        assert!(tree.is_balanced());
+        for v in 0..200 {
+            tree.insert(v);
+            assert!(tree.is_balanced());
+        }
+        for v in (0..200).step_by(3) {
+            tree.delete(v);
+            assert!(tree.is_balanced());
+        }
+
+        // a wrong cached height is caught even if the shape is balanced
+        let root = tree.root.clone().unwrap();
+        let height = root.borrow().height;
+        root.borrow_mut().height = height + 1;
+        assert!(!tree.is_balanced());
+        root.borrow_mut().height = height;
+        assert!(tree.is_balanced());
+
+        // cutting off the left subtree unbalances the root
+        let left = root.borrow_mut().left.take();
+        assert!(!tree.is_balanced());
+        root.borrow_mut().left = left;
+        assert!(tree.is_balanced());
+    }
+
+    #[test]
+    fn bst_into_avl() {
+        use crate::bstree::BinarySearchTree;
+
+        let mut bst = BinarySearchTree::new();
+        for v in 0..500 {
+            bst.insert(v);
+        }
+        let avl = bst.into_avl();
+        assert!(avl.is_balanced());
+        assert!(avl.is_valid());
+        assert_eq!(avl.to_vec(), (0..500).collect::<Vec<_>>());
+        assert_eq!(avl.height(), 9);
+
+        // a comparator order is not the order the AVL tree searches in
+        let mut by_abs = BinarySearchTree::with_comparator(|a: &i32, b: &i32| a.abs().cmp(&b.abs()));
+        for &v in [-3, 1, -2, 5, 4].iter() {
+            by_abs.insert(v);
+        }
+        let avl = by_abs.into_avl();
+        assert!(avl.is_balanced());
+        assert_eq!(avl.to_vec(), vec![-3, -2, 1, 4, 5]);
+        assert!(avl.contains(-2));
+
+        assert!(BinarySearchTree::<i32>::new().into_avl().is_empty());
+    }
+
+    #[test]
+    fn rotation_free_order_avl() {
+        // each value splits the remaining range in half
+        assert!(is_rotation_free_order(&[3, 1, 5, 0, 2, 4, 6]));
+        assert!(!is_rotation_free_order(&[0, 1, 2, 3, 4, 5, 6]));
+        // 5 has no left child, so the chain 6, 7 below it needs a rotation
+        assert!(!is_rotation_free_order(&[3, 1, 5, 0, 6, 2, 7, 8, 9]));
+        assert!(is_rotation_free_order::<i32>(&[]));
+        assert!(is_rotation_free_order(&[1, 1, 1]));
+    }
+
+    #[test]
+    fn delete_rotation_map_avl() {
+        use std::collections::BTreeMap;
+
+        // 1 is the only value whose delete unbalances the root, one rotation
+        let tree: AVLTree<i32> = vec![2, 1, 3, 4].into_iter().collect();
+        let expected: BTreeMap<i32, u64> = vec![(1, 1), (2, 0), (3, 0), (4, 0)].into_iter().collect();
+        assert_eq!(tree.delete_rotation_map(), expected);
+        assert_eq!(tree.to_vec(), vec![1, 2, 3, 4]);
+
+        // deleting 1 needs a right-left double rotation
+        let tree: AVLTree<i32> = vec![3, 1, 5, 4].into_iter().collect();
+        let expected: BTreeMap<i32, u64> = vec![(1, 2), (3, 0), (4, 0), (5, 0)].into_iter().collect();
+        assert_eq!(tree.delete_rotation_map(), expected);
+
+        assert!(AVLTree::<i32>::new().delete_rotation_map().is_empty());
+    }
+
+    #[test]
+    fn remove_where_avl() {
+        let mut tree = AVLTree::new();
+        assert_eq!(tree.remove_where(|_| true), 0);
+        for v in 0..100 {
+            tree.insert(v);
+        }
+        assert_eq!(tree.remove_where(|v| v % 2 == 1), 50);
+        assert_eq!(tree.to_vec(), (0..100).step_by(2).collect::<Vec<_>>());
+        assert!(tree.is_balanced());
+        assert_eq!(tree.height(), 6);
+        assert_eq!(tree.remove_where(|v| v % 2 == 1), 0);
+        assert_eq!(tree.remove_where(|_| true), 50);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn insert_stream_avl() {
+        use std::cell::Cell;
+
+        let mut tree = AVLTree::new();
+        let pulled = Cell::new(0);
+        let stream = (0..1000).rev().filter(|v| v % 3 != 0).inspect(|_| pulled.set(pulled.get() + 1));
+        let mut calls = 0;
+        let total = tree.insert_stream(stream, |n| {
+            // every item is inserted before the next one is pulled
+            assert_eq!(n, pulled.get());
+            calls += 1;
+        });
+        assert_eq!(total, 666);
+        assert_eq!(calls, 666);
+        assert_eq!(tree.len(), 666);
+        assert!(tree.to_vec().iter().copied().eq((0..1000).filter(|v| v % 3 != 0)));
+        assert!(tree.is_balanced());
+
+        assert_eq!(tree.insert_stream(std::iter::empty(), |_| panic!()), 0);
+        assert_eq!(tree.len(), 666);
+    }
+
+    #[test]
+    fn extend_avl() {
+        let mut tree: AVLTree<i32> = (0..10).collect();
+        tree.extend(10..200);
+        assert!(tree.is_balanced());
+        tree.extend((-100..0).rev());
+        assert!(tree.is_balanced());
+        tree.extend(vec![5, 50, 500]);
+        assert!(tree.is_balanced());
+        assert_eq!(tree.len(), 301);
+        assert!(tree.iter().eq((-100..200).chain(Some(500))));
+    }
+
     #[test]
     fn test_debug_delete_avl() {
         let mut tree = AVLTree::new();
 
         for x in vec![7, 2, 4, 0, 9, 3, 5, 8, 6, 1] {
             tree.insert(x);
-            assert!(tree._is_balanced());
+            assert!(tree.is_balanced());
             tree.print_inorder();
         }
         assert_eq!(tree.len(), 10);
-        assert!(tree._is_balanced());
+        assert!(tree.is_balanced());
         tree.print_inorder();
 
         for (i, v) in (0..10).enumerate() {
             tree.delete(v);
             assert_eq!(tree.len(), 10 - i - 1);
-            assert!(tree._is_balanced());
+            assert!(tree.is_balanced());
         }
     }
-}
 
- 
\ No newline at end of file
+    /// Return a weak link to every node of the tree
+    fn weak_nodes(tree: &AVLTree<i32>) -> Vec<std::rc::Weak<RefCell<AVLTreeNode<i32>>>> {
+        let mut nodes = vec![];
+        let mut stack: Vec<_> = tree.root.clone().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            stack.extend(node.borrow().left.clone());
+            stack.extend(node.borrow().right.clone());
+            nodes.push(Rc::downgrade(&node));
+        }
+        nodes
+    }
+
+    #[test]
+    fn nodes_freed_on_drop() {
+        let tree: AVLTree<i32> = (0..1000).collect();
+        let nodes = weak_nodes(&tree);
+        assert_eq!(nodes.len(), 1000);
+        drop(tree);
+        assert!(nodes.iter().all(|node| node.upgrade().is_none()));
+
+        // the nodes a partly consumed iterator has not yielded yet are freed
+        // when it is dropped
+        let tree: AVLTree<i32> = (0..1000).collect();
+        let nodes = weak_nodes(&tree);
+        let mut iter = tree.into_iter();
+        assert_eq!(iter.by_ref().take(10).collect::<Vec<_>>(), (0..10).collect::<Vec<_>>());
+        assert_eq!(nodes.iter().filter(|node| node.upgrade().is_some()).count(), 990);
+        drop(iter);
+        assert!(nodes.iter().all(|node| node.upgrade().is_none()));
+    }
+}
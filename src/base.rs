@@ -6,19 +6,324 @@
 //! use trees::base::{QueryableTreeNode, QueryableTree};
 //! ```
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
-use std::cmp::max;
+use std::cmp::{max, Ordering};
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::Peekable;
+use std::marker::PhantomData;
+
+use crate::num::Integer;
+
+/// A comparator a tree orders its values by instead of `Ord`, see
+/// [QueryableTree.comparator](trait.QueryableTree.html#method.comparator)
+pub type Comparator<T> = Rc<dyn Fn(&T, &T) -> Ordering>;
+
+/// Return `cmp` as a comparison function, or `Ord` if there is none
+pub(crate) fn ordering<T: Ord>(cmp: &Option<Comparator<T>>) -> &dyn Fn(&T, &T) -> Ordering {
+    match cmp.as_ref() {
+        Some(cmp) => cmp.as_ref(),
+        None => &T::cmp,
+    }
+}
+
+/// A single modification of a tree, as applied by `apply_ops` on
+/// [BinarySearchTree](../bstree/struct.BinarySearchTree.html#method.apply_ops),
+/// [AVLTree](../avltree/struct.AVLTree.html#method.apply_ops) and
+/// [RedBlackTree](../rbtree/struct.RedBlackTree.html#method.apply_ops)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TreeOp<T> {
+    /// Insert the value
+    Insert(T),
+    /// Delete the value
+    Delete(T),
+}
+
+/// Reason why a tree could not be built from the given values, as returned
+/// by [BinarySearchTree::try_from_sorted](../bstree/struct.BinarySearchTree.html#method.try_from_sorted)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError<T> {
+    /// The value at `index` is smaller than the one before it
+    NotSorted { index: usize },
+    /// The value appears more than once
+    Duplicate { value: T },
+}
+
+impl<T: fmt::Debug> fmt::Display for BuildError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BuildError::NotSorted { index } => write!(f, "value at index {} is out of order", index),
+            BuildError::Duplicate { value } => write!(f, "duplicate value {:?}", value),
+        }
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for BuildError<T> {}
+
+/// [FNV-1a](https://en.wikipedia.org/wiki/Fowler%E2%80%93Noll%E2%80%93Vo_hash_function)
+/// hasher with fixed parameters, which gives the same hash in every run
+/// unlike the randomly seeded `HashMap` hasher
+struct FnvHasher(u64);
+
+impl FnvHasher {
+    fn new() -> Self {
+        FnvHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Return true if the shape of `a` is the mirror image of the shape of `b`,
+/// ignoring the values
+fn is_mirror_shape<T, QTN>(a: &Option<Rc<RefCell<QTN>>>, b: &Option<Rc<RefCell<QTN>>>) -> bool
+where
+    T: Ord + Clone + fmt::Debug,
+    QTN: QueryableTreeNode<T>,
+{
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => {
+            let (a, b) = (a.borrow(), b.borrow());
+            is_mirror_shape(a.get_left(), b.get_right())
+                && is_mirror_shape(a.get_right(), b.get_left())
+        }
+        _ => false,
+    }
+}
+
+/// Return true if `a` and `b` have the same shape, ignoring the values
+fn is_same_shape<T, A, B>(a: &Option<Rc<RefCell<A>>>, b: &Option<Rc<RefCell<B>>>) -> bool
+where
+    T: Ord + Clone + fmt::Debug,
+    A: QueryableTreeNode<T>,
+    B: QueryableTreeNode<T>,
+{
+    match (a, b) {
+        (None, None) => true,
+        (Some(a), Some(b)) => {
+            let (a, b) = (a.borrow(), b.borrow());
+            is_same_shape(a.get_left(), b.get_left())
+                && is_same_shape(a.get_right(), b.get_right())
+        }
+        _ => false,
+    }
+}
+
+/// Return the sum of the values not greater than `value` by `cmp` below
+/// `node`
+fn sum_at_most<T, QTN>(mut node: Option<Rc<RefCell<QTN>>>, value: T, cmp: &dyn Fn(&T, &T) -> Ordering) -> T
+where
+    T: Integer,
+    QTN: QueryableTreeNode<T>,
+{
+    let mut total = T::ZERO;
+    while let Some(n) = node {
+        let data = n.borrow().get_data();
+        if cmp(&data, &value) != Ordering::Greater {
+            let left_sum = n.borrow().get_left().as_ref().map(
+                |l| l.borrow().sum()
+            ).unwrap_or(T::ZERO);
+            total = total + left_sum + data;
+            node = n.borrow().get_right().clone();
+        } else {
+            node = n.borrow().get_left().clone();
+        }
+    }
+    total
+}
+
+/// Return the sum of the values not less than `value` by `cmp` below `node`
+fn sum_at_least<T, QTN>(mut node: Option<Rc<RefCell<QTN>>>, value: T, cmp: &dyn Fn(&T, &T) -> Ordering) -> T
+where
+    T: Integer,
+    QTN: QueryableTreeNode<T>,
+{
+    let mut total = T::ZERO;
+    while let Some(n) = node {
+        let data = n.borrow().get_data();
+        if cmp(&data, &value) != Ordering::Less {
+            let right_sum = n.borrow().get_right().as_ref().map(
+                |r| r.borrow().sum()
+            ).unwrap_or(T::ZERO);
+            total = total + right_sum + data;
+            node = n.borrow().get_left().clone();
+        } else {
+            node = n.borrow().get_right().clone();
+        }
+    }
+    total
+}
+
+/// Drop the nodes below and including `root` one at a time, detaching the
+/// children of each node first, so that freeing a very deep tree does not
+/// recurse once per level
+pub(crate) fn drop_subtree<T, QTN>(root: Option<Rc<RefCell<QTN>>>)
+where
+    T: Ord + Clone + fmt::Debug,
+    QTN: QueryableTreeNode<T>,
+{
+    let mut stack: Vec<_> = root.into_iter().collect();
+    while let Some(node) = stack.pop() {
+        let mut node = node.borrow_mut();
+        stack.extend(node.get_left_mut().take());
+        stack.extend(node.get_right_mut().take());
+    }
+}
+
+/// Swap the two children of every node below and including `root`, turning
+/// the subtree into its mirror image
+pub(crate) fn mirror_subtree<T, QTN>(root: &Option<Rc<RefCell<QTN>>>)
+where
+    T: Ord + Clone + fmt::Debug,
+    QTN: QueryableTreeNode<T>,
+{
+    let mut stack: Vec<_> = root.iter().cloned().collect();
+    while let Some(node) = stack.pop() {
+        let mut node = node.borrow_mut();
+        let left = node.get_left_mut().take();
+        let right = node.get_right_mut().take();
+        stack.extend(left.iter().cloned());
+        stack.extend(right.iter().cloned());
+        *node.get_left_mut() = right;
+        *node.get_right_mut() = left;
+    }
+}
+
+/// Copy the subtree rooted at `root` into freshly allocated nodes, level by
+/// level without recursion. `copy` makes a copy of a single node without
+/// its children, given the copy of its parent, `None` for the root.
+pub(crate) fn clone_subtree<T, QTN>(
+    root: &Rc<RefCell<QTN>>,
+    copy: impl Fn(&QTN, Option<&Rc<RefCell<QTN>>>) -> QTN,
+) -> Rc<RefCell<QTN>>
+where
+    T: Ord + Clone + fmt::Debug,
+    QTN: QueryableTreeNode<T>,
+{
+    let root_copy = Rc::new(RefCell::new(copy(&root.borrow(), None)));
+    let mut stack = vec![(root.clone(), root_copy.clone())];
+    while let Some((node, node_copy)) = stack.pop() {
+        let node = node.borrow();
+        if let Some(left) = node.get_left() {
+            let left_copy = Rc::new(RefCell::new(copy(&left.borrow(), Some(&node_copy))));
+            *node_copy.borrow_mut().get_left_mut() = Some(left_copy.clone());
+            stack.push((left.clone(), left_copy));
+        }
+        if let Some(right) = node.get_right() {
+            let right_copy = Rc::new(RefCell::new(copy(&right.borrow(), Some(&node_copy))));
+            *node_copy.borrow_mut().get_right_mut() = Some(right_copy.clone());
+            stack.push((right.clone(), right_copy));
+        }
+    }
+    root_copy
+}
+
+/// Merge two runs of values, each ascending by `cmp` without duplicates,
+/// into one such run in linear time. Of two equal values the one from
+/// `left` is kept.
+pub(crate) fn merge_sorted<T>(
+    left: impl IntoIterator<Item = T>,
+    right: impl IntoIterator<Item = T>,
+    cmp: impl Fn(&T, &T) -> Ordering,
+) -> Vec<T> {
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    let mut merged = vec![];
+    loop {
+        let order = match (left.peek(), right.peek()) {
+            (Some(l), Some(r)) => cmp(l, r),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => return merged,
+        };
+        match order {
+            Ordering::Less => merged.extend(left.next()),
+            Ordering::Greater => merged.extend(right.next()),
+            Ordering::Equal => {
+                merged.extend(left.next());
+                right.next();
+            }
+        }
+    }
+}
+
+/// Return true if both trees hold the same values, whatever their shapes.
+/// This backs the `PartialEq` impls between all the trees.
+pub(crate) fn same_values<T, A, AN, B, BN>(a: &A, b: &B) -> bool
+where
+    T: Ord + Clone + fmt::Debug,
+    AN: QueryableTreeNode<T>,
+    BN: QueryableTreeNode<T>,
+    A: QueryableTree<T, AN>,
+    B: QueryableTree<T, BN>,
+{
+    if a.len() != b.len() {
+        return false;
+    }
+    if a.comparator().is_none() && b.comparator().is_none() {
+        return a.iter().eq(b.iter());
+    }
+    values_by_ord(a) == values_by_ord(b)
+}
+
+/// Return the values of `tree` in ascending order of `Ord`, which is their
+/// inorder unless the tree has a comparator
+fn values_by_ord<T, QTN, Tree>(tree: &Tree) -> Vec<T>
+where
+    T: Ord + Clone + fmt::Debug,
+    QTN: QueryableTreeNode<T>,
+    Tree: QueryableTree<T, QTN> + ?Sized,
+{
+    let mut values = tree.to_vec();
+    if tree.comparator().is_some() {
+        values.sort();
+    }
+    values
+}
+
+/// Write `values` within braces, separated by commas, e.g. `{0, 1, 3}`, each
+/// in its `Debug` format. This backs the `Display` impls of all the trees.
+pub(crate) fn fmt_values<T: fmt::Debug>(
+    values: impl IntoIterator<Item = T>,
+    f: &mut fmt::Formatter,
+) -> fmt::Result {
+    write!(f, "{{")?;
+    for (i, v) in values.into_iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{:?}", v)?;
+    }
+    write!(f, "}}")
+}
 
 /// Provide query functions for nodes
-pub trait QueryableTreeNode<T: Ord + Copy + fmt::Debug> {
+pub trait QueryableTreeNode<T: Ord + Clone + fmt::Debug> {
     /// Get left child node
     fn get_left(&self) -> &Option<Rc<RefCell<Self>>>;
 
     /// Get right child node
     fn get_right(&self) -> &Option<Rc<RefCell<Self>>>;
 
+    /// Get mutable left child link
+    fn get_left_mut(&mut self) -> &mut Option<Rc<RefCell<Self>>>;
+
+    /// Get mutable right child link
+    fn get_right_mut(&mut self) -> &mut Option<Rc<RefCell<Self>>>;
+
     /// Get data from current node
     fn get_data(&self) -> T;
 
@@ -34,6 +339,21 @@ pub trait QueryableTreeNode<T: Ord + Copy + fmt::Debug> {
         max(left_height, right_height) + 1
     }
 
+    /// Return the height of current node like [height](#method.height),
+    /// raising `best` to the number of edges of the longest path through
+    /// the current node if that is longer, which will be called by
+    /// [QueryableTree.diameter](trait.QueryableTree.html#method.diameter)
+    fn height_and_diameter(&self, best: &mut usize) -> usize {
+        let left_height = self.get_left().as_ref().map(
+            |l| l.borrow().height_and_diameter(best)
+        ).unwrap_or(0);
+        let right_height = self.get_right().as_ref().map(
+            |r| r.borrow().height_and_diameter(best)
+        ).unwrap_or(0);
+        *best = max(*best, left_height + right_height);
+        max(left_height, right_height) + 1
+    }
+
     /// Return the number of leaves, which will be called by
     /// [QueryableTree.count_leaves](trait.QueryableTree.html#method.count_leaves)
     fn count_leaves(&self) -> usize {
@@ -51,6 +371,51 @@ pub trait QueryableTreeNode<T: Ord + Copy + fmt::Debug> {
         }
     }
 
+    /// Return the number of nodes `depth` levels below the current node,
+    /// which will be called by
+    /// [QueryableTree.count_at_depth](trait.QueryableTree.html#method.count_at_depth)
+    fn count_at_depth(&self, depth: usize) -> usize {
+        if depth == 0 {
+            return 1;
+        }
+        let left = self.get_left().as_ref().map(
+            |l| l.borrow().count_at_depth(depth - 1)
+        ).unwrap_or(0);
+        let right = self.get_right().as_ref().map(
+            |r| r.borrow().count_at_depth(depth - 1)
+        ).unwrap_or(0);
+        left + right
+    }
+
+    /// Return true if every value below and including the current node lies
+    /// strictly between `lo` and `hi` by `cmp` when given and the subtrees
+    /// are in order as well, which will be called by
+    /// [QueryableTree.is_valid_bst](trait.QueryableTree.html#method.is_valid_bst)
+    fn is_within(&self, lo: Option<&T>, hi: Option<&T>, cmp: &dyn Fn(&T, &T) -> Ordering) -> bool {
+        let data = self.get_data();
+        if lo.is_some_and(|lo| cmp(&data, lo) != Ordering::Greater)
+            || hi.is_some_and(|hi| cmp(&data, hi) != Ordering::Less) {
+            return false;
+        }
+        self.get_left().as_ref().is_none_or(|l| l.borrow().is_within(lo, Some(&data), cmp))
+            && self.get_right().as_ref().is_none_or(|r| r.borrow().is_within(Some(&data), hi, cmp))
+    }
+
+    /// Count the leaves below and including the current node by their depth,
+    /// where the current node is at `depth`, which will be called by
+    /// [QueryableTree.leaf_depth_histogram](trait.QueryableTree.html#method.leaf_depth_histogram)
+    fn collect_leaf_depths(&self, depth: usize, histogram: &mut BTreeMap<usize, usize>) {
+        if self.get_left().is_none() && self.get_right().is_none() {
+            *histogram.entry(depth).or_insert(0) += 1;
+        }
+        if let Some(l) = self.get_left() {
+            l.borrow().collect_leaf_depths(depth + 1, histogram);
+        }
+        if let Some(r) = self.get_right() {
+            r.borrow().collect_leaf_depths(depth + 1, histogram);
+        }
+    }
+
     /// Print nodes [inorder](https://en.wikipedia.org/wiki/Tree_traversal#In-order_(LNR))
     /// , which will be called by
     /// [QueryableTree.print_inorder](trait.QueryableTree.html#method.print_inorder)
@@ -64,6 +429,62 @@ pub trait QueryableTreeNode<T: Ord + Copy + fmt::Debug> {
         }
     }
 
+    /// Push the values of the current node and its successors to `container`
+    /// [inorder](https://en.wikipedia.org/wiki/Tree_traversal#In-order_(LNR)),
+    /// which will be called by
+    /// [QueryableTree.to_vec](trait.QueryableTree.html#method.to_vec)
+    fn collect_inorder(&self, container: &mut Vec<T>) {
+        if let Some(l) = self.get_left() {
+            l.borrow().collect_inorder(container);
+        }
+        container.push(self.get_data());
+        if let Some(r) = self.get_right() {
+            r.borrow().collect_inorder(container);
+        }
+    }
+
+    /// Push the values of the current node and its successors to `container`
+    /// [preorder](https://en.wikipedia.org/wiki/Tree_traversal#Pre-order_(NLR)),
+    /// which will be called by
+    /// [QueryableTree.preorder](trait.QueryableTree.html#method.preorder)
+    fn collect_preorder(&self, container: &mut Vec<T>) {
+        container.push(self.get_data());
+        if let Some(l) = self.get_left() {
+            l.borrow().collect_preorder(container);
+        }
+        if let Some(r) = self.get_right() {
+            r.borrow().collect_preorder(container);
+        }
+    }
+
+    /// Push a `(value, left child, right child)` triple for the current node
+    /// and its successors to `container` preorder, which will be called by
+    /// [QueryableTree.to_triples](trait.QueryableTree.html#method.to_triples)
+    fn collect_triples(&self, container: &mut Vec<(T, Option<T>, Option<T>)>) {
+        let child = |link: &Option<Rc<RefCell<Self>>>| link.as_ref().map(|n| n.borrow().get_data());
+        container.push((self.get_data(), child(self.get_left()), child(self.get_right())));
+        if let Some(l) = self.get_left() {
+            l.borrow().collect_triples(container);
+        }
+        if let Some(r) = self.get_right() {
+            r.borrow().collect_triples(container);
+        }
+    }
+
+    /// Push the values of the current node and its successors to `container`
+    /// [postorder](https://en.wikipedia.org/wiki/Tree_traversal#Post-order_(LRN)),
+    /// which will be called by
+    /// [QueryableTree.postorder](trait.QueryableTree.html#method.postorder)
+    fn collect_postorder(&self, container: &mut Vec<T>) {
+        if let Some(l) = self.get_left() {
+            l.borrow().collect_postorder(container);
+        }
+        if let Some(r) = self.get_right() {
+            r.borrow().collect_postorder(container);
+        }
+        container.push(self.get_data());
+    }
+
     /// Return the minimum value of current node, which will be called by
     /// [QueryableTree.min](trait.QueryableTree.html#method.min)
     fn min(&self) -> T {
@@ -99,8 +520,24 @@ pub trait QueryableTreeNode<T: Ord + Copy + fmt::Debug> {
         }
     }
 
-    /// Return the length of the current node,
+    /// Return the stored value which compares equal to the given value,
     /// which will be called by
+    /// [QueryableTree.get](trait.QueryableTree.html#method.get)
+    fn get(&self, value: T) -> Option<T> {
+        match self.get_data().cmp(&value) {
+            Ordering::Equal => Some(self.get_data()),
+            Ordering::Less => self.get_right().as_ref().and_then(
+                |node| node.borrow().get(value)
+            ),
+            Ordering::Greater => self.get_left().as_ref().and_then(
+                |node| node.borrow().get(value)
+            ),
+        }
+    }
+
+    /// Return the length of the current node, counting the whole subtree
+    /// below it. Node types that cache their subtree size should override
+    /// this, the default walks the subtree. It will be called by
     /// [QueryableTree.len](trait.QueryableTree.html#method.len)
     fn len(&self) -> usize {
         let left_len = self.get_left().as_ref().map(
@@ -111,14 +548,199 @@ pub trait QueryableTreeNode<T: Ord + Copy + fmt::Debug> {
         ).unwrap_or(0);
         left_len + right_len + 1
     }
+
+    /// Get the cell caching the sum of the values of the current node and
+    /// its successors, if the node has one.
+    ///
+    /// The cell has to be reset to `None` whenever the subtree changes, and
+    /// is filled again by [sum](#method.sum).
+    fn get_sum_cache(&self) -> Option<&Cell<Option<T>>> {
+        None
+    }
+
+    /// Return the text shown for the current node by
+    /// [QueryableTree.to_structure_string](trait.QueryableTree.html#method.to_structure_string),
+    /// which is the value unless the node has more to show.
+    fn label(&self) -> String {
+        format!("{:?}", self.get_data())
+    }
+
+    /// Return the sum of the values of the current node and its successors,
+    /// which will be called by
+    /// [QueryableTree.prefix_sum_up_to](trait.QueryableTree.html#method.prefix_sum_up_to)
+    /// and [QueryableTree.range_sum](trait.QueryableTree.html#method.range_sum)
+    fn sum(&self) -> T where T: Integer {
+        if let Some(sum) = self.get_sum_cache().and_then(|c| c.get()) {
+            return sum;
+        }
+        let left_sum = self.get_left().as_ref().map(
+            |l| l.borrow().sum()
+        ).unwrap_or(T::ZERO);
+        let right_sum = self.get_right().as_ref().map(
+            |r| r.borrow().sum()
+        ).unwrap_or(T::ZERO);
+        let sum = left_sum + self.get_data() + right_sum;
+        if let Some(cache) = self.get_sum_cache() {
+            cache.set(Some(sum));
+        }
+        sum
+    }
+
+    /// Return the height of the current node together with the value and
+    /// balance factor (left height minus right height) of the most unbalanced
+    /// node below it, which will be called by
+    /// [QueryableTree.worst_balance_factor](trait.QueryableTree.html#method.worst_balance_factor)
+    fn worst_balance_factor(&self) -> (usize, T, i64) {
+        let left = self.get_left().as_ref().map(
+            |l| l.borrow().worst_balance_factor()
+        );
+        let right = self.get_right().as_ref().map(
+            |r| r.borrow().worst_balance_factor()
+        );
+        let left_height = left.as_ref().map_or(0, |l| l.0);
+        let right_height = right.as_ref().map_or(0, |r| r.0);
+        let mut worst = (self.get_data(), left_height as i64 - right_height as i64);
+        for (_, value, factor) in left.into_iter().chain(right) {
+            if factor.abs() > worst.1.abs() {
+                worst = (value, factor);
+            }
+        }
+        (max(left_height, right_height) + 1, worst.0, worst.1)
+    }
+
+    /// Push the values `depth` levels below the current node to `container`
+    /// from left to right, skipping the children of the node holding
+    /// `parent`, which will be called by
+    /// [QueryableTree.cousins_of](trait.QueryableTree.html#method.cousins_of)
+    fn collect_cousins(&self, depth: usize, parent: T, container: &mut Vec<T>) {
+        if depth == 1 {
+            if self.get_data() != parent {
+                container.extend(self.get_left().iter().map(|l| l.borrow().get_data()));
+                container.extend(self.get_right().iter().map(|r| r.borrow().get_data()));
+            }
+            return;
+        }
+        if let Some(l) = self.get_left() {
+            l.borrow().collect_cousins(depth - 1, parent.clone(), container);
+        }
+        if let Some(r) = self.get_right() {
+            r.borrow().collect_cousins(depth - 1, parent, container);
+        }
+    }
+
+    /// Push the values of the current node and its successors inorder to
+    /// `container`, each with whether it is on the left and on the right
+    /// spine, given the flags of the current node, which will be called by
+    /// [QueryableTree.iter_with_spine_flag](trait.QueryableTree.html#method.iter_with_spine_flag)
+    fn collect_spine_flags(&self, on_left: bool, on_right: bool, container: &mut Vec<(T, bool, bool)>) {
+        if let Some(l) = self.get_left() {
+            l.borrow().collect_spine_flags(on_left, false, container);
+        }
+        container.push((self.get_data(), on_left, on_right));
+        if let Some(r) = self.get_right() {
+            r.borrow().collect_spine_flags(false, on_right, container);
+        }
+    }
+
+    /// Push the values of the current node and its successors inorder to
+    /// `even` or `odd` by the parity of their depth, where the current node is
+    /// at `depth`, which will be called by
+    /// [QueryableTree.by_depth_parity](trait.QueryableTree.html#method.by_depth_parity)
+    fn collect_by_depth_parity(&self, depth: usize, even: &mut Vec<T>, odd: &mut Vec<T>) {
+        if let Some(l) = self.get_left() {
+            l.borrow().collect_by_depth_parity(depth + 1, even, odd);
+        }
+        if depth % 2 == 1 {
+            odd.push(self.get_data());
+        } else {
+            even.push(self.get_data());
+        }
+        if let Some(r) = self.get_right() {
+            r.borrow().collect_by_depth_parity(depth + 1, even, odd);
+        }
+    }
+
+    /// Push the values of the two children of every node below and including
+    /// the current node which has both, in inorder of the parents, which will
+    /// be called by
+    /// [QueryableTree.sibling_pairs](trait.QueryableTree.html#method.sibling_pairs)
+    fn collect_sibling_pairs(&self, container: &mut Vec<(T, T)>) {
+        if let Some(l) = self.get_left() {
+            l.borrow().collect_sibling_pairs(container);
+        }
+        if let (Some(l), Some(r)) = (self.get_left(), self.get_right()) {
+            container.push((l.borrow().get_data(), r.borrow().get_data()));
+        }
+        if let Some(r) = self.get_right() {
+            r.borrow().collect_sibling_pairs(container);
+        }
+    }
+
+    /// Push the values of the nodes below the current node whose subtree
+    /// heights differ by more than one to `container`, and return the height
+    /// of the current node, which will be called by
+    /// [QueryableTree.avl_violations](trait.QueryableTree.html#method.avl_violations)
+    fn collect_avl_violations(&self, container: &mut Vec<T>) -> usize {
+        let left_height = self.get_left().as_ref().map(
+            |l| l.borrow().collect_avl_violations(container)
+        ).unwrap_or(0);
+        let right_height = self.get_right().as_ref().map(
+            |r| r.borrow().collect_avl_violations(container)
+        ).unwrap_or(0);
+        if (left_height as i64 - right_height as i64).abs() > 1 {
+            container.push(self.get_data());
+        }
+        max(left_height, right_height) + 1
+    }
+
+    /// Append the lines drawing the current node and its successors sideways
+    /// to `out`, where `is_left` tells which child of its parent the node is
+    /// or `None` for the root, which will be called by
+    /// [QueryableTree.to_structure_string](trait.QueryableTree.html#method.to_structure_string)
+    fn write_structure(&self, prefix: &str, is_left: Option<bool>, out: &mut String) {
+        let (connector, right_prefix, left_prefix) = match is_left {
+            None => ("", String::new(), String::new()),
+            Some(true) => ("└── ", format!("{}│   ", prefix), format!("{}    ", prefix)),
+            Some(false) => ("┌── ", format!("{}    ", prefix), format!("{}│   ", prefix)),
+        };
+        if let Some(r) = self.get_right() {
+            r.borrow().write_structure(&right_prefix, Some(false), out);
+        }
+        out.push_str(&format!("{}{}{}\n", prefix, connector, self.label()));
+        if let Some(l) = self.get_left() {
+            l.borrow().write_structure(&left_prefix, Some(true), out);
+        }
+    }
+
+    /// Append the nested representation of the current node to `out`,
+    /// which will be called by
+    /// [QueryableTree.to_debug_nested](trait.QueryableTree.html#method.to_debug_nested)
+    fn write_nested(&self, out: &mut String) {
+        out.push_str(&format!("({:?} ", self.get_data()));
+        for child in [self.get_left(), self.get_right()].iter() {
+            match child {
+                None => out.push('.'),
+                Some(c) => c.borrow().write_nested(out),
+            }
+        }
+        out.push(')');
+    }
 }
 
 /// Provide query functions for trees
 ///
 /// `QTN` means [QueryableTreeNode](trait.QueryableTreeNode.html)
-pub trait QueryableTree<T: Ord + Copy + fmt::Debug, QTN: QueryableTreeNode<T>> {
+pub trait QueryableTree<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> {
     fn get_root(&self) -> &Option<Rc<RefCell<QTN>>>;
 
+    /// Return the comparator the values are ordered by, or `None` if they
+    /// follow `Ord`. Only a tree built by
+    /// [BinarySearchTree::with_comparator](../bstree/struct.BinarySearchTree.html#method.with_comparator)
+    /// has one, and the queries which compare values go by it.
+    fn comparator(&self) -> Option<Comparator<T>> {
+        None
+    }
+
     /// Return the number of leaves.
     ///
     /// # Example
@@ -147,7 +769,8 @@ pub trait QueryableTree<T: Ord + Copy + fmt::Debug, QTN: QueryableTreeNode<T>> {
         }
     }
 
-    /// Return the height of tree.
+    /// Return the number of nodes at `depth`, the root being at depth 0.
+    /// Depths below the bottom of the tree have no nodes.
     ///
     /// # Example
     ///
@@ -156,18 +779,22 @@ pub trait QueryableTree<T: Ord + Copy + fmt::Debug, QTN: QueryableTreeNode<T>> {
     /// use trees::base::QueryableTree;
     ///
     /// let mut tree = BinarySearchTree::new();
-    /// println!("{}", tree.height());  // 0
-    /// tree.insert(1);
-    /// println!("{}", tree.height());  // 1
+    /// for v in vec![4, 2, 6, 1] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{}", tree.count_at_depth(1));  // 2
+    /// println!("{}", tree.count_at_depth(2));  // 1
+    /// println!("{}", tree.count_at_depth(3));  // 0
     /// ```
-    fn height(&self) -> usize {
-        match &self.get_root() {
+    fn count_at_depth(&self, depth: usize) -> usize {
+        match self.get_root() {
             None => 0,
-            Some(node) => node.borrow().height(),
+            Some(node) => node.borrow().count_at_depth(depth),
         }
     }
 
-    /// Print tree [inorder](https://en.wikipedia.org/wiki/Tree_traversal#In-order_(LNR))
+    /// Return how many leaves there are at every depth, the root being at
+    /// depth 0. Depths without a leaf are left out.
     ///
     /// # Example
     ///
@@ -176,24 +803,21 @@ pub trait QueryableTree<T: Ord + Copy + fmt::Debug, QTN: QueryableTreeNode<T>> {
     /// use trees::base::QueryableTree;
     ///
     /// let mut tree = BinarySearchTree::new();
-    /// tree.insert(1);
-    /// tree.insert(0);
-    /// tree.insert(5);
-    /// tree.insert(3);
-    /// tree.insert(2);
-    /// tree.print_inorder(); // 0 1 2 3 5
+    /// for v in vec![4, 2, 6, 1, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.leaf_depth_histogram());  // {1: 1, 2: 2}
     /// ```
-    fn print_inorder(&self) {
-        match &self.get_root() {
-            None => println!("It is an empty tree!"),
-            Some(node) => {
-                node.borrow().print_inorder();
-                println!();
-            }
+    fn leaf_depth_histogram(&self) -> BTreeMap<usize, usize> {
+        let mut histogram = BTreeMap::new();
+        if let Some(node) = self.get_root() {
+            node.borrow().collect_leaf_depths(0, &mut histogram);
         }
+        histogram
     }
 
-    /// Determine whether the tree is empty
+    /// Return the number of leaves at the deepest level of the tree. A
+    /// perfect tree has all its leaves there, a skewed tree usually one.
     ///
     /// # Example
     ///
@@ -202,18 +826,16 @@ pub trait QueryableTree<T: Ord + Copy + fmt::Debug, QTN: QueryableTreeNode<T>> {
     /// use trees::base::QueryableTree;
     ///
     /// let mut tree = BinarySearchTree::new();
-    /// println!("{}", tree.is_empty());  // true
-    /// tree.insert(1);
-    /// println!("{}", tree.is_empty());  // false
+    /// for v in vec![4, 2, 6, 1, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{}", tree.deepest_leaf_count());  // 2
     /// ```
-    fn is_empty(&self) -> bool {
-        match self.get_root() {
-            None => true,
-            Some(_) => false
-        }
+    fn deepest_leaf_count(&self) -> usize {
+        self.leaf_depth_histogram().values().next_back().copied().unwrap_or(0)
     }
 
-    /// Return the minimum value of the tree
+    /// Return the height of tree.
     ///
     /// # Example
     ///
@@ -222,22 +844,20 @@ pub trait QueryableTree<T: Ord + Copy + fmt::Debug, QTN: QueryableTreeNode<T>> {
     /// use trees::base::QueryableTree;
     ///
     /// let mut tree = BinarySearchTree::new();
-    /// println!("{:?}", tree.min());  // None
+    /// println!("{}", tree.height());  // 0
     /// tree.insert(1);
-    /// tree.insert(0);
-    /// tree.insert(5);
-    /// tree.insert(3);
-    /// tree.insert(2);
-    /// println!("{:?}", tree.min());  // Some(0)
+    /// println!("{}", tree.height());  // 1
     /// ```
-    fn min(&self) -> Option<T> {
-        match self.get_root() {
-            None => None,
-            Some(node) => Some(node.borrow().min()),
+    fn height(&self) -> usize {
+        match &self.get_root() {
+            None => 0,
+            Some(node) => node.borrow().height(),
         }
     }
 
-    /// Return the maximum value of the tree
+    /// Return the number of edges on the longest path between any two nodes
+    /// of the tree, which doesn't need to pass through the root. The empty
+    /// tree and a single node return 0.
     ///
     /// # Example
     ///
@@ -246,22 +866,23 @@ pub trait QueryableTree<T: Ord + Copy + fmt::Debug, QTN: QueryableTreeNode<T>> {
     /// use trees::base::QueryableTree;
     ///
     /// let mut tree = BinarySearchTree::new();
-    /// println!("{:?}", tree.max());  // None
-    /// tree.insert(1);
-    /// tree.insert(0);
-    /// tree.insert(5);
-    /// tree.insert(3);
-    /// tree.insert(2);
-    /// println!("{:?}", tree.max());  // Some(5)
+    /// for v in vec![2, 1, 3, 4] {
+    ///     tree.insert(v);
+    /// }
+    /// // from 1 over 2 and 3 down to 4
+    /// println!("{}", tree.diameter());  // 3
     /// ```
-    fn max(&self) -> Option<T> {
-        match self.get_root() {
-            None => None,
-            Some(node) => Some(node.borrow().max()),
+    fn diameter(&self) -> usize {
+        let mut best = 0;
+        if let Some(node) = self.get_root() {
+            node.borrow().height_and_diameter(&mut best);
         }
+        best
     }
 
-    /// Determine whether the tree contains given value
+    /// Return the height of the tree like [height](#method.height), but
+    /// computed without recursion by counting the levels of a breadth-first
+    /// traversal, so it is safe on very deep trees.
     ///
     /// # Example
     ///
@@ -271,17 +892,72 @@ pub trait QueryableTree<T: Ord + Copy + fmt::Debug, QTN: QueryableTreeNode<T>> {
     ///
     /// let mut tree = BinarySearchTree::new();
     /// tree.insert(1);
-    /// println!("{}", tree.contains(1));  // true
-    /// println!("{}", tree.contains(0));  // false
+    /// tree.insert(0);
+    /// tree.insert(2);
+    /// tree.insert(3);
+    /// println!("{}", tree.height_bfs());  // 3
     /// ```
-    fn contains(&self, value: T) -> bool {
-        match self.get_root() {
-            None => false,
-            Some(node) => node.borrow().contains(value),
+    fn height_bfs(&self) -> usize {
+        let mut queue = VecDeque::new();
+        if let Some(root) = self.get_root() {
+            queue.push_back(root.clone());
+        }
+        let mut height = 0;
+        while !queue.is_empty() {
+            height += 1;
+            for _ in 0..queue.len() {
+                let node = queue.pop_front().unwrap();
+                let node = node.borrow();
+                if let Some(left) = node.get_left() {
+                    queue.push_back(left.clone());
+                }
+                if let Some(right) = node.get_right() {
+                    queue.push_back(right.clone());
+                }
+            }
+        }
+        height
+    }
+
+    /// Return the largest number of nodes on a single level of the tree,
+    /// found with the same breadth-first traversal as
+    /// [height_bfs](#method.height_bfs). The empty tree has width 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{}", tree.width());  // 2
+    /// ```
+    fn width(&self) -> usize {
+        let mut queue = VecDeque::new();
+        if let Some(root) = self.get_root() {
+            queue.push_back(root.clone());
+        }
+        let mut width = 0;
+        while !queue.is_empty() {
+            width = max(width, queue.len());
+            for _ in 0..queue.len() {
+                let node = queue.pop_front().unwrap();
+                let node = node.borrow();
+                if let Some(left) = node.get_left() {
+                    queue.push_back(left.clone());
+                }
+                if let Some(right) = node.get_right() {
+                    queue.push_back(right.clone());
+                }
+            }
         }
+        width
     }
 
-    /// Return the length of the tree
+    /// Print tree [inorder](https://en.wikipedia.org/wiki/Tree_traversal#In-order_(LNR))
     ///
     /// # Example
     ///
@@ -291,14 +967,1914 @@ pub trait QueryableTree<T: Ord + Copy + fmt::Debug, QTN: QueryableTreeNode<T>> {
     ///
     /// let mut tree = BinarySearchTree::new();
     /// tree.insert(1);
-    /// tree.insert(10);
-    /// tree.insert(13);
-    /// println!("{}", tree.len());  // 3
+    /// tree.insert(0);
+    /// tree.insert(5);
+    /// tree.insert(3);
+    /// tree.insert(2);
+    /// tree.print_inorder(); // 0 1 2 3 5
     /// ```
-    fn len(&self) -> usize {
+    fn print_inorder(&self) {
+        match &self.get_root() {
+            None => println!("It is an empty tree!"),
+            Some(node) => {
+                node.borrow().print_inorder();
+                println!();
+            }
+        }
+    }
+
+    /// Return the values of the tree in ascending order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(0);
+    /// tree.insert(5);
+    /// println!("{:?}", tree.to_vec());  // [0, 1, 5]
+    /// ```
+    fn to_vec(&self) -> Vec<T> {
+        let mut container = vec![];
+        if let Some(node) = self.get_root() {
+            node.borrow().collect_inorder(&mut container);
+        }
+        container
+    }
+
+    /// Return the values laid out as an implicit binary min-heap, where the
+    /// children of index `i` are at `2i + 1` and `2i + 2` and no value is
+    /// smaller than its parent.
+    ///
+    /// An ascending array already has this property, as every parent comes
+    /// before its children, so heapifying the sorted values moves nothing and
+    /// this is the same as [to_vec](#method.to_vec).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::collections::BinaryHeap;
+    /// use std::cmp::Reverse;
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![3, 1, 2] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.to_heap_array());  // [1, 2, 3]
+    /// let heap: BinaryHeap<_> = tree.to_heap_array().into_iter().map(Reverse).collect();
+    /// println!("{:?}", heap.peek());  // Some(Reverse(1))
+    /// ```
+    fn to_heap_array(&self) -> Vec<T> {
+        self.to_vec()
+    }
+
+    /// Return the values in ascending order like [to_vec](#method.to_vec),
+    /// using a [Morris traversal](https://en.wikipedia.org/wiki/Tree_traversal#Morris_in-order_traversal_using_threading)
+    /// which needs neither recursion nor a stack, so it is safe on very deep
+    /// trees.
+    ///
+    /// The traversal temporarily links the largest node of every left
+    /// subtree back to its ancestor, and removes those links again before
+    /// returning, so the tree ends up with its original structure.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(0);
+    /// tree.insert(5);
+    /// println!("{:?}", tree.morris_inorder());  // [0, 1, 5]
+    /// ```
+    fn morris_inorder(&self) -> Vec<T> {
+        let mut container = vec![];
+        let mut current = self.get_root().clone();
+        while let Some(node) = current {
+            let left = node.borrow().get_left().clone();
+            let left = match left {
+                None => {
+                    container.push(node.borrow().get_data());
+                    current = node.borrow().get_right().clone();
+                    continue;
+                }
+                Some(left) => left,
+            };
+            // find the predecessor, stopping at an existing thread
+            let mut pred = left;
+            loop {
+                let right = pred.borrow().get_right().clone();
+                match right {
+                    Some(right) if !Rc::ptr_eq(&right, &node) => pred = right,
+                    _ => break,
+                }
+            }
+            if pred.borrow().get_right().is_none() {
+                *pred.borrow_mut().get_right_mut() = Some(node.clone());
+                current = node.borrow().get_left().clone();
+            } else {
+                *pred.borrow_mut().get_right_mut() = None;
+                container.push(node.borrow().get_data());
+                current = node.borrow().get_right().clone();
+            }
+        }
+        container
+    }
+
+    /// Return the values
+    /// [preorder](https://en.wikipedia.org/wiki/Tree_traversal#Pre-order_(NLR)),
+    /// which is enough to rebuild the same tree by inserting them in order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(0);
+    /// tree.insert(5);
+    /// println!("{:?}", tree.preorder());  // [1, 0, 5]
+    /// ```
+    fn preorder(&self) -> Vec<T> {
+        let mut container = vec![];
+        if let Some(node) = self.get_root() {
+            node.borrow().collect_preorder(&mut container);
+        }
+        container
+    }
+
+    /// Return one `(value, left child, right child)` triple per node,
+    /// preorder, which describes the whole structure of the tree. See
+    /// [BinarySearchTree::from_triples](../bstree/struct.BinarySearchTree.html#method.from_triples)
+    /// to rebuild it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(2);
+    /// tree.insert(1);
+    /// tree.insert(3);
+    /// println!("{:?}", tree.to_triples());  // [(2, Some(1), Some(3)), (1, None, None), (3, None, None)]
+    /// ```
+    fn to_triples(&self) -> Vec<(T, Option<T>, Option<T>)> {
+        let mut container = vec![];
+        if let Some(node) = self.get_root() {
+            node.borrow().collect_triples(&mut container);
+        }
+        container
+    }
+
+    /// Return the values
+    /// [postorder](https://en.wikipedia.org/wiki/Tree_traversal#Post-order_(LRN))
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(0);
+    /// tree.insert(5);
+    /// println!("{:?}", tree.postorder());  // [0, 5, 1]
+    /// ```
+    fn postorder(&self) -> Vec<T> {
+        let mut container = vec![];
+        if let Some(node) = self.get_root() {
+            node.borrow().collect_postorder(&mut container);
+        }
+        container
+    }
+
+    /// Return the values level by level
+    /// ([breadth-first](https://en.wikipedia.org/wiki/Tree_traversal#Breadth-first_search)),
+    /// with one inner `Vec` per depth ordered from left to right
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(2);
+    /// tree.insert(1);
+    /// tree.insert(3);
+    /// tree.insert(4);
+    /// println!("{:?}", tree.level_order());  // [[2], [1, 3], [4]]
+    /// ```
+    fn level_order(&self) -> Vec<Vec<T>> {
+        let mut levels = vec![];
+        let mut queue = VecDeque::new();
+        if let Some(root) = self.get_root() {
+            queue.push_back(root.clone());
+        }
+        while !queue.is_empty() {
+            let mut level = vec![];
+            for _ in 0..queue.len() {
+                let node = queue.pop_front().unwrap();
+                let node = node.borrow();
+                level.push(node.get_data());
+                if let Some(left) = node.get_left() {
+                    queue.push_back(left.clone());
+                }
+                if let Some(right) = node.get_right() {
+                    queue.push_back(right.clone());
+                }
+            }
+            levels.push(level);
+        }
+        levels
+    }
+
+    /// Return a lazy iterator over the values in
+    /// [level order](https://en.wikipedia.org/wiki/Tree_traversal#Breadth-first_search),
+    /// from the root down and from left to right within a depth. Unlike
+    /// [level_order](#method.level_order) nothing is collected up front,
+    /// so taking only the first few values only visits the top of the tree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1, 3, 5, 7] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.iter_bfs().take(3).collect::<Vec<_>>());  // [4, 2, 6]
+    /// ```
+    fn iter_bfs(&self) -> BfsIter<T, QTN> {
+        BfsIter { queue: self.get_root().iter().cloned().collect(), marker: PhantomData }
+    }
+
+    /// Iterate the values inorder, each with whether its node is on the left
+    /// spine and whether it is on the right spine of the tree. The left
+    /// spine is the path from the root following left children, ending at
+    /// the minimum, and the right spine likewise ends at the maximum. The
+    /// root is on both.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![2, 1, 4, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// let flagged: Vec<_> = tree.iter_with_spine_flag().collect();
+    /// println!("{:?}", flagged);  // [(1, true, false), (2, true, true), (3, false, false), (4, false, true)]
+    /// ```
+    fn iter_with_spine_flag(&self) -> impl Iterator<Item = (T, bool, bool)> {
+        let mut container = vec![];
+        if let Some(node) = self.get_root() {
+            node.borrow().collect_spine_flags(true, true, &mut container);
+        }
+        container.into_iter()
+    }
+
+    /// Return a lazy iterator over the values in ascending order, which can
+    /// be reversed with `rev()` to get them in descending order.
+    ///
+    /// The traversal keeps an explicit stack of the nodes on the current
+    /// path instead of collecting the values up front.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(0);
+    /// tree.insert(5);
+    /// for v in tree.iter() {
+    ///     println!("{}", v);  // 0, 1, 5
+    /// }
+    /// println!("{:?}", tree.iter().collect::<Vec<_>>());  // [0, 1, 5]
+    /// ```
+    fn iter(&self) -> Iter<T, QTN> {
+        Iter::new(self.get_root())
+    }
+
+    /// Return a lazy iterator over the values between `lo` and `hi`, both
+    /// included, in ascending order. Nothing is yielded if `lo > hi`.
+    ///
+    /// Subtrees entirely outside of the bounds are never visited, so this
+    /// is much cheaper than filtering [iter](#method.iter) on large trees.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![5, 2, 8, 1, 3, 9] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.range(2, 8).collect::<Vec<_>>());  // [2, 3, 5, 8]
+    /// println!("{:?}", tree.range(8, 2).collect::<Vec<_>>());  // []
+    /// ```
+    fn range(&self, lo: T, hi: T) -> Range<T, QTN> {
+        Range::new(self.get_root(), lo, hi, self.comparator())
+    }
+
+    /// Return a lazy iterator over the union of the values of both trees in
+    /// ascending order, yielding values found in both trees once, without
+    /// building a merged tree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut a = BinarySearchTree::new();
+    /// let mut b = BinarySearchTree::new();
+    /// for v in vec![1, 3, 5] {
+    ///     a.insert(v);
+    /// }
+    /// for v in vec![2, 3, 6] {
+    ///     b.insert(v);
+    /// }
+    /// println!("{:?}", a.merge_iter(&b).collect::<Vec<_>>());  // [1, 2, 3, 5, 6]
+    /// ```
+    fn merge_iter(&self, other: &Self) -> MergeIter<T, QTN> where Self: Sized {
+        MergeIter { left: self.iter().peekable(), right: other.iter().peekable(), cmp: self.comparator() }
+    }
+
+    /// Return the smallest value which is in exactly one of the two trees,
+    /// or `None` if they hold the same values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut a = BinarySearchTree::new();
+    /// let mut b = BinarySearchTree::new();
+    /// for v in vec![1, 2, 4] {
+    ///     a.insert(v);
+    /// }
+    /// for v in vec![1, 2, 3, 4] {
+    ///     b.insert(v);
+    /// }
+    /// println!("{:?}", a.first_divergence(&b));  // Some(3)
+    /// a.insert(3);
+    /// println!("{:?}", a.first_divergence(&b));  // None
+    /// ```
+    fn first_divergence(&self, other: &Self) -> Option<T> where Self: Sized {
+        // Walk both trees in lockstep: before the first mismatch both hold
+        // the same values, so the smaller of the two can't be in the other.
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        let mut left = self.iter();
+        let mut right = other.iter();
+        loop {
+            match (left.next(), right.next()) {
+                (None, None) => return None,
+                (Some(v), None) | (None, Some(v)) => return Some(v),
+                (Some(l), Some(r)) => match cmp(&l, &r) {
+                    Ordering::Equal => {}
+                    Ordering::Less => return Some(l),
+                    Ordering::Greater => return Some(r),
+                },
+            }
+        }
+    }
+
+    /// Determine whether the tree is empty
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// println!("{}", tree.is_empty());  // true
+    /// tree.insert(1);
+    /// println!("{}", tree.is_empty());  // false
+    /// ```
+    fn is_empty(&self) -> bool {
         match self.get_root() {
-            None => 0,
-            Some(node) => node.borrow().len(),
+            None => true,
+            Some(_) => false
+        }
+    }
+
+    /// Return the minimum value of the tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// println!("{:?}", tree.min());  // None
+    /// tree.insert(1);
+    /// tree.insert(0);
+    /// tree.insert(5);
+    /// tree.insert(3);
+    /// tree.insert(2);
+    /// println!("{:?}", tree.min());  // Some(0)
+    /// ```
+    fn min(&self) -> Option<T> {
+        match self.get_root() {
+            None => None,
+            Some(node) => Some(node.borrow().min()),
+        }
+    }
+
+    /// Return the maximum value of the tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// println!("{:?}", tree.max());  // None
+    /// tree.insert(1);
+    /// tree.insert(0);
+    /// tree.insert(5);
+    /// tree.insert(3);
+    /// tree.insert(2);
+    /// println!("{:?}", tree.max());  // Some(5)
+    /// ```
+    fn max(&self) -> Option<T> {
+        match self.get_root() {
+            None => None,
+            Some(node) => Some(node.borrow().max()),
+        }
+    }
+
+    /// Return the minimum and maximum values of the tree, sharing the
+    /// empty check and the access to the root
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// println!("{:?}", tree.extremes());  // None
+    /// tree.insert(1);
+    /// tree.insert(0);
+    /// tree.insert(5);
+    /// println!("{:?}", tree.extremes());  // Some((0, 5))
+    /// ```
+    fn extremes(&self) -> Option<(T, T)> {
+        self.get_root().as_ref().map(|node| {
+            let node = node.borrow();
+            (node.min(), node.max())
+        })
+    }
+
+    /// Determine whether the tree contains given value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// println!("{}", tree.contains(1));  // true
+    /// println!("{}", tree.contains(0));  // false
+    /// ```
+    fn contains(&self, value: T) -> bool {
+        match self.get_root() {
+            None => false,
+            Some(node) => node.borrow().contains(value),
+        }
+    }
+
+    /// Return the stored value which compares equal to `value`, or `None` if
+    /// there is no such value, like `BTreeSet::get`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(3);
+    /// println!("{:?}", tree.get(3));  // Some(3)
+    /// println!("{:?}", tree.get(2));  // None
+    /// ```
+    fn get(&self, value: T) -> Option<T> {
+        self.get_root().as_ref().and_then(|node| node.borrow().get(value))
+    }
+
+    /// Return the smallest value in the tree which is strictly greater than
+    /// `value`, or `None` if there is no such value, e.g. for the maximum.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(3);
+    /// tree.insert(5);
+    /// println!("{:?}", tree.successor(3));  // Some(5)
+    /// println!("{:?}", tree.successor(4));  // Some(5)
+    /// println!("{:?}", tree.successor(5));  // None
+    /// ```
+    fn successor(&self, value: T) -> Option<T> {
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        let mut best = None;
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            let data = n.borrow().get_data();
+            node = if cmp(&data, &value) == Ordering::Greater {
+                best = Some(data);
+                n.borrow().get_left().clone()
+            } else {
+                n.borrow().get_right().clone()
+            };
+        }
+        best
+    }
+
+    /// Return the largest value in the tree which is strictly less than
+    /// `value`, or `None` if there is no such value, e.g. for the minimum.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(3);
+    /// tree.insert(5);
+    /// println!("{:?}", tree.predecessor(3));  // Some(1)
+    /// println!("{:?}", tree.predecessor(2));  // Some(1)
+    /// println!("{:?}", tree.predecessor(1));  // None
+    /// ```
+    fn predecessor(&self, value: T) -> Option<T> {
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        let mut best = None;
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            let data = n.borrow().get_data();
+            node = if cmp(&data, &value) == Ordering::Less {
+                best = Some(data);
+                n.borrow().get_right().clone()
+            } else {
+                n.borrow().get_left().clone()
+            };
+        }
+        best
+    }
+
+    /// Return the largest value in the tree which is less than or equal to
+    /// `value`, or `None` if `value` is below the minimum.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(3);
+    /// tree.insert(5);
+    /// println!("{:?}", tree.floor(3));  // Some(3)
+    /// println!("{:?}", tree.floor(4));  // Some(3)
+    /// println!("{:?}", tree.floor(0));  // None
+    /// ```
+    fn floor(&self, value: T) -> Option<T> {
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        let mut best = None;
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            let data = n.borrow().get_data();
+            node = if cmp(&data, &value) != Ordering::Greater {
+                best = Some(data);
+                n.borrow().get_right().clone()
+            } else {
+                n.borrow().get_left().clone()
+            };
+        }
+        best
+    }
+
+    /// Return the smallest value in the tree which is greater than or equal to
+    /// `value`, or `None` if `value` is above the maximum.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(3);
+    /// tree.insert(5);
+    /// println!("{:?}", tree.ceiling(3));  // Some(3)
+    /// println!("{:?}", tree.ceiling(4));  // Some(5)
+    /// println!("{:?}", tree.ceiling(6));  // None
+    /// ```
+    fn ceiling(&self, value: T) -> Option<T> {
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        let mut best = None;
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            let data = n.borrow().get_data();
+            node = if cmp(&data, &value) != Ordering::Less {
+                best = Some(data);
+                n.borrow().get_left().clone()
+            } else {
+                n.borrow().get_right().clone()
+            };
+        }
+        best
+    }
+
+    /// Return the length of the tree. The trees cache the size of every
+    /// subtree in its root node, so this is O(1), it only reads the root.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(1);
+    /// tree.insert(10);
+    /// tree.insert(13);
+    /// println!("{}", tree.len());  // 3
+    /// ```
+    fn len(&self) -> usize {
+        match self.get_root() {
+            None => 0,
+            Some(node) => node.borrow().len(),
+        }
+    }
+
+    /// Return how full the tree is compared to a perfect tree of the same
+    /// height, i.e. `len / (2^height - 1)`. A perfect tree, including the
+    /// empty tree, returns `1.0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(2);
+    /// tree.insert(1);
+    /// tree.insert(3);
+    /// println!("{}", tree.fill_percentage());  // 1
+    /// tree.insert(4);
+    /// println!("{}", tree.fill_percentage());  // 0.5714285714285714
+    /// ```
+    fn fill_percentage(&self) -> f64 {
+        if self.is_empty() {
+            return 1.0;
+        }
+        self.len() as f64 / ((self.height() as f64).exp2() - 1.0)
+    }
+
+    /// Return a 64-bit fingerprint of the values in the tree.
+    ///
+    /// The values are hashed in ascending order with a fixed hasher, so trees
+    /// holding the same values get the same fingerprint regardless of their
+    /// shape, and the fingerprint is stable across runs on the same platform.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut a = BinarySearchTree::new();
+    /// a.insert(1);
+    /// a.insert(2);
+    /// let mut b = BinarySearchTree::new();
+    /// b.insert(2);
+    /// b.insert(1);
+    /// assert_eq!(a.content_hash(), b.content_hash());
+    /// ```
+    fn content_hash(&self) -> u64 where T: Hash {
+        let mut hasher = FnvHasher::new();
+        for v in values_by_ord(self) {
+            v.hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// Return true if the tree holds exactly the values of `a` and `b`, with
+    /// the two sources disjoint and free of duplicates themselves, i.e. each
+    /// value of the tree comes from exactly one entry of the sources and
+    /// nothing else is in the tree. Meant as a post-condition check after
+    /// merging two sources that should not overlap.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let tree: BinarySearchTree<i32> = vec![1, 2, 3, 4].into_iter().collect();
+    /// println!("{}", tree.built_from_disjoint(&[1, 3], &[2, 4]));  // true
+    /// println!("{}", tree.built_from_disjoint(&[1, 2, 3], &[3, 4]));  // false, 3 overlaps
+    /// println!("{}", tree.built_from_disjoint(&[1, 3], &[2]));  // false, 4 is extra
+    /// ```
+    fn built_from_disjoint(&self, a: &[T], b: &[T]) -> bool {
+        if a.len() + b.len() != self.len() {
+            return false;
+        }
+        let mut union: Vec<T> = a.iter().chain(b).cloned().collect();
+        union.sort();
+        union == values_by_ord(self)
+    }
+
+    /// Return true if the keys of the values of both trees are the same
+    /// multiset, ignoring everything else in the values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut a = BinarySearchTree::new();
+    /// a.insert((1, "one"));
+    /// a.insert((2, "two"));
+    /// let mut b = BinarySearchTree::new();
+    /// b.insert((2, "zwei"));
+    /// b.insert((1, "eins"));
+    /// println!("{}", a.equal_by_key(&b, |v| v.0));  // true
+    /// println!("{}", a.equal_by_key(&b, |v| v.1));  // false
+    /// ```
+    fn equal_by_key<K: Ord, F: Fn(&T) -> K>(&self, other: &Self, key: F) -> bool where Self: Sized {
+        if self.len() != other.len() {
+            return false;
+        }
+        let mut keys: Vec<K> = self.iter().map(|v| key(&v)).collect();
+        let mut other_keys: Vec<K> = other.iter().map(|v| key(&v)).collect();
+        keys.sort();
+        other_keys.sort();
+        keys == other_keys
+    }
+
+    /// Return the value of the most unbalanced node and its balance factor
+    /// (left height minus right height), or `None` for an empty tree.
+    ///
+    /// When several nodes share the largest absolute factor, the one closest
+    /// to the root is reported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// println!("{:?}", tree.worst_balance_factor());  // None
+    /// tree.insert(1);
+    /// tree.insert(2);
+    /// tree.insert(3);
+    /// println!("{:?}", tree.worst_balance_factor());  // Some((1, -2))
+    /// ```
+    fn worst_balance_factor(&self) -> Option<(T, i64)> {
+        self.get_root().as_ref().map(|node| {
+            let (_, value, factor) = node.borrow().worst_balance_factor();
+            (value, factor)
+        })
+    }
+
+    /// Return the value of the non-root node with the largest subtree, i.e.
+    /// the node whose removal would detach the most values, or `None` if the
+    /// tree has no node besides the root.
+    ///
+    /// Every subtree below the root is contained in one of the subtrees of
+    /// the children of the root, so this is the child of the root with more
+    /// values below it, the left one on a tie.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// //        4
+    /// //      /   \
+    /// //     2     6
+    /// //          / \
+    /// //         5   7
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 5, 7] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.most_critical_node());  // Some(6)
+    /// ```
+    fn most_critical_node(&self) -> Option<T> {
+        let root = self.get_root().as_ref()?.borrow();
+        let size = |child: &Option<Rc<RefCell<QTN>>>| child.as_ref().map_or(0, |c| c.borrow().len());
+        let child = if size(root.get_left()) >= size(root.get_right()) {
+            root.get_left()
+        } else {
+            root.get_right()
+        };
+        child.as_ref().map(|c| c.borrow().get_data())
+    }
+
+    /// Return the values of all nodes whose left and right subtree heights
+    /// differ by more than one, i.e. the nodes which break the AVL balance
+    /// condition, in ascending order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in 1..=4 {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.avl_violations());  // [1, 2]
+    /// ```
+    fn avl_violations(&self) -> Vec<T> {
+        let mut container = vec![];
+        if let Some(node) = self.get_root() {
+            node.borrow().collect_avl_violations(&mut container);
+        }
+        let order = self.comparator();
+        container.sort_by(ordering(&order));
+        container
+    }
+
+    /// Return the values at the same depth as `value` which do not share its
+    /// parent, in ascending order. The result is empty if `value` is not in
+    /// the tree or is the root.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// //        4
+    /// //      /   \
+    /// //     2     6
+    /// //    / \   / \
+    /// //   1   3 5   7
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1, 3, 5, 7] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.cousins_of(3));  // [5, 7]
+    /// println!("{:?}", tree.cousins_of(2));  // []
+    /// ```
+    fn cousins_of(&self, value: T) -> Vec<T> {
+        let mut container = vec![];
+        let mut node = match self.get_root() {
+            None => return container,
+            Some(root) => root.clone(),
+        };
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        let mut depth = 0;
+        let mut parent = None;
+        loop {
+            let data = node.borrow().get_data();
+            let next = match cmp(&data, &value) {
+                Ordering::Equal => break,
+                Ordering::Less => node.borrow().get_right().clone(),
+                Ordering::Greater => node.borrow().get_left().clone(),
+            };
+            match next {
+                None => return container,
+                Some(next) => node = next,
+            }
+            parent = Some(data);
+            depth += 1;
+        }
+        if let (Some(parent), Some(root)) = (parent, self.get_root()) {
+            root.borrow().collect_cousins(depth, parent, &mut container);
+        }
+        container
+    }
+
+    /// Return the `(left, right)` values of the children of every node which
+    /// has both, ordered by an inorder traversal of the parents.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// //        4
+    /// //      /   \
+    /// //     2     6
+    /// //    /     / \
+    /// //   1     5   7
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1, 5, 7] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.sibling_pairs());  // [(2, 6), (5, 7)]
+    /// ```
+    fn sibling_pairs(&self) -> Vec<(T, T)> {
+        let mut container = vec![];
+        if let Some(root) = self.get_root() {
+            root.borrow().collect_sibling_pairs(&mut container);
+        }
+        container
+    }
+
+    /// Return the values at even depths and the values at odd depths, each
+    /// inorder, where the root is at depth 0.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// //        4
+    /// //      /   \
+    /// //     2     6
+    /// //    /
+    /// //   1
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.by_depth_parity());  // ([1, 4], [2, 6])
+    /// ```
+    fn by_depth_parity(&self) -> (Vec<T>, Vec<T>) {
+        let mut even = vec![];
+        let mut odd = vec![];
+        if let Some(root) = self.get_root() {
+            root.borrow().collect_by_depth_parity(0, &mut even, &mut odd);
+        }
+        (even, odd)
+    }
+
+    /// Return true if an inorder traversal of the tree is strictly
+    /// ascending, i.e. the binary search tree ordering holds. This is the
+    /// same check as [is_valid_bst](#method.is_valid_bst).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(2);
+    /// tree.insert(1);
+    /// println!("{}", tree.is_bst());  // true
+    /// ```
+    fn is_bst(&self) -> bool {
+        self.is_valid_bst()
+    }
+
+    /// Return true if the binary search tree ordering holds, i.e. every
+    /// value is larger than all values in its left subtree and smaller than
+    /// all values in its right subtree. Each node is checked against the
+    /// bounds set by its ancestors, so nothing is collected and the walk
+    /// stops at the first value out of place.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![5, 2, 8, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// tree.delete(5);
+    /// println!("{}", tree.is_valid_bst());  // true
+    /// ```
+    fn is_valid_bst(&self) -> bool {
+        let order = self.comparator();
+        self.get_root().as_ref().is_none_or(|root| root.borrow().is_within(None, None, ordering(&order)))
+    }
+
+    /// Return true if the tree satisfies all of its invariants.
+    ///
+    /// This is [is_bst](#method.is_bst) by default. Trees with extra
+    /// invariants, such as the AVL balance or the red-black coloring, check
+    /// those as well.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::avltree::AVLTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = AVLTree::new();
+    /// for v in 0..10 {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{}", tree.is_valid());  // true
+    /// ```
+    fn is_valid(&self) -> bool {
+        self.is_bst()
+    }
+
+    /// Return true if the shape of the tree is its own mirror image, i.e. the
+    /// shape of the left subtree mirrors the shape of the right subtree.
+    /// The values are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(2);
+    /// tree.insert(1);
+    /// println!("{}", tree.is_shape_symmetric());  // false
+    /// tree.insert(3);
+    /// println!("{}", tree.is_shape_symmetric());  // true
+    /// ```
+    fn is_shape_symmetric(&self) -> bool {
+        match self.get_root() {
+            None => true,
+            Some(root) => {
+                let root = root.borrow();
+                is_mirror_shape(root.get_left(), root.get_right())
+            }
+        }
+    }
+
+    /// Return true if `other` has the same shape as the tree, ignoring the
+    /// values. The two trees can be of different types.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let a: BinarySearchTree<i32> = vec![2, 1, 3].into_iter().collect();
+    /// let b: BinarySearchTree<i32> = vec![20, 10, 30].into_iter().collect();
+    /// let c: BinarySearchTree<i32> = vec![1, 2, 3].into_iter().collect();
+    /// println!("{}", a.same_shape(&b));  // true
+    /// println!("{}", a.same_shape(&c));  // false
+    /// ```
+    fn same_shape<QTN2, Other>(&self, other: &Other) -> bool
+    where
+        QTN2: QueryableTreeNode<T>,
+        Other: QueryableTree<T, QTN2>,
+    {
+        is_same_shape(self.get_root(), other.get_root())
+    }
+
+    /// Return the shape of the tree as a nested string for exact-shape
+    /// assertions in tests. Every node is rendered as
+    /// `(value left right)` without a space between the two children, and a
+    /// missing child or an empty tree as `.`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// tree.insert(2);
+    /// tree.insert(1);
+    /// tree.insert(3);
+    /// println!("{}", tree.to_debug_nested());  // (2 (1 ..)(3 ..))
+    /// ```
+    fn to_debug_nested(&self) -> String {
+        match self.get_root() {
+            None => String::from("."),
+            Some(node) => {
+                let mut out = String::new();
+                node.borrow().write_nested(&mut out);
+                out
+            }
+        }
+    }
+
+    /// Return the tree drawn sideways, one node per line, with the root at
+    /// the left edge, the right subtree above it and the left subtree below
+    /// it. Nodes of a red-black tree show their color. An empty tree gives
+    /// an empty string.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// print!("{}", tree.to_structure_string());
+    /// // ┌── 6
+    /// // 4
+    /// // │   ┌── 3
+    /// // └── 2
+    /// //     └── 1
+    /// ```
+    fn to_structure_string(&self) -> String {
+        let mut out = String::new();
+        if let Some(node) = self.get_root() {
+            node.borrow().write_structure("", None, &mut out);
+        }
+        out
+    }
+
+    /// Print the tree drawn by [to_structure_string](#method.to_structure_string)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = RedBlackTree::new();
+    /// tree.print_structure(); // It is an empty tree!
+    /// for v in vec![2, 1, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// tree.print_structure();
+    /// // ┌── 3 (Red)
+    /// // 2 (Black)
+    /// // └── 1 (Red)
+    /// ```
+    fn print_structure(&self) {
+        match self.get_root() {
+            None => println!("It is an empty tree!"),
+            Some(_) => print!("{}", self.to_structure_string()),
+        }
+    }
+
+    /// Return the sum of all values less than or equal to `value`.
+    ///
+    /// This descends a single path from the root, adding up every node not
+    /// greater than `value` together with its left subtree. The trees cache
+    /// the sum of every subtree, so this takes O(height) once the sums have
+    /// been computed.
+    ///
+    /// # Panics
+    ///
+    /// Panics on overflow in debug builds, like the `+` operator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in 1..=10 {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{}", tree.prefix_sum_up_to(5));  // 15
+    /// println!("{}", tree.prefix_sum_up_to(0));  // 0
+    /// ```
+    fn prefix_sum_up_to(&self, value: T) -> T where T: Integer {
+        let order = self.comparator();
+        sum_at_most(self.get_root().clone(), value, ordering(&order))
+    }
+
+    /// Return the sum of all values between `lo` and `hi`, both included,
+    /// or `0` if `lo > hi`.
+    ///
+    /// Like [prefix_sum_up_to](#method.prefix_sum_up_to) this uses the cached
+    /// subtree sums and takes O(height).
+    ///
+    /// # Panics
+    ///
+    /// Panics on overflow in debug builds, like the `+` operator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in 1..=10 {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{}", tree.range_sum(3, 5));  // 12
+    /// println!("{}", tree.range_sum(5, 3));  // 0
+    /// ```
+    fn range_sum(&self, lo: T, hi: T) -> T where T: Integer {
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            let data = n.borrow().get_data();
+            if cmp(&data, &lo) == Ordering::Less {
+                node = n.borrow().get_right().clone();
+            } else if cmp(&data, &hi) == Ordering::Greater {
+                node = n.borrow().get_left().clone();
+            } else {
+                // every value in range lies below this node
+                let left = n.borrow().get_left().clone();
+                let right = n.borrow().get_right().clone();
+                return sum_at_least(left, lo, cmp) + data + sum_at_most(right, hi, cmp);
+            }
+        }
+        T::ZERO
+    }
+
+    /// Split the values in ascending order into clusters, starting a new
+    /// cluster whenever the gap to the previous value is larger than
+    /// `max_gap`. An empty tree has no clusters.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![1, 2, 3, 10, 11, 20] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.clusters(2));  // [[1, 2, 3], [10, 11], [20]]
+    /// ```
+    fn clusters(&self, max_gap: T) -> Vec<Vec<T>> where T: Integer {
+        let mut clusters: Vec<Vec<T>> = vec![];
+        for v in values_by_ord(self) {
+            let last = clusters.last_mut().filter(|c| {
+                let prev = *c.last().unwrap();
+                v.checked_sub(prev).is_some_and(|gap| gap <= max_gap)
+            });
+            match last {
+                Some(cluster) => cluster.push(v),
+                None => clusters.push(vec![v]),
+            }
+        }
+        clusters
+    }
+
+    /// Return the `(lo, hi)` with the smallest `hi - lo` such that exactly
+    /// `k` values of the tree fall in `[lo, hi]`, taking the lowest window
+    /// on a tie. Returns `None` if `k` is zero or larger than the tree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![1, 10, 12, 13, 30, 31] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.tightest_range_with_count(2));  // Some((12, 13))
+    /// println!("{:?}", tree.tightest_range_with_count(3));  // Some((10, 13))
+    /// println!("{:?}", tree.tightest_range_with_count(7));  // None
+    /// ```
+    fn tightest_range_with_count(&self, k: usize) -> Option<(T, T)> where T: Integer {
+        if k == 0 {
+            return None;
+        }
+        let values = values_by_ord(self);
+        // a span which overflows `T` is wider than any span which fits
+        let mut best: Option<(T, T, Option<T>)> = None;
+        for window in values.windows(k) {
+            let (lo, hi) = (window[0], window[k - 1]);
+            let span = hi.checked_sub(lo);
+            let tighter = match &best {
+                None => true,
+                Some((_, _, best_span)) => match (span, best_span) {
+                    (Some(span), Some(best_span)) => span < *best_span,
+                    (Some(_), None) => true,
+                    (None, _) => false,
+                },
+            };
+            if tighter {
+                best = Some((lo, hi, span));
+            }
+        }
+        best.map(|(lo, hi, _)| (lo, hi))
+    }
+
+    /// Return the value at position `index` (0-based) in ascending order,
+    /// or `None` if the index is out of range.
+    ///
+    /// The trees cache the size of every subtree, so this descends a single
+    /// path from the root in O(height).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.value_at_index(0));  // Some(1)
+    /// println!("{:?}", tree.value_at_index(3));  // Some(4)
+    /// println!("{:?}", tree.value_at_index(5));  // None
+    /// ```
+    fn value_at_index(&self, index: usize) -> Option<T> {
+        let mut index = index;
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            let left_len = n.borrow().get_left().as_ref().map(
+                |l| l.borrow().len()
+            ).unwrap_or(0);
+            if index < left_len {
+                node = n.borrow().get_left().clone();
+            } else if index == left_len {
+                return Some(n.borrow().get_data());
+            } else {
+                index -= left_len + 1;
+                node = n.borrow().get_right().clone();
+            }
+        }
+        None
+    }
+
+    /// Return the `k`-th smallest value, counting from `1`, or `None` if
+    /// `k` is `0` or larger than the number of values.
+    ///
+    /// Like [value_at_index](#method.value_at_index) this takes O(height).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.kth_smallest(1));  // Some(1)
+    /// println!("{:?}", tree.kth_smallest(5));  // Some(6)
+    /// println!("{:?}", tree.kth_smallest(0));  // None
+    /// ```
+    fn kth_smallest(&self, k: usize) -> Option<T> {
+        if k == 0 {
+            return None;
+        }
+        self.value_at_index(k - 1)
+    }
+
+    /// Return the number of values strictly less than `value`, whether or
+    /// not `value` is in the tree.
+    ///
+    /// The trees cache the size of every subtree, so this descends a single
+    /// path from the root in O(height).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{}", tree.rank(4));  // 3
+    /// println!("{}", tree.rank(5));  // 4
+    /// println!("{}", tree.rank(0));  // 0
+    /// ```
+    fn rank(&self, value: T) -> usize {
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        let mut rank = 0;
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            if cmp(&n.borrow().get_data(), &value) == Ordering::Less {
+                rank += n.borrow().get_left().as_ref().map(
+                    |l| l.borrow().len()
+                ).unwrap_or(0) + 1;
+                node = n.borrow().get_right().clone();
+            } else {
+                node = n.borrow().get_left().clone();
+            }
+        }
+        rank
+    }
+
+    /// Return the number of edges from the root down to the node holding
+    /// `value`, the root being at depth 0, or `None` if the value is not in
+    /// the tree. Looking the value up compares it against one more value
+    /// than its depth.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![5, 2, 8, 1] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.depth_of(5));  // Some(0)
+    /// println!("{:?}", tree.depth_of(1));  // Some(2)
+    /// println!("{:?}", tree.depth_of(4));  // None
+    /// ```
+    fn depth_of(&self, value: T) -> Option<usize> {
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        let mut depth = 0;
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            let data = n.borrow().get_data();
+            node = match cmp(&value, &data) {
+                Ordering::Equal => return Some(depth),
+                Ordering::Less => n.borrow().get_left().clone(),
+                Ordering::Greater => n.borrow().get_right().clone(),
+            };
+            depth += 1;
+        }
+        None
+    }
+
+    /// Return the value of the lowest common ancestor of the nodes holding
+    /// `a` and `b`, i.e. the deepest node with both of them in its subtree,
+    /// counting a node as part of its own subtree. Returns `None` if either
+    /// value is not in the tree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![5, 2, 8, 1, 3, 9] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.lca(1, 3));  // Some(2)
+    /// println!("{:?}", tree.lca(3, 9));  // Some(5)
+    /// println!("{:?}", tree.lca(8, 9));  // Some(8)
+    /// println!("{:?}", tree.lca(1, 4));  // None
+    /// ```
+    fn lca(&self, a: T, b: T) -> Option<T> {
+        if !self.contains(a.clone()) || !self.contains(b.clone()) {
+            return None;
+        }
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        let (lo, hi) = if cmp(&a, &b) != Ordering::Greater { (a, b) } else { (b, a) };
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            let data = n.borrow().get_data();
+            node = if cmp(&hi, &data) == Ordering::Less {
+                n.borrow().get_left().clone()
+            } else if cmp(&lo, &data) == Ordering::Greater {
+                n.borrow().get_right().clone()
+            } else {
+                return Some(data);
+            };
+        }
+        None
+    }
+
+    /// Return the first and last index, both included, in ascending order
+    /// of the values in the subtree rooted at the node holding `value`, or
+    /// `None` if the value is not in the tree. A subtree always covers a
+    /// contiguous run of the sorted values.
+    ///
+    /// Like [rank](#method.rank) this counts with the cached subtree sizes,
+    /// in O(height).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![5, 2, 8, 1, 3, 9] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.subtree_index_range(2));  // Some((0, 2))
+    /// println!("{:?}", tree.subtree_index_range(8));  // Some((4, 5))
+    /// println!("{:?}", tree.subtree_index_range(4));  // None
+    /// ```
+    fn subtree_index_range(&self, value: T) -> Option<(usize, usize)> {
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        // the number of values left of the current subtree
+        let mut before = 0;
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            let data = n.borrow().get_data();
+            match cmp(&value, &data) {
+                Ordering::Equal => return Some((before, before + n.borrow().len() - 1)),
+                Ordering::Less => node = n.borrow().get_left().clone(),
+                Ordering::Greater => {
+                    before += n.borrow().get_left().as_ref().map(
+                        |l| l.borrow().len()
+                    ).unwrap_or(0) + 1;
+                    node = n.borrow().get_right().clone();
+                }
+            }
+        }
+        None
+    }
+
+    /// Return how many values lie between `lo` and `hi`, both included, or
+    /// `0` if `lo > hi`.
+    ///
+    /// This counts with the cached subtree sizes like [rank](#method.rank),
+    /// in O(height), without visiting the values in the range.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![5, 2, 8, 1, 3, 9] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{}", tree.count_in_range(2, 8));  // 4
+    /// println!("{}", tree.count_in_range(8, 2));  // 0
+    /// ```
+    fn count_in_range(&self, lo: T, hi: T) -> usize {
+        let order = self.comparator();
+        let cmp = ordering(&order);
+        if cmp(&lo, &hi) == Ordering::Greater {
+            return 0;
+        }
+        // number of values not greater than `hi`
+        let mut at_most_hi = 0;
+        let mut node = self.get_root().clone();
+        while let Some(n) = node {
+            if cmp(&n.borrow().get_data(), &hi) != Ordering::Greater {
+                at_most_hi += n.borrow().get_left().as_ref().map(
+                    |l| l.borrow().len()
+                ).unwrap_or(0) + 1;
+                node = n.borrow().get_right().clone();
+            } else {
+                node = n.borrow().get_left().clone();
+            }
+        }
+        at_most_hi - self.rank(lo)
+    }
+
+    /// Return the median of the tree, or `None` for an empty tree.
+    /// For an even number of values the lower median is returned.
+    ///
+    /// This walks the whole tree; see
+    /// [median_fast](#method.median_fast) for the O(height) version.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.median());  // Some(2)
+    /// ```
+    fn median(&self) -> Option<T> {
+        let values = self.to_vec();
+        if values.is_empty() {
+            return None;
+        }
+        Some(values[(values.len() - 1) / 2].clone())
+    }
+
+    /// Return the same value as [median](#method.median) in O(height) by
+    /// descending directly to the median index using the cached subtree
+    /// sizes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.median_fast());  // Some(2)
+    /// ```
+    fn median_fast(&self) -> Option<T> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        self.value_at_index((len - 1) / 2)
+    }
+
+    /// Return the value at the `p`-th percentile of the sorted values, for
+    /// `p` from `0.0` (the minimum) to `1.0` (the maximum). The index is
+    /// `round(p * (len - 1))`, looked up with
+    /// [value_at_index](#method.value_at_index).
+    ///
+    /// Return `None` for an empty tree or if `p` is not within `0.0..=1.0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut tree = BinarySearchTree::new();
+    /// for v in vec![4, 2, 6, 1, 3] {
+    ///     tree.insert(v);
+    /// }
+    /// println!("{:?}", tree.percentile(0.0));  // Some(1)
+    /// println!("{:?}", tree.percentile(0.5));  // Some(3)
+    /// println!("{:?}", tree.percentile(0.9));  // Some(6)
+    /// ```
+    fn percentile(&self, p: f64) -> Option<T> {
+        let len = self.len();
+        if len == 0 || !(0.0..=1.0).contains(&p) {
+            return None;
+        }
+        self.value_at_index((p * (len - 1) as f64).round() as usize)
+    }
+}
+
+/// Inorder iterator over the values of a tree, created by
+/// [QueryableTree.iter](trait.QueryableTree.html#method.iter)
+///
+/// It can also be walked from the back, in descending order.
+pub struct Iter<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> {
+    front: Vec<Rc<RefCell<QTN>>>,
+    back: Vec<Rc<RefCell<QTN>>>,
+    remaining: usize,
+    marker: PhantomData<T>,
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> Iter<T, QTN> {
+    fn new(root: &Option<Rc<RefCell<QTN>>>) -> Self {
+        let mut iter = Self {
+            front: vec![],
+            back: vec![],
+            remaining: root.as_ref().map(|n| n.borrow().len()).unwrap_or(0),
+            marker: PhantomData,
+        };
+        iter.push_left(root.clone());
+        iter.push_right(root.clone());
+        iter
+    }
+
+    /// Push `node` and its chain of left children onto the front stack
+    fn push_left(&mut self, mut node: Option<Rc<RefCell<QTN>>>) {
+        while let Some(n) = node {
+            node = n.borrow().get_left().clone();
+            self.front.push(n);
+        }
+    }
+
+    /// Push `node` and its chain of right children onto the back stack
+    fn push_right(&mut self, mut node: Option<Rc<RefCell<QTN>>>) {
+        while let Some(n) = node {
+            node = n.borrow().get_right().clone();
+            self.back.push(n);
+        }
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> Iterator for Iter<T, QTN> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let node = self.front.pop()?;
+        let data = node.borrow().get_data();
+        let right = node.borrow().get_right().clone();
+        self.push_left(right);
+        Some(data)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> DoubleEndedIterator for Iter<T, QTN> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let node = self.back.pop()?;
+        let data = node.borrow().get_data();
+        let left = node.borrow().get_left().clone();
+        self.push_right(left);
+        Some(data)
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> ExactSizeIterator for Iter<T, QTN> {}
+
+/// Iterator over the values of a tree within bounds, created by
+/// [QueryableTree.range](trait.QueryableTree.html#method.range)
+pub struct Range<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> {
+    stack: Vec<Rc<RefCell<QTN>>>,
+    hi: T,
+    cmp: Option<Comparator<T>>,
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> Range<T, QTN> {
+    fn new(root: &Option<Rc<RefCell<QTN>>>, lo: T, hi: T, cmp: Option<Comparator<T>>) -> Self {
+        if ordering(&cmp)(&lo, &hi) == Ordering::Greater {
+            return Self { stack: vec![], hi, cmp };
+        }
+        let mut range = Self { stack: vec![], hi, cmp };
+        // push the path to the smallest value not less than `lo`, skipping
+        // the nodes below it and their left subtrees
+        let mut node = root.clone();
+        while let Some(n) = node {
+            if ordering(&range.cmp)(&n.borrow().get_data(), &lo) == Ordering::Less {
+                node = n.borrow().get_right().clone();
+            } else {
+                node = n.borrow().get_left().clone();
+                range.stack.push(n);
+            }
+        }
+        range
+    }
+
+    /// Push `node` and its chain of left children onto the stack
+    fn push_left(&mut self, mut node: Option<Rc<RefCell<QTN>>>) {
+        while let Some(n) = node {
+            node = n.borrow().get_left().clone();
+            self.stack.push(n);
+        }
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> Iterator for Range<T, QTN> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.stack.pop()?;
+        let data = node.borrow().get_data();
+        if ordering(&self.cmp)(&data, &self.hi) == Ordering::Greater {
+            self.stack.clear();
+            return None;
+        }
+        let right = node.borrow().get_right().clone();
+        self.push_left(right);
+        Some(data)
+    }
+}
+
+/// Breadth-first iterator over the values of a tree, created by
+/// [QueryableTree.iter_bfs](trait.QueryableTree.html#method.iter_bfs)
+///
+/// The children of a node are only queued once the node has been yielded.
+pub struct BfsIter<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> {
+    queue: VecDeque<Rc<RefCell<QTN>>>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> Iterator for BfsIter<T, QTN> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.queue.pop_front()?;
+        let node = node.borrow();
+        if let Some(left) = node.get_left() {
+            self.queue.push_back(left.clone());
+        }
+        if let Some(right) = node.get_right() {
+            self.queue.push_back(right.clone());
+        }
+        Some(node.get_data())
+    }
+}
+
+/// Iterator over the union of the values of two trees, created by
+/// [QueryableTree.merge_iter](trait.QueryableTree.html#method.merge_iter)
+pub struct MergeIter<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> {
+    left: Peekable<Iter<T, QTN>>,
+    right: Peekable<Iter<T, QTN>>,
+    cmp: Option<Comparator<T>>,
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> Iterator for MergeIter<T, QTN> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let cmp = ordering(&self.cmp);
+        let order = match (self.left.peek(), self.right.peek()) {
+            (None, None) => return None,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(l), Some(r)) => cmp(l, r),
+        };
+        match order {
+            Ordering::Less => self.left.next(),
+            Ordering::Greater => self.right.next(),
+            Ordering::Equal => {
+                self.right.next();
+                self.left.next()
+            }
+        }
+    }
+}
+
+/// Owning inorder iterator over the values of a tree, created by the
+/// `into_iter` method of the trees.
+///
+/// Nodes are detached from the tree while walking it, so each node is freed
+/// as soon as its value has been yielded.
+pub struct IntoIter<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> {
+    stack: Vec<Rc<RefCell<QTN>>>,
+    marker: PhantomData<T>,
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> IntoIter<T, QTN> {
+    pub(crate) fn new(root: Option<Rc<RefCell<QTN>>>) -> Self {
+        let mut iter = Self { stack: vec![], marker: PhantomData };
+        iter.push_left(root);
+        iter
+    }
+
+    /// Push `node` and its chain of left children onto the stack, detaching
+    /// every left child from its parent
+    fn push_left(&mut self, mut node: Option<Rc<RefCell<QTN>>>) {
+        while let Some(n) = node {
+            node = n.borrow_mut().get_left_mut().take();
+            self.stack.push(n);
+        }
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> Iterator for IntoIter<T, QTN> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let node = self.stack.pop()?;
+        let right = node.borrow_mut().get_right_mut().take();
+        self.push_left(right);
+        let data = node.borrow().get_data();
+        Some(data)
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug, QTN: QueryableTreeNode<T>> Drop for IntoIter<T, QTN> {
+    /// Free the nodes which were not yielded without recursing down the
+    /// right children still attached to them
+    fn drop(&mut self) {
+        for node in self.stack.drain(..) {
+            drop_subtree::<T, _>(Some(node));
         }
     }
 }
@@ -8,140 +8,1375 @@
 //! use trees::base::QueryableTree;
 //! ```
 
-use std::cell::{RefCell};
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::fmt;
+use std::iter::FromIterator;
 use std::cmp::{Ord, Ordering};
+use std::convert::TryInto;
+use std::io;
+use std::hash::Hash;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::base::{QueryableTreeNode, QueryableTree};
+use crate::base::{self, QueryableTreeNode, QueryableTree, TreeOp, IntoIter, BuildError, Comparator};
+use crate::num::{self, Integer};
 
 type RcRefBaseNode<T> = Rc<RefCell<BinarySearchTreeNode<T>>>;
 type BaseNodeLink<T> = Option<RcRefBaseNode<T>>;
 
 /// Node struct for [BinarySearchTree](struct.BinarySearchTree.html) struct
-pub struct BinarySearchTreeNode<T: Ord + Copy + fmt::Debug> {
+pub struct BinarySearchTreeNode<T: Ord + Clone + fmt::Debug> {
     /// Data stored in the node
     pub data: T,
     left: BaseNodeLink<T>,
     right: BaseNodeLink<T>,
+    size: usize,
+    sum: Cell<Option<T>>,
 }
 
-impl <T: Ord + Copy + fmt::Debug> QueryableTreeNode<T> for BinarySearchTreeNode<T> {
+impl <T: Ord + Clone + fmt::Debug> QueryableTreeNode<T> for BinarySearchTreeNode<T> {
     fn get_left(&self) -> &BaseNodeLink<T> { return &self.left; }
     fn get_right(&self) -> &BaseNodeLink<T> { return &self.right; }
-    fn get_data(&self) -> T { return self.data; }
+    fn get_left_mut(&mut self) -> &mut BaseNodeLink<T> { &mut self.left }
+    fn get_right_mut(&mut self) -> &mut BaseNodeLink<T> { &mut self.right }
+    fn get_data(&self) -> T { return self.data.clone(); }
+    fn len(&self) -> usize { self.size }
+    fn get_sum_cache(&self) -> Option<&Cell<Option<T>>> { Some(&self.sum) }
 }
 
-impl <T: Ord + Copy + fmt::Debug> BinarySearchTreeNode<T> {
+impl <T: Ord + Clone + fmt::Debug> BinarySearchTreeNode<T> {
     /// Create an new node, which will be called by [BinarySearchTree](struct.BinarySearchTree.html)
     fn new(data: T) -> BaseNodeLink<T> {
         Some(Rc::new(RefCell::new(Self{
             data,
             left: None,
-            right: None
+            right: None,
+            size: 1,
+            sum: Cell::new(None),
         })))
     }
 
-    /// Insert a node, which will be called by [BinarySearchTree](struct.BinarySearchTree.html)
-    fn insert(&mut self, new_value: T) {
-        if self.data == new_value {
-            return
+    /// Insert a node below `root` and return whether it was added, which will
+    /// be called by [BinarySearchTree](struct.BinarySearchTree.html)
+    ///
+    /// The tree is walked down in a loop rather than by recursion, so
+    /// inserting into a degenerate tree can't overflow the stack. The nodes
+    /// on the way are kept to update their sizes once the value was added.
+    fn insert(root: &Rc<RefCell<Self>>, new_value: T, cmp: &dyn Fn(&T, &T) -> Ordering) -> bool {
+        let mut path = vec![root.clone()];
+        loop {
+            let node = path[path.len() - 1].clone();
+            let mut node = node.borrow_mut();
+            let child = match cmp(&new_value, &node.data) {
+                Ordering::Equal => return false,
+                Ordering::Less => &mut node.left,
+                Ordering::Greater => &mut node.right,
+            };
+            match child {
+                Some(child) => path.push(child.clone()),
+                None => {
+                    *child = Self::new(new_value);
+                    break;
+                }
+            }
+        }
+        for node in path {
+            let mut node = node.borrow_mut();
+            node.size += 1;
+            node.sum.set(None);
+        }
+        true
+    }
+
+    /// Build a height-balanced subtree from sorted and deduplicated values
+    fn from_sorted(values: &[T]) -> BaseNodeLink<T> {
+        if values.is_empty() {
+            return None;
+        }
+        let mid = values.len() / 2;
+        let node = Self::new(values[mid].clone());
+        if let Some(n) = node.as_ref() {
+            n.borrow_mut().left = Self::from_sorted(&values[..mid]);
+            n.borrow_mut().right = Self::from_sorted(&values[mid + 1..]);
+            n.borrow_mut().size = values.len();
+        }
+        node
+    }
+
+    /// Copy the subtree rooted at `node` into freshly allocated nodes
+    fn deep_clone(node: &RcRefBaseNode<T>) -> RcRefBaseNode<T> {
+        base::clone_subtree(node, |node, _| Self {
+            data: node.data.clone(),
+            left: None,
+            right: None,
+            size: node.size,
+            sum: Cell::new(None),
+        })
+    }
+
+    /// Build the subtree rooted at `value` from the remaining `children`
+    /// entries, taking each entry out as it is used so that a value can't
+    /// be placed twice. Return `None` if an entry is missing.
+    fn from_children(value: T, children: &mut BTreeMap<T, (Option<T>, Option<T>)>) -> Option<RcRefBaseNode<T>> {
+        let (left, right) = children.remove(&value)?;
+        let node = Self::new(value).unwrap();
+        let mut size = 1;
+        if let Some(l) = left {
+            let l = Self::from_children(l, children)?;
+            size += l.borrow().size;
+            node.borrow_mut().left = Some(l);
+        }
+        if let Some(r) = right {
+            let r = Self::from_children(r, children)?;
+            size += r.borrow().size;
+            node.borrow_mut().right = Some(r);
+        }
+        node.borrow_mut().size = size;
+        Some(node)
+    }
+
+    /// Build the subtree holding `values[lo..hi]` from the `roots` table of
+    /// [BinarySearchTree::optimal_from](struct.BinarySearchTree.html#method.optimal_from)
+    fn from_roots(values: &[T], roots: &[Vec<usize>], lo: usize, hi: usize) -> BaseNodeLink<T> {
+        if lo == hi {
+            return None;
+        }
+        let r = roots[lo][hi];
+        let node = Self::new(values[r].clone());
+        if let Some(n) = node.as_ref() {
+            n.borrow_mut().left = Self::from_roots(values, roots, lo, r);
+            n.borrow_mut().right = Self::from_roots(values, roots, r + 1, hi);
+            n.borrow_mut().size = hi - lo;
+        }
+        node
+    }
+
+    /// Return the greatest height a height-balanced tree of `size` nodes can
+    /// have. The fewest nodes of such a tree of height `h` follow
+    /// `N(h) = N(h - 1) + N(h - 2) + 1`, so the height is about
+    /// `1.44 log2(size + 2)`.
+    fn max_balanced_height(size: usize) -> usize {
+        let (mut height, mut fewest, mut fewest_below) = (0, 0, 0);
+        while fewest + fewest_below < size {
+            let next = fewest + fewest_below + 1;
+            fewest_below = fewest;
+            fewest = next;
+            height += 1;
+        }
+        height
+    }
+
+    /// Return, for every height `h`, the largest number of nodes which can be
+    /// kept from the subtree at `node` by pruning whole subtrees, such that
+    /// the kept part has height exactly `h` and is height-balanced, or
+    /// `None` if that height is impossible. Heights above
+    /// [max_balanced_height](#method.max_balanced_height) of the subtree
+    /// size are left out, and the subtrees are walked in postorder with an
+    /// explicit stack, so a deep chain costs neither recursion nor a
+    /// quadratic amount of work.
+    fn balanced_keep(node: &BaseNodeLink<T>) -> Vec<Option<usize>> {
+        let get = |v: &Vec<Option<usize>>, h: usize| v.get(h).copied().flatten();
+        // a node is pushed again as expanded once its children are queued,
+        // and the results of its two subtrees are on top of `results` when
+        // it comes up the second time
+        let mut stack = vec![(node.clone(), false)];
+        let mut results: Vec<Vec<Option<usize>>> = vec![];
+        while let Some((link, expanded)) = stack.pop() {
+            let n = match link {
+                None => {
+                    results.push(vec![Some(0)]);
+                    continue;
+                }
+                Some(n) => n,
+            };
+            if !expanded {
+                let (left, right) = (n.borrow().left.clone(), n.borrow().right.clone());
+                stack.push((Some(n), true));
+                stack.push((right, false));
+                stack.push((left, false));
+                continue;
+            }
+            let right = results.pop().unwrap();
+            let left = results.pop().unwrap();
+            let cap = Self::max_balanced_height(n.borrow().size);
+            let mut keep = vec![Some(0)];
+            for h in 1..=left.len().max(right.len()).min(cap) {
+                let mut best = None;
+                let mut pairs = vec![(h - 1, h - 1)];
+                if h >= 2 {
+                    pairs.push((h - 1, h - 2));
+                    pairs.push((h - 2, h - 1));
+                }
+                for (lh, rh) in pairs {
+                    if let (Some(l), Some(r)) = (get(&left, lh), get(&right, rh)) {
+                        best = best.max(Some(l + r + 1));
+                    }
+                }
+                keep.push(best);
+            }
+            results.push(keep);
+        }
+        results.pop().unwrap()
+    }
+
+    /// Recompute the cached size of `node` from its children
+    fn _update_size(node: &RcRefBaseNode<T>) {
+        let mut n = node.borrow_mut();
+        let left_size = n.left.as_ref().map_or(0, |l| l.borrow().size);
+        let right_size = n.right.as_ref().map_or(0, |r| r.borrow().size);
+        n.size = left_size + right_size + 1;
+        n.sum.set(None);
+    }
+
+    /// Rotate the subtree rooted at `node` to the right and return its new
+    /// root, the former left child
+    fn _rotate_right(node: RcRefBaseNode<T>) -> RcRefBaseNode<T> {
+        let left = node.borrow_mut().left.take().unwrap();
+        node.borrow_mut().left = left.borrow_mut().right.take();
+        Self::_update_size(&node);
+        left.borrow_mut().right = Some(node);
+        Self::_update_size(&left);
+        left
+    }
+
+    /// Rotate the subtree rooted at `node` to the left and return its new
+    /// root, the former right child
+    fn _rotate_left(node: RcRefBaseNode<T>) -> RcRefBaseNode<T> {
+        let right = node.borrow_mut().right.take().unwrap();
+        node.borrow_mut().right = right.borrow_mut().left.take();
+        Self::_update_size(&node);
+        right.borrow_mut().left = Some(node);
+        Self::_update_size(&right);
+        right
+    }
+
+    /// Turn the subtree rooted at `root` into a "vine" where no node has a
+    /// left child, by rotating right until every left child is gone, and
+    /// return the head of the vine
+    fn _tree_to_vine(mut root: RcRefBaseNode<T>) -> RcRefBaseNode<T> {
+        while root.borrow().left.is_some() {
+            root = Self::_rotate_right(root);
         }
-        let new_node =
-            if new_value < self.data {&mut self.left}
-            else {&mut self.right};
-        match new_node {
-            Some(node) => node.borrow_mut().insert(new_value),
-            None => {
-                *new_node = Self::new(new_value);
+        let mut tail = root.clone();
+        loop {
+            let rest = tail.borrow().right.clone();
+            match rest {
+                None => break,
+                Some(rest) if rest.borrow().left.is_some() => {
+                    tail.borrow_mut().right = Some(Self::_rotate_right(rest));
+                }
+                Some(rest) => tail = rest,
             }
         }
+        root
     }
 
-    fn _delete_node_have_two_children(left: &RcRefBaseNode<T>) {
+    /// Rotate left `count` times down the vine starting at `head`, every
+    /// other node along it, and return the new head
+    fn _compress(head: RcRefBaseNode<T>, count: usize) -> RcRefBaseNode<T> {
+        if count == 0 {
+            return head;
+        }
+        let head = Self::_rotate_left(head);
+        let mut scanner = head.clone();
+        for _ in 1..count {
+            let child = scanner.borrow_mut().right.take().unwrap();
+            let child = Self::_rotate_left(child);
+            scanner.borrow_mut().right = Some(child.clone());
+            scanner = child;
+        }
+        head
+    }
+
+    fn _delete_node_have_two_children(left: &RcRefBaseNode<T>, cmp: &dyn Fn(&T, &T) -> Ordering) {
         let right_min = left.borrow().right.as_ref().unwrap().borrow().min();
-        left.borrow_mut().delete(right_min);
+        left.borrow_mut().delete(right_min.clone(), cmp);
         left.borrow_mut().data = right_min;
     }
 
-    fn _delete_right(&mut self, val: T) {
-        if let Some(right) = self.right.as_ref() {
-            if right.borrow().data == val {
-                if right.borrow().left.is_none() && right.borrow().right.is_none() {
-                    self.right = None;
-                } else if right.borrow().left.is_none() && !right.borrow().right.is_none() {
-                    self.right.take().map(|node| {
-                        self.right = node.borrow().right.clone()
-                    });
-                } else if !right.borrow().left.is_none() && right.borrow().right.is_none() {
-                    self.right.take().map(|node| {
-                        self.right = node.borrow().left.clone()
-                    });
-                } else {
-                    Self::_delete_node_have_two_children(right);
-                }
+    /// Delete the node in `child`, which compares equal to the value to
+    /// delete, and return its value
+    fn _delete_child(child: &mut BaseNodeLink<T>, cmp: &dyn Fn(&T, &T) -> Ordering) -> T {
+        let node = child.clone().unwrap();
+        let removed = node.borrow().data.clone();
+        let (left, right) = (node.borrow().left.clone(), node.borrow().right.clone());
+        match (left, right) {
+            (Some(_), Some(_)) => Self::_delete_node_have_two_children(&node, cmp),
+            (left, None) => *child = left,
+            (None, right) => *child = right,
+        }
+        removed
+    }
+
+    fn _delete_right(&mut self, val: T, cmp: &dyn Fn(&T, &T) -> Ordering) -> Option<T> {
+        let right = self.right.clone()?;
+        if cmp(&right.borrow().data, &val) == Ordering::Equal {
+            Some(Self::_delete_child(&mut self.right, cmp))
+        } else {
+            right.borrow_mut().delete(val, cmp)
+        }
+    }
+
+    fn _delete_left(&mut self, val: T, cmp: &dyn Fn(&T, &T) -> Ordering) -> Option<T> {
+        let left = self.left.clone()?;
+        if cmp(&left.borrow().data, &val) == Ordering::Equal {
+            Some(Self::_delete_child(&mut self.left, cmp))
+        } else {
+            left.borrow_mut().delete(val, cmp)
+        }
+    }
+
+    /// Delete a node below the current one and return the stored value which
+    /// was removed, which will be called by
+    /// [BinarySearchTree](struct.BinarySearchTree.html)
+    fn delete(&mut self, val: T, cmp: &dyn Fn(&T, &T) -> Ordering) -> Option<T> {
+        let deleted = match cmp(&self.data, &val) {
+            Ordering::Greater => self._delete_left(val, cmp),
+            Ordering::Less => self._delete_right(val, cmp),
+            _ => unreachable!(),
+        };
+        if deleted.is_some() {
+            self.size -= 1;
+            self.sum.set(None);
+        }
+        deleted
+    }
+}
+
+/// An implementation of [Binary Search Tree](https://en.wikipedia.org/wiki/Binary_search_tree)
+pub struct BinarySearchTree<T: Ord + Clone + fmt::Debug> {
+    root: BaseNodeLink<T>,
+    history: Option<Vec<T>>,
+    comparator: Option<Comparator<T>>,
+}
+
+impl<T: Ord + Clone + fmt::Debug> Drop for BinarySearchTree<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl <T: Ord + Clone + fmt::Debug> QueryableTree<T, BinarySearchTreeNode<T>> for BinarySearchTree<T> {
+    fn get_root(&self) -> &BaseNodeLink<T> {
+        &self.root
+    }
+
+    fn comparator(&self) -> Option<Comparator<T>> {
+        self.comparator.clone()
+    }
+
+    fn contains(&self, value: T) -> bool {
+        self.get(value).is_some()
+    }
+
+    fn get(&self, value: T) -> Option<T> {
+        let cmp = self.ordering();
+        let mut node = self.root.clone();
+        while let Some(n) = node {
+            let n = n.borrow();
+            node = match cmp(&value, &n.data) {
+                Ordering::Equal => return Some(n.data.clone()),
+                Ordering::Less => n.left.clone(),
+                Ordering::Greater => n.right.clone(),
+            };
+        }
+        None
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> BinarySearchTree<T> {
+    /// Create a new Binary Search Tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst: BinarySearchTree<i32> = BinarySearchTree::new();
+    /// ```
+    pub fn new() -> Self {
+        Self{ root: None, history: None, comparator: None }
+    }
+
+    /// Create a new Binary Search Tree which records every value passed to
+    /// [insert](#method.insert), see [insertion_history](#method.insertion_history)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst: BinarySearchTree<i32> = BinarySearchTree::with_history();
+    /// ```
+    pub fn with_history() -> Self {
+        Self{ root: None, history: Some(vec![]), comparator: None }
+    }
+
+    /// Create a new Binary Search Tree ordered by `cmp` instead of `Ord`,
+    /// e.g. in reverse or by a key of the values.
+    ///
+    /// Every query which compares values goes by `cmp`, so `min` returns the
+    /// first value by `cmp`, `range` and `rank` count in its order and
+    /// `is_bst` checks it, and the inorder traversals follow it. Only the
+    /// queries measuring the values themselves, such as `clusters`,
+    /// `content_hash` and equality with other trees, use `Ord`.
+    ///
+    /// `T` must still implement `Ord`, even though `cmp` places the values:
+    /// every tree and node of the crate, and the
+    /// [QueryableTree](../base/trait.QueryableTree.html) trait they share,
+    /// require it, and the queries above fall back on it.
+    ///
+    /// `cmp` must be a strict weak ordering which does not change while the
+    /// tree is alive, or values may become unreachable. Values which `cmp`
+    /// considers equal are stored only once.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst = BinarySearchTree::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+    /// bst.insert(1);
+    /// bst.insert(3);
+    /// bst.insert(2);
+    /// println!("{:?}", bst.to_vec());  // [3, 2, 1]
+    /// ```
+    pub fn with_comparator(cmp: impl Fn(&T, &T) -> Ordering + 'static) -> Self {
+        Self{ root: None, history: None, comparator: Some(Rc::new(cmp)) }
+    }
+
+    /// Return the ordering used to place values, which is the comparator of
+    /// [with_comparator](#method.with_comparator) or else `Ord`
+    fn ordering(&self) -> &dyn Fn(&T, &T) -> Ordering {
+        base::ordering(&self.comparator)
+    }
+
+    /// Build a height-balanced tree from values in strictly increasing order
+    /// in O(n), taking the middle value as the root of every subtree, so the
+    /// height is the smallest possible.
+    ///
+    /// The slice must be sorted and deduplicated. That is not checked, use
+    /// [try_from_sorted](#method.try_from_sorted) for unchecked input.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let bst = BinarySearchTree::from_sorted(&[1, 2, 3, 4, 5, 6, 7]);
+    /// println!("{}", bst.height());  // 3
+    /// ```
+    pub fn from_sorted(data: &[T]) -> Self {
+        Self{ root: BinarySearchTreeNode::from_sorted(data), history: None, comparator: None }
+    }
+
+    /// Build a height-balanced tree from values in strictly increasing order,
+    /// or return why the values are not usable
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let bst = BinarySearchTree::try_from_sorted(&[1, 2, 3]).unwrap();
+    /// println!("{:?}", bst.to_vec());  // [1, 2, 3]
+    /// println!("{:?}", BinarySearchTree::try_from_sorted(&[1, 3, 2]).err());  // Some(NotSorted { index: 2 })
+    /// println!("{:?}", BinarySearchTree::try_from_sorted(&[1, 2, 2]).err());  // Some(Duplicate { value: 2 })
+    /// ```
+    pub fn try_from_sorted(data: &[T]) -> Result<Self, BuildError<T>> {
+        for (i, pair) in data.windows(2).enumerate() {
+            match pair[0].cmp(&pair[1]) {
+                Ordering::Less => (),
+                Ordering::Equal => return Err(BuildError::Duplicate { value: pair[1].clone() }),
+                Ordering::Greater => return Err(BuildError::NotSorted { index: i + 1 }),
+            }
+        }
+        Ok(Self::from_sorted(data))
+    }
+
+    /// Rebuild a tree from the `(value, left child, right child)` triples of
+    /// [QueryableTree::to_triples](../base/trait.QueryableTree.html#method.to_triples),
+    /// the first triple being the root. Return `None` if the triples don't
+    /// describe exactly one binary search tree: a child without its own
+    /// triple, a value used twice, a triple never reached from the root, or
+    /// values out of order.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let bst = BinarySearchTree::from_triples(&[(2, Some(1), None), (1, None, None)]).unwrap();
+    /// println!("{:?}", bst.preorder());  // [2, 1]
+    /// println!("{}", BinarySearchTree::from_triples(&[(2, None, Some(1)), (1, None, None)]).is_none());  // true
+    /// ```
+    pub fn from_triples(triples: &[(T, Option<T>, Option<T>)]) -> Option<Self> {
+        let mut children = BTreeMap::new();
+        for (value, left, right) in triples {
+            if children.insert(value.clone(), (left.clone(), right.clone())).is_some() {
+                return None;
+            }
+        }
+        let root = match triples.first() {
+            None => None,
+            Some((value, _, _)) => Some(BinarySearchTreeNode::from_children(value.clone(), &mut children)?),
+        };
+        let tree = Self{ root, history: None, comparator: None };
+        if !children.is_empty() || !tree.is_valid() {
+            return None;
+        }
+        Some(tree)
+    }
+
+    /// Build the tree with the smallest
+    /// [weighted_path_cost](fn.weighted_path_cost.html) for the given access
+    /// frequencies, so frequently accessed values sit near the root.
+    ///
+    /// The values do not need to be sorted; the frequencies of repeated
+    /// values are added up. This is the classic dynamic programming
+    /// construction with Knuth's bound on the root positions, which takes
+    /// `O(n^2)` time and memory.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let bst = BinarySearchTree::optimal_from(&[(1, 1), (2, 1), (3, 10)]);
+    /// println!("{:?}", bst.preorder());  // [3, 1, 2]
+    /// ```
+    pub fn optimal_from(values_with_freq: &[(T, u64)]) -> Self {
+        let mut pairs = values_with_freq.to_vec();
+        pairs.sort_by_key(|p| p.0.clone());
+        let mut values: Vec<T> = Vec::with_capacity(pairs.len());
+        let mut freq: Vec<u64> = Vec::with_capacity(pairs.len());
+        for (v, f) in pairs {
+            if values.last() == Some(&v) {
+                *freq.last_mut().unwrap() += f;
             } else {
-                right.borrow_mut().delete(val);
+                values.push(v);
+                freq.push(f);
             }
         }
+
+        // cost[i][j] and roots[i][j] describe the best subtree of values[i..j]
+        let n = values.len();
+        let mut prefix = vec![0u64; n + 1];
+        for i in 0..n {
+            prefix[i + 1] = prefix[i] + freq[i];
+        }
+        let mut cost = vec![vec![0u64; n + 1]; n + 1];
+        let mut roots = vec![vec![0usize; n + 1]; n + 1];
+        for i in 0..n {
+            cost[i][i + 1] = freq[i];
+            roots[i][i + 1] = i;
+        }
+        for len in 2..=n {
+            for i in 0..=n - len {
+                let j = i + len;
+                let mut best = (u64::MAX, i);
+                for r in roots[i][j - 1]..=roots[i + 1][j] {
+                    let c = cost[i][r] + cost[r + 1][j];
+                    if c < best.0 {
+                        best = (c, r);
+                    }
+                }
+                cost[i][j] = best.0 + prefix[j] - prefix[i];
+                roots[i][j] = best.1;
+            }
+        }
+        Self{ root: BinarySearchTreeNode::from_roots(&values, &roots, 0, n), history: None, comparator: None }
+    }
+
+    /// Insert a new value to the tree, returning `true` if it was added and
+    /// `false` if an equal value was already present
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// assert!(bst.insert(1));
+    /// assert!(!bst.insert(1));
+    /// ```
+    pub fn insert(&mut self, new_val: T) -> bool {
+        if let Some(history) = self.history.as_mut() {
+            history.push(new_val.clone());
+        }
+        if self.root.is_none() {
+            self.root = Some(Rc::new(RefCell::new(BinarySearchTreeNode{
+                data: new_val,
+                left: None,
+                right: None,
+                size: 1,
+                sum: Cell::new(None),
+            })));
+            true
+        } else {
+            let cmp = self.ordering();
+            BinarySearchTreeNode::insert(self.root.as_ref().unwrap(), new_val, cmp)
+        }
+    }
+    /// Delete a value from the tree, returning the stored value which was
+    /// removed or `None` if there was no equal value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(1);
+    /// assert_eq!(bst.delete(1), Some(1));
+    /// assert_eq!(bst.delete(1), None);
+    /// ```
+    pub fn delete(&mut self, val: T) -> Option<T> {
+        let mut root = self.root.take();
+        let cmp = self.ordering();
+        let removed = match root.clone() {
+            None => None,
+            Some(node) if cmp(&node.borrow().data, &val) == Ordering::Equal => {
+                Some(BinarySearchTreeNode::_delete_child(&mut root, cmp))
+            }
+            Some(node) => node.borrow_mut().delete(val, cmp),
+        };
+        self.root = root;
+        removed
+    }
+
+    /// Remove every value from the tree, keeping the tree itself for reuse.
+    /// The nodes are freed one at a time, so clearing a degenerate tree
+    /// can't overflow the stack. A recorded insertion history is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(1);
+    /// bst.insert(2);
+    /// bst.clear();
+    /// assert!(bst.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        base::drop_subtree::<T, _>(self.root.take());
+    }
+
+    /// Consume the tree and return its values in ascending order. The nodes
+    /// are freed while the values are collected, see
+    /// [into_iter](#method.into_iter).
+    ///
+    /// A tree built [with a comparator](#method.with_comparator) yields its
+    /// values in the order of the comparator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// for &v in [3, 1, 2].iter() {
+    ///     bst.insert(v);
+    /// }
+    /// assert_eq!(bst.into_sorted_vec(), vec![1, 2, 3]);
+    /// ```
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.into_iter().collect()
+    }
+
+    /// Turn the tree into its mirror image by swapping the two children of
+    /// every node. The values are then stored in descending order, so
+    /// [iter](../base/trait.QueryableTree.html#method.iter) and the other
+    /// traversals yield them reversed, and lookups like `contains` or
+    /// `delete` can't find them any more until the tree is mirrored back.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst: BinarySearchTree<i32> = (1..=5).collect();
+    /// bst.mirror();
+    /// println!("{:?}", bst.to_vec());  // [5, 4, 3, 2, 1]
+    /// bst.mirror();
+    /// println!("{:?}", bst.to_vec());  // [1, 2, 3, 4, 5]
+    /// ```
+    pub fn mirror(&mut self) {
+        base::mirror_subtree::<T, _>(&self.root);
+    }
+
+    /// Remove the smallest value from the tree and return it, or `None` if
+    /// the tree is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(2);
+    /// bst.insert(1);
+    /// assert_eq!(bst.pop_min(), Some(1));
+    /// assert_eq!(bst.pop_min(), Some(2));
+    /// assert_eq!(bst.pop_min(), None);
+    /// ```
+    pub fn pop_min(&mut self) -> Option<T> {
+        let min = self.min()?;
+        self.delete(min.clone());
+        Some(min)
+    }
+
+    /// Insert `val`, then if the tree holds more than `window` values remove
+    /// the smallest one and return it, so that streaming values through a
+    /// tree keeps the `window` largest ones seen. At most one value is
+    /// removed per call.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// assert_eq!(bst.insert_windowed(3, 2), None);
+    /// assert_eq!(bst.insert_windowed(1, 2), None);
+    /// assert_eq!(bst.insert_windowed(2, 2), Some(1));
+    /// println!("{:?}", bst.to_vec());  // [2, 3]
+    /// ```
+    pub fn insert_windowed(&mut self, val: T, window: usize) -> Option<T> {
+        self.insert(val);
+        if self.len() > window {
+            self.pop_min()
+        } else {
+            None
+        }
+    }
+
+    /// Remove the largest value from the tree and return it, or `None` if
+    /// the tree is empty.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(1);
+    /// bst.insert(2);
+    /// assert_eq!(bst.pop_max(), Some(2));
+    /// assert_eq!(bst.pop_max(), Some(1));
+    /// assert_eq!(bst.pop_max(), None);
+    /// ```
+    pub fn pop_max(&mut self) -> Option<T> {
+        let max = self.max()?;
+        self.delete(max.clone());
+        Some(max)
+    }
+
+    /// Move every value not less than `key` into a new tree and return it,
+    /// keeping the smaller values. Both halves are rebuilt height-balanced from
+    /// their sorted values. The returned tree doesn't record any history.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst: BinarySearchTree<i32> = (1..=6).collect();
+    /// let upper = bst.split_off(4);
+    /// println!("{:?}", bst.to_vec());  // [1, 2, 3]
+    /// println!("{:?}", upper.to_vec());  // [4, 5, 6]
+    /// ```
+    pub fn split_off(&mut self, key: T) -> Self {
+        let cmp = self.ordering();
+        let mut lower = self.to_vec();
+        let upper = lower.split_off(lower.partition_point(|v| cmp(v, &key) == Ordering::Less));
+        self.clear();
+        self.root = BinarySearchTreeNode::from_sorted(&lower);
+        Self{ root: BinarySearchTreeNode::from_sorted(&upper), history: None, comparator: self.comparator.clone() }
+    }
+
+    /// Rebalance the tree in place with the
+    /// [Day-Stout-Warren algorithm](https://en.wikipedia.org/wiki/Day%E2%80%93Stout%E2%80%93Warren_algorithm):
+    /// the tree is first flattened into a right-leaning "vine" with right
+    /// rotations, then folded back with rounds of left rotations. This takes
+    /// O(n) time and, unlike rebuilding from the sorted values, no extra
+    /// memory. Afterwards the height is `ceil(log2(len + 1))`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// for v in 0..7 {
+    ///     bst.insert(v);
+    /// }
+    /// println!("{}", bst.height());  // 7
+    /// bst.rebalance();
+    /// println!("{}", bst.height());  // 3
+    /// ```
+    pub fn rebalance(&mut self) {
+        let root = match self.root.take() {
+            None => return,
+            Some(root) => root,
+        };
+        let len = root.borrow().size;
+        let mut head = BinarySearchTreeNode::_tree_to_vine(root);
+        // the number of nodes in the largest perfect tree not larger than
+        // `len`, the rest hang off its bottom level
+        let mut perfect = (1usize << (usize::BITS - 1 - (len + 1).leading_zeros())) - 1;
+        head = BinarySearchTreeNode::_compress(head, len - perfect);
+        while perfect > 1 {
+            perfect /= 2;
+            head = BinarySearchTreeNode::_compress(head, perfect);
+        }
+        self.root = Some(head);
+    }
+
+    /// Return the values passed to [insert](#method.insert) in call order,
+    /// or `None` if the tree was not created by
+    /// [with_history](#method.with_history).
+    ///
+    /// Every call is recorded, including values which were already in the
+    /// tree and therefore did not change it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::with_history();
+    /// bst.insert(2);
+    /// bst.insert(1);
+    /// bst.insert(2);
+    /// assert_eq!(bst.insertion_history(), Some(&[2, 1, 2][..]));
+    /// assert_eq!(BinarySearchTree::<i32>::new().insertion_history(), None);
+    /// ```
+    pub fn insertion_history(&self) -> Option<&[T]> {
+        self.history.as_deref()
+    }
+
+    /// Delete every value for which `pred` returns `true`, and return how
+    /// many values were deleted
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// for v in 0..10 {
+    ///     bst.insert(v);
+    /// }
+    /// assert_eq!(bst.remove_where(|v| v % 3 == 0), 4);
+    /// assert_eq!(bst.to_vec(), vec![1, 2, 4, 5, 7, 8]);
+    /// ```
+    pub fn remove_where<F: Fn(&T) -> bool>(&mut self, pred: F) -> usize {
+        let values: Vec<T> = self.iter().collect();
+        let survivors: Vec<T> = values.iter().filter(|v| !pred(v)).cloned().collect();
+        let removed = values.len() - survivors.len();
+        if removed > 0 {
+            self.clear();
+            self.root = BinarySearchTreeNode::from_sorted(&survivors);
+        }
+        removed
+    }
+
+    /// Apply `ops` in order and return whether the tree was still
+    /// [valid](../base/trait.QueryableTree.html#method.is_valid) after every
+    /// step. Stops at the first step which leaves the tree invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::TreeOp;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// assert!(bst.apply_ops(&[TreeOp::Insert(2), TreeOp::Insert(1), TreeOp::Delete(2)]));
+    /// ```
+    pub fn apply_ops(&mut self, ops: &[TreeOp<T>]) -> bool {
+        for op in ops {
+            match op {
+                TreeOp::Insert(val) => { self.insert(val.clone()); }
+                TreeOp::Delete(val) => { self.delete(val.clone()); }
+            }
+            if !self.is_valid() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Apply `f` to the value stored in the root, and return whether the tree
+    /// had to be rebuilt.
+    ///
+    /// If the new value still lies between the largest value of the left
+    /// subtree and the smallest value of the right subtree, it is updated in
+    /// place. Otherwise the tree is rebuilt as a balanced tree from its
+    /// values, and a value that became a duplicate is only kept once.
+    /// Nothing happens to an empty tree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(5);
+    /// bst.insert(3);
+    /// bst.insert(7);
+    /// assert!(!bst.map_root(|v| v + 1));
+    /// assert!(bst.map_root(|v| v * 2));
+    /// assert_eq!(bst.to_vec(), vec![3, 7, 12]);
+    /// ```
+    pub fn map_root<F: FnOnce(T) -> T>(&mut self, f: F) -> bool {
+        let root = match self.root.as_ref() {
+            None => return false,
+            Some(root) => root,
+        };
+        let cmp = self.ordering();
+        let new_val = f(root.borrow().data.clone());
+        root.borrow_mut().data = new_val.clone();
+        root.borrow().sum.set(None);
+        let above_left = root.borrow().left.as_ref().is_none_or(
+            |l| cmp(&l.borrow().max(), &new_val) == Ordering::Less
+        );
+        let below_right = root.borrow().right.as_ref().is_none_or(
+            |r| cmp(&r.borrow().min(), &new_val) == Ordering::Greater
+        );
+        if above_left && below_right {
+            return false;
+        }
+        let mut values: Vec<T> = self.iter().collect();
+        values.sort_by(|a, b| cmp(a, b));
+        values.dedup_by(|a, b| cmp(a, b) == Ordering::Equal);
+        self.clear();
+        self.root = BinarySearchTreeNode::from_sorted(&values);
+        true
+    }
+
+    /// Walk the values in ascending order and replace every two adjacent
+    /// values for which `merge` returns `Some(combined)` with `combined`,
+    /// then rebuild the tree as a balanced tree if anything was merged.
+    ///
+    /// A combined value is merged again with the value following it, so a
+    /// whole run of mergeable values collapses into one. This is handy when
+    /// `T` represents an interval ordered by its bounds.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// for v in vec![1, 2, 3, 7, 8, 12] {
+    ///     bst.insert(v);
+    /// }
+    /// // keep the larger of two values that are one apart
+    /// bst.coalesce(|a, b| if *b - *a == 1 { Some(*b) } else { None });
+    /// assert_eq!(bst.to_vec(), vec![3, 8, 12]);
+    /// ```
+    pub fn coalesce<F: Fn(&T, &T) -> Option<T>>(&mut self, merge: F) {
+        let values: Vec<T> = self.iter().collect();
+        let mut merged: Vec<T> = Vec::with_capacity(values.len());
+        for v in values.iter() {
+            let combined = merged.last().and_then(|last| merge(last, v));
+            match combined {
+                Some(c) => *merged.last_mut().unwrap() = c,
+                None => merged.push(v.clone()),
+            }
+        }
+        if merged.len() < values.len() {
+            let cmp = self.ordering();
+            merged.sort_by(|a, b| cmp(a, b));
+            merged.dedup_by(|a, b| cmp(a, b) == Ordering::Equal);
+            self.clear();
+            self.root = BinarySearchTreeNode::from_sorted(&merged);
+        }
+    }
+
+    /// Return the fewest nodes which have to be removed so that the tree
+    /// becomes height-balanced, i.e. the heights of the two subtrees of
+    /// every node differ by at most one.
+    ///
+    /// Removing a node here means cutting it off together with its whole
+    /// subtree, so the shape of the remaining nodes is left untouched and
+    /// the answer is `len()` minus the size of the largest balanced part of
+    /// the tree that contains the root. Rebuilding the tree from its values
+    /// is not considered, since that would always balance it without any
+    /// removal.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// for v in 1..=5 {
+    ///     bst.insert(v);
+    /// }
+    /// // only 1 -> 2 can be kept from the chain
+    /// assert_eq!(bst.min_removals_to_balance(), 3);
+    /// ```
+    pub fn min_removals_to_balance(&self) -> usize {
+        let keep = BinarySearchTreeNode::balanced_keep(&self.root);
+        self.len() - keep.into_iter().flatten().max().unwrap_or(0)
+    }
+
+    /// Return the height of the tree divided by the smallest height any
+    /// binary tree holding the same number of values can have, i.e. the
+    /// height of a balanced tree like an [AVLTree](../avltree/struct.AVLTree.html)
+    /// built from the same values. A value close to `1.0` means the tree is
+    /// well balanced, large values mean it degenerates towards a list. The
+    /// empty tree returns `1.0`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// for v in 1..=7 {
+    ///     bst.insert(v);
+    /// }
+    /// // a chain of height 7, where 3 would be enough
+    /// println!("{}", bst.height_ratio_vs_optimal());  // 2.3333333333333335
+    /// ```
+    pub fn height_ratio_vs_optimal(&self) -> f64 {
+        let len = self.len();
+        if len == 0 {
+            return 1.0;
+        }
+        // ceil(log2(len + 1)), the number of levels needed for `len` nodes
+        let minimal_height = (usize::BITS - len.leading_zeros()) as f64;
+        self.height() as f64 / minimal_height
+    }
+
+    /// Move the values into a new, balanced [AVLTree](../avltree/struct.AVLTree.html),
+    /// built directly from the sorted values in O(n). With a custom
+    /// comparator the values are sorted again by their own order first,
+    /// keeping one of each equal values.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// for v in 0..7 {
+    ///     bst.insert(v);
+    /// }
+    /// let avl = bst.into_avl();
+    /// println!("{}", avl.height());  // 3
+    /// ```
+    pub fn into_avl(self) -> crate::avltree::AVLTree<T> {
+        let custom_order = self.comparator.is_some();
+        let mut values: Vec<T> = self.into_iter().collect();
+        if custom_order {
+            values.sort();
+            values.dedup();
+        }
+        crate::avltree::AVLTree::from_sorted(&values)
+    }
+
+    /// Return the number of structurally distinct binary search trees which
+    /// could hold the values of this tree, see
+    /// [catalan_tree_count](fn.catalan_tree_count.html)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(1);
+    /// bst.insert(2);
+    /// bst.insert(3);
+    /// assert_eq!(bst.possible_shapes(), 5);
+    /// ```
+    pub fn possible_shapes(&self) -> u128 {
+        catalan_tree_count(self.len())
+    }
+}
+
+/// Return the `n`-th [Catalan number](https://en.wikipedia.org/wiki/Catalan_number),
+/// i.e. the number of distinct binary search tree shapes for `n` distinct
+/// keys.
+///
+/// The intermediate products are reduced before multiplying, so no
+/// intermediate value overflows before the result does.
+///
+/// # Panics
+///
+/// Panics if the result does not fit in a `u128`, i.e. for `n > 69`.
+///
+/// # Example
+///
+/// ```
+/// use trees::bstree::catalan_tree_count;
+///
+/// assert_eq!(catalan_tree_count(4), 14);
+/// ```
+pub fn catalan_tree_count(n: usize) -> u128 {
+    fn gcd(a: u128, b: u128) -> u128 {
+        if b == 0 { a } else { gcd(b, a % b) }
+    }
+
+    // C(k + 1) = C(k) * 2(2k + 1) / (k + 2), where the division is exact
+    let mut count: u128 = 1;
+    for k in 0..n as u128 {
+        let numerator = 2 * (2 * k + 1);
+        let denominator = k + 2;
+        let g = gcd(count, denominator);
+        count = (count / g)
+            .checked_mul(numerator / (denominator / g))
+            .expect("Catalan number does not fit in u128");
+    }
+    count
+}
+
+/// Return the total number of comparisons needed to look up every value
+/// `freq[value]` times, i.e. the sum of `depth(node) * freq[value]` over all
+/// nodes, where the root has depth 1.
+///
+/// Values missing from `freq` are never accessed and cost nothing.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use trees::bstree::{BinarySearchTree, weighted_path_cost};
+///
+/// let mut bst = BinarySearchTree::new();
+/// bst.insert(2);
+/// bst.insert(1);
+/// bst.insert(3);
+/// let freq: HashMap<_, _> = vec![(1, 5), (2, 10), (3, 1)].into_iter().collect();
+/// assert_eq!(weighted_path_cost(&bst, &freq), 10 + 2 * 5 + 2 * 1);
+/// ```
+pub fn weighted_path_cost<T: Ord + Clone + fmt::Debug + Hash>(
+    tree: &BinarySearchTree<T>, freq: &HashMap<T, u64>
+) -> u64 {
+    let mut cost = 0;
+    let mut stack: Vec<(RcRefBaseNode<T>, u64)> = tree.root.iter().map(|n| (n.clone(), 1)).collect();
+    while let Some((node, depth)) = stack.pop() {
+        let node = node.borrow();
+        cost += depth * freq.get(&node.data).copied().unwrap_or(0);
+        for child in node.left.iter().chain(node.right.iter()) {
+            stack.push((child.clone(), depth + 1));
+        }
+    }
+    cost
+}
+
+/// Trees are equal when they hold the same values, whatever their shapes.
+/// This is content equality, not structural equality; compare
+/// [to_debug_nested](../base/trait.QueryableTree.html#method.to_debug_nested)
+/// to check the shapes as well.
+///
+/// # Example
+///
+/// ```
+/// use trees::prelude::*;
+///
+/// let a: BinarySearchTree<i32> = vec![1, 2, 3].into_iter().collect();
+/// let b: BinarySearchTree<i32> = vec![3, 2, 1].into_iter().collect();
+/// let avl: AVLTree<i32> = vec![2, 1, 3].into_iter().collect();
+/// assert!(a == b);
+/// assert!(a == avl);
+/// ```
+impl<T: Ord + Clone + fmt::Debug> PartialEq<BinarySearchTree<T>> for BinarySearchTree<T> {
+    fn eq(&self, other: &BinarySearchTree<T>) -> bool {
+        base::same_values(self, other)
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> Eq for BinarySearchTree<T> {}
+
+/// Content equality with an [AVLTree](../avltree/struct.AVLTree.html)
+impl<T: Ord + Clone + fmt::Debug> PartialEq<crate::avltree::AVLTree<T>> for BinarySearchTree<T> {
+    fn eq(&self, other: &crate::avltree::AVLTree<T>) -> bool {
+        base::same_values(self, other)
+    }
+}
+
+/// Content equality with a [RedBlackTree](../rbtree/struct.RedBlackTree.html)
+impl<T: Ord + Clone + fmt::Debug> PartialEq<crate::rbtree::RedBlackTree<T>> for BinarySearchTree<T> {
+    fn eq(&self, other: &crate::rbtree::RedBlackTree<T>) -> bool {
+        base::same_values(self, other)
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> Default for BinarySearchTree<T> {
+    /// Create an empty tree, same as [new](#method.new)
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> Clone for BinarySearchTree<T> {
+    /// Copy the tree into freshly allocated nodes, so changing the copy
+    /// leaves the original alone
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(1);
+    /// let mut copy = bst.clone();
+    /// copy.insert(2);
+    /// assert_eq!(bst.to_vec(), vec![1]);
+    /// ```
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.as_ref().map(BinarySearchTreeNode::deep_clone),
+            history: self.history.clone(),
+            comparator: self.comparator.clone(),
+        }
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> IntoIterator for BinarySearchTree<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T, BinarySearchTreeNode<T>>;
+
+    /// Consume the tree and iterate its values in ascending order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(2);
+    /// bst.insert(1);
+    /// let values: Vec<_> = bst.into_iter().collect();
+    /// assert_eq!(values, vec![1, 2]);
+    /// ```
+    fn into_iter(mut self) -> Self::IntoIter {
+        IntoIter::new(self.root.take())
     }
+}
 
-    fn _delete_left(&mut self, val: T) {
-        if let Some(left) = self.left.as_ref() {
-            if left.borrow().data == val {
-                if left.borrow().left.is_none() && left.borrow().right.is_none() {
-                    self.left = None;
-                } else if left.borrow().left.is_none() && !left.borrow().right.is_none() {
-                    self.left.take().map(|node| {
-                        self.left = node.borrow().right.clone()
-                    });
-                } else if !left.borrow().left.is_none() && left.borrow().right.is_none() {
-                    self.left.take().map(|node| {
-                        self.left = node.borrow().left.clone()
-                    });
-                } else {
-                    Self::_delete_node_have_two_children(left);
-                }
-            } else {
-                left.borrow_mut().delete(val);
-            }
-        }
+impl<T: Ord + Clone + fmt::Debug> FromIterator<T> for BinarySearchTree<T> {
+    /// Create a tree by inserting the values one by one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let bst: BinarySearchTree<i32> = vec![5, 3, 8].into_iter().collect();
+    /// assert_eq!(bst.to_vec(), vec![3, 5, 8]);
+    /// ```
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        tree.extend(iter);
+        tree
     }
+}
 
-    /// Delete a node, which will be called by [BinarySearchTree](struct.BinarySearchTree.html)
-    fn delete(&mut self, val: T) {
-        match self.data.cmp(&val) {
-            Ordering::Greater => self._delete_left(val),
-            Ordering::Less => self._delete_right(val),
-            _ => unreachable!(),
+impl<T: Ord + Clone + fmt::Debug> Extend<T> for BinarySearchTree<T> {
+    /// Insert the values one by one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(4);
+    /// bst.extend(vec![1, 2, 3]);
+    /// assert_eq!(bst.to_vec(), vec![1, 2, 3, 4]);
+    /// ```
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for val in iter {
+            self.insert(val);
         }
     }
 }
 
-/// An implementation of [Binary Search Tree](https://en.wikipedia.org/wiki/Binary_search_tree)
-pub struct BinarySearchTree<T: Ord + Copy + fmt::Debug> {root: BaseNodeLink<T>}
-
-impl <T: Ord + Copy + fmt::Debug> QueryableTree<T, BinarySearchTreeNode<T>> for BinarySearchTree<T> {
-    fn get_root(&self) -> &BaseNodeLink<T> {
-        &self.root
+impl<T: Ord + Clone + fmt::Debug> fmt::Display for BinarySearchTree<T> {
+    /// Format the values in ascending order within braces, e.g. `{0, 1, 3}`.
+    /// The values are written with their `Debug` format.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::bstree::BinarySearchTree;
+    ///
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(3);
+    /// bst.insert(1);
+    /// assert_eq!(format!("{}", bst), "{1, 3}");
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        base::fmt_values(self.iter(), f)
     }
 }
 
-impl<T: Ord + Copy + fmt::Debug> BinarySearchTree<T> {
-    /// Create a new Binary Search Tree
+impl<T: Integer> BinarySearchTree<T> {
+    /// Insert every integer from `lo` to `hi`, both included. Nothing is
+    /// inserted if `lo > hi`.
+    ///
+    /// The range is merged with the values already in the tree and the whole
+    /// tree is rebuilt height-balanced, which takes `O(n + k)` time for `n`
+    /// values and `k` new ones instead of inserting them one by one into a
+    /// degenerate chain.
     ///
     /// # Example
     ///
     /// ```
     /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
     ///
-    /// let mut bst: BinarySearchTree<i32> = BinarySearchTree::new();
+    /// let mut bst = BinarySearchTree::new();
+    /// bst.insert(0);
+    /// bst.insert_range(3, 5);
+    /// assert_eq!(bst.to_vec(), vec![0, 3, 4, 5]);
     /// ```
-    pub fn new() -> Self {
-        Self{ root: None }
+    pub fn insert_range(&mut self, lo: T, hi: T) {
+        let block = num::inclusive_range(lo, hi);
+        if block.is_empty() {
+            return;
+        }
+        if let Some(history) = self.history.as_mut() {
+            history.extend(block.iter().copied());
+        }
+        let cmp = self.ordering();
+        let mut block = block;
+        // ascending integers are already sorted, or reversed, by a comparator
+        block.sort_by(|a, b| cmp(a, b));
+        let values = base::merge_sorted(self.iter(), block, cmp);
+        self.clear();
+        self.root = BinarySearchTreeNode::from_sorted(&values);
     }
 
-    /// Insert a new value to the tree
+    /// Write the tree to `w` in a compact binary format: the number of values
+    /// as a little-endian `u64`, followed by the values in ascending order,
+    /// each encoded as a fixed-width little-endian integer.
     ///
     /// # Example
     ///
@@ -149,54 +1384,80 @@ impl<T: Ord + Copy + fmt::Debug> BinarySearchTree<T> {
     /// use trees::bstree::BinarySearchTree;
     ///
     /// let mut bst = BinarySearchTree::new();
-    /// bst.insert(1);
+    /// bst.insert(1u16);
+    /// let mut buf = vec![];
+    /// bst.write_binary(&mut buf).unwrap();
+    /// assert_eq!(buf, vec![1, 0, 0, 0, 0, 0, 0, 0, 1, 0]);
     /// ```
-    pub fn insert(&mut self, new_val: T) {
-        if self.root.is_none() {
-            self.root = Some(Rc::new(RefCell::new(BinarySearchTreeNode{
-                data: new_val,
-                left: None,
-                right: None
-            })));
-        } else {
-            self.root.as_ref().unwrap().borrow_mut().insert(new_val);
+    pub fn write_binary<W: io::Write>(&self, w: &mut W) -> io::Result<()> {
+        let values = self.to_vec();
+        w.write_all(&(values.len() as u64).to_le_bytes())?;
+        for v in values {
+            w.write_all(&v.to_le_vec())?;
         }
+        Ok(())
     }
-    /// Delete a value from the tree
+
+    /// Read a tree written by [write_binary](#method.write_binary).
+    /// The tree is rebuilt balanced.
+    ///
+    /// Return an error of kind `InvalidData` if the values are not strictly
+    /// increasing, or `UnexpectedEof` if the input is truncated.
     ///
     /// # Example
     ///
     /// ```
+    /// use std::io::Cursor;
     /// use trees::bstree::BinarySearchTree;
+    /// use trees::base::QueryableTree;
     ///
     /// let mut bst = BinarySearchTree::new();
-    /// bst.insert(1);
-    /// bst.delete(1);
+    /// bst.insert(1u16);
+    /// bst.insert(2u16);
+    /// let mut buf = vec![];
+    /// bst.write_binary(&mut buf).unwrap();
+    /// let copy = BinarySearchTree::<u16>::read_binary(&mut Cursor::new(buf)).unwrap();
+    /// assert_eq!(copy.to_vec(), vec![1, 2]);
     /// ```
-    pub fn delete(&mut self, val: T) {
-        if self.root.is_none() {
-            return
-        } else {
-            if let Some(root) = self.root.as_ref() {
-                if root.borrow().data == val {
-                    if root.borrow().left.is_none() && root.borrow().right.is_none() {
-                        self.root = None;
-                    } else if root.borrow().left.is_none() && !root.borrow().right.is_none() {
-                        self.root.take().map(|node| {
-                            self.root = node.borrow().right.clone()
-                        });
-                    } else if !root.borrow().left.is_none() && root.borrow().right.is_none() {
-                        self.root.take().map(|node| {
-                            self.root = node.borrow().left.clone()
-                        });
-                    } else {
-                        BinarySearchTreeNode::_delete_node_have_two_children(root);
-                    }
-                } else {
-                    root.borrow_mut().delete(val);
-                }
+    pub fn read_binary<R: io::Read>(r: &mut R) -> io::Result<Self> {
+        let mut len = [0u8; 8];
+        r.read_exact(&mut len)?;
+        let len: usize = u64::from_le_bytes(len).try_into().map_err(
+            |_| io::Error::new(io::ErrorKind::InvalidData, "length does not fit in memory")
+        )?;
+        let mut buf = vec![0u8; T::BYTES];
+        let mut values: Vec<T> = vec![];
+        for _ in 0..len {
+            r.read_exact(&mut buf)?;
+            let v = T::from_le_slice(&buf);
+            if values.last().is_some_and(|last| *last >= v) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData, "values are not strictly increasing"
+                ));
             }
+            values.push(v);
         }
+        Ok(Self{ root: BinarySearchTreeNode::from_sorted(&values), history: None, comparator: None })
+    }
+}
+
+/// Serialized as the sequence of values in inorder
+#[cfg(feature = "serde")]
+impl<T: Ord + Clone + fmt::Debug + serde::Serialize> serde::Serialize for BinarySearchTree<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+/// Deserialized from a sequence of values, which is rebuilt into a
+/// height-balanced tree
+#[cfg(feature = "serde")]
+impl<'de, T: Ord + Clone + fmt::Debug + serde::Deserialize<'de>> serde::Deserialize<'de> for BinarySearchTree<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut values = Vec::<T>::deserialize(deserializer)?;
+        values.sort();
+        values.dedup();
+        Ok(Self{ root: BinarySearchTreeNode::from_sorted(&values), history: None, comparator: None })
     }
 }
 
@@ -353,6 +1614,619 @@ mod test {
         assert_eq!(bst.len(), 0);
     }
 
+    #[test]
+    fn test_worst_balance_factor() {
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(bst.worst_balance_factor(), None);
+        bst.insert(10);
+        assert_eq!(bst.worst_balance_factor(), Some((10, 0)));
+        for v in [5, 15, 12, 20].iter() {
+            bst.insert(*v);
+        }
+        assert_eq!(bst.worst_balance_factor(), Some((10, -1)));
+        // a left-leaning chain hanging off 5
+        for v in (1..5).rev() {
+            bst.insert(v);
+        }
+        assert_eq!(bst.worst_balance_factor(), Some((5, 4)));
+
+        let mut skewed = BinarySearchTree::new();
+        for v in 0..10 {
+            skewed.insert(v);
+        }
+        assert_eq!(skewed.worst_balance_factor(), Some((0, -9)));
+
+        let mut avl = crate::avltree::AVLTree::new();
+        for v in 0..100 {
+            avl.insert(v);
+        }
+        assert!(avl.worst_balance_factor().unwrap().1.abs() <= 1);
+    }
+
+    #[test]
+    fn test_avl_violations() {
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(bst.avl_violations(), Vec::<i32>::new());
+        // right-skewed chain: every node with at least two nodes below it
+        for v in 0..10 {
+            bst.insert(v);
+        }
+        assert_eq!(bst.avl_violations(), (0..8).collect::<Vec<_>>());
+
+        //          10
+        //        /    \
+        //       5      15
+        //      / \
+        //     3   7
+        //    /
+        //   1
+        let mut bst = BinarySearchTree::new();
+        for v in [10, 5, 15, 3, 7, 1].iter() {
+            bst.insert(*v);
+        }
+        assert_eq!(bst.avl_violations(), vec![10]);
+        bst.insert(0);
+        assert_eq!(bst.avl_violations(), vec![3, 5, 10]);
+
+        let avl: crate::avltree::AVLTree<i32> = (0..100).collect();
+        assert_eq!(avl.avl_violations(), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn test_remove_where() {
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(bst.remove_where(|_| true), 0);
+        for v in 0..100 {
+            bst.insert(v);
+        }
+        assert_eq!(bst.remove_where(|v| v % 2 == 1), 50);
+        assert_eq!(bst.to_vec(), (0..100).step_by(2).collect::<Vec<_>>());
+        assert_eq!(bst.height(), 6);
+        assert_eq!(bst.remove_where(|v| v % 2 == 1), 0);
+        assert_eq!(bst.remove_where(|_| true), 50);
+        assert!(bst.is_empty());
+    }
+
+    #[test]
+    fn test_fill_percentage() {
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(bst.fill_percentage(), 1.0);
+        for v in [4, 2, 6, 1, 3, 5, 7].iter() {
+            bst.insert(*v);
+        }
+        assert_eq!(bst.fill_percentage(), 1.0);
+
+        let mut skewed = BinarySearchTree::new();
+        for v in 1..8 {
+            skewed.insert(v);
+        }
+        assert_eq!(skewed.len(), bst.len());
+        assert_eq!(skewed.fill_percentage(), 7.0 / 127.0);
+        assert!(skewed.fill_percentage() < bst.fill_percentage());
+    }
+
+    #[test]
+    fn test_cousins_of() {
+        //          4
+        //        /   \
+        //       2     6
+        //      / \   / \
+        //     1   3 5   7
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(bst.cousins_of(4), Vec::<i32>::new());
+        for v in [4, 2, 6, 1, 3, 5, 7].iter() {
+            bst.insert(*v);
+        }
+        assert_eq!(bst.cousins_of(4), Vec::<i32>::new());
+        assert_eq!(bst.cousins_of(2), Vec::<i32>::new());
+        assert_eq!(bst.cousins_of(6), Vec::<i32>::new());
+        assert_eq!(bst.cousins_of(1), vec![5, 7]);
+        assert_eq!(bst.cousins_of(3), vec![5, 7]);
+        assert_eq!(bst.cousins_of(5), vec![1, 3]);
+        assert_eq!(bst.cousins_of(7), vec![1, 3]);
+        assert_eq!(bst.cousins_of(8), Vec::<i32>::new());
+        bst.delete(3);
+        assert_eq!(bst.cousins_of(7), vec![1]);
+    }
+
+    #[test]
+    fn test_by_depth_parity() {
+        //          4
+        //        /   \
+        //       2     6
+        //      / \   / \
+        //     1   3 5   7
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(bst.by_depth_parity(), (vec![], vec![]));
+        for v in [4, 2, 6, 1, 3, 5, 7].iter() {
+            bst.insert(*v);
+        }
+        assert_eq!(bst.by_depth_parity(), (vec![1, 3, 4, 5, 7], vec![2, 6]));
+        bst.insert(8);
+        assert_eq!(bst.by_depth_parity(), (vec![1, 3, 4, 5, 7], vec![2, 6, 8]));
+    }
+
+    #[test]
+    fn test_sibling_pairs() {
+        //              8
+        //         /         \
+        //       4             12
+        //     /   \         /    \
+        //    2     6      10      14
+        //   / \   / \    /  \    /  \
+        //  1   3 5   7  9   11 13  15
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(bst.sibling_pairs(), vec![]);
+        for v in [8, 4, 12, 2, 6, 10, 14, 1, 3, 5, 7, 9, 11, 13, 15].iter() {
+            bst.insert(*v);
+        }
+        assert_eq!(bst.sibling_pairs(), vec![
+            (1, 3), (2, 6), (5, 7), (4, 12), (9, 11), (10, 14), (13, 15)
+        ]);
+        assert_eq!(bst.sibling_pairs().len(), bst.len() - bst.count_leaves());
+        bst.delete(3);
+        bst.delete(12);
+        assert_eq!(bst.sibling_pairs(), vec![(2, 6), (5, 7), (4, 13), (9, 11), (10, 14)]);
+    }
+
+    #[test]
+    fn test_map_root() {
+        let mut bst = BinarySearchTree::new();
+        assert!(!bst.map_root(|v: i32| v + 1));
+        for v in [10, 4, 2, 6, 16, 12, 18].iter() {
+            bst.insert(*v);
+        }
+        // order-preserving: 10 -> 11 keeps 6 < 11 < 12
+        assert!(!bst.map_root(|v| v + 1));
+        assert_eq!(bst.to_vec(), vec![2, 4, 6, 11, 12, 16, 18]);
+        assert_eq!(bst.height(), 3);
+
+        // order-breaking: 11 -> 22 is larger than the whole right subtree
+        assert!(bst.map_root(|v| v * 2));
+        assert_eq!(bst.to_vec(), vec![2, 4, 6, 12, 16, 18, 22]);
+        assert_eq!(bst.height(), 3);
+        assert!(bst.contains(22));
+        assert!(!bst.contains(11));
+
+        // colliding with an existing value drops the duplicate
+        let root = bst.root.as_ref().unwrap().borrow().data;
+        assert_eq!(root, 12);
+        assert!(bst.map_root(|_| 2));
+        assert_eq!(bst.to_vec(), vec![2, 4, 6, 16, 18, 22]);
+        assert_eq!(bst.len(), 6);
+
+        // order-breaking at the top of a degenerate chain, which is freed
+        // without recursion when the tree is rebuilt
+        let mut chain = skewed(200_000);
+        assert!(chain.map_root(|v| v + 1_000_000));
+        assert_eq!(chain.len(), 200_000);
+        assert_eq!(chain.extremes(), Some((1, 1_000_000)));
+        assert_eq!(chain.height(), 18);
+    }
+
+    #[test]
+    fn test_with_comparator() {
+        let mut bst = BinarySearchTree::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+        for v in [5, 2, 8, 1, 9, 3, 7, 5].iter() {
+            bst.insert(*v);
+        }
+        assert_eq!(bst.to_vec(), vec![9, 8, 7, 5, 3, 2, 1]);
+        assert_eq!(bst.len(), 7);
+        assert_eq!(bst.min(), Some(9));
+        assert_eq!(bst.max(), Some(1));
+        assert!(bst.contains(3));
+        assert!(!bst.contains(4));
+        bst.delete(5);
+        bst.delete(9);
+        bst.delete(4);
+        assert_eq!(bst.to_vec(), vec![8, 7, 3, 2, 1]);
+        assert_eq!(bst.len(), 5);
+        assert!(!bst.contains(5));
+        bst.coalesce(|a, b| if a - b == 1 { Some(*a) } else { None });
+        assert_eq!(bst.to_vec(), vec![8, 3, 1]);
+
+        // ordered by the key only, so equal keys are stored once
+        let mut by_key = BinarySearchTree::with_comparator(|a: &(i32, char), b: &(i32, char)| a.0.cmp(&b.0));
+        by_key.insert((2, 'a'));
+        by_key.insert((1, 'b'));
+        by_key.insert((2, 'c'));
+        assert_eq!(by_key.to_vec(), vec![(1, 'b'), (2, 'a')]);
+        assert_eq!(by_key.get((2, 'z')), Some((2, 'a')));
+    }
+
+    #[test]
+    fn test_with_comparator_queries() {
+        // placed in descending order: 5 at the root, 8 to its left and 2 to
+        // its right
+        let mut bst = BinarySearchTree::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+        for v in [5, 2, 8, 1, 9, 3, 7, 4, 6].iter() {
+            bst.insert(*v);
+        }
+        assert_eq!(bst.extremes(), Some((9, 1)));
+        assert_eq!(bst.successor(5), Some(4));
+        assert_eq!(bst.predecessor(5), Some(6));
+        assert_eq!(bst.floor(10), None);
+        assert_eq!(bst.ceiling(10), Some(9));
+        assert_eq!(bst.range(7, 3).collect::<Vec<_>>(), vec![7, 6, 5, 4, 3]);
+        assert_eq!(bst.range(3, 7).count(), 0);
+        assert_eq!(bst.merge_iter(&bst.clone()).collect::<Vec<_>>(), bst.to_vec());
+        assert_eq!(bst.rank(7), 2);
+        assert_eq!(bst.count_in_range(8, 5), 4);
+        assert_eq!(bst.depth_of(5), Some(0));
+        assert_eq!(bst.depth_of(2), Some(1));
+        assert_eq!(bst.depth_of(10), None);
+        assert_eq!(bst.lca(1, 3), Some(2));
+        assert_eq!(bst.lca(9, 1), Some(5));
+        assert_eq!(bst.subtree_index_range(2), Some((5, 8)));
+        assert_eq!(bst.cousins_of(1), vec![9, 7]);
+        assert_eq!(bst.prefix_sum_up_to(7), 24);
+        assert_eq!(bst.range_sum(8, 6), 21);
+        assert!(bst.is_bst());
+        assert!(bst.apply_ops(&[TreeOp::Insert(10), TreeOp::Delete(5), TreeOp::Insert(0)]));
+        assert_eq!(bst.to_vec(), vec![10, 9, 8, 7, 6, 4, 3, 2, 1, 0]);
+        bst.insert_range(10, 11);
+        assert_eq!(bst.to_vec()[..3], [11, 10, 9]);
+        assert!(bst.delete(11).is_some());
+
+        // the values themselves are measured in their own order
+        assert_eq!(bst.clusters(1), vec![vec![0, 1, 2, 3, 4], vec![6, 7, 8, 9, 10]]);
+        assert_eq!(bst.tightest_range_with_count(2), Some((0, 1)));
+        let avl: crate::avltree::AVLTree<i32> = bst.iter().collect();
+        assert!(bst == avl);
+        assert!(avl == bst);
+        assert_eq!(bst.content_hash(), avl.content_hash());
+
+        let mut other = BinarySearchTree::with_comparator(|a: &i32, b: &i32| b.cmp(a));
+        for v in bst.iter().filter(|v| *v != 3) {
+            other.insert(v);
+        }
+        assert_eq!(bst.first_divergence(&other), Some(3));
+        assert!(bst != other);
+
+        // a tree which is out of the comparator's order is not a BST
+        let root = bst.root.clone().unwrap();
+        let first = root.borrow().data;
+        root.borrow_mut().data = 100;
+        assert!(!bst.is_bst());
+        root.borrow_mut().data = first;
+        assert!(bst.is_valid());
+    }
+
+    #[test]
+    fn test_median_fast() {
+        let values: Vec<i32> = (0..1000).collect();
+        let bst = BinarySearchTree {
+            root: BinarySearchTreeNode::from_sorted(&values),
+            history: None,
+            comparator: None,
+        };
+        assert_eq!(bst.median(), Some(499));
+        assert_eq!(bst.median_fast(), bst.median());
+
+        // hold the leftmost leaf mutably: visiting it would panic, so
+        // median_fast must not walk the whole tree
+        let mut leaf = bst.root.clone().unwrap();
+        loop {
+            let left = leaf.borrow().left.clone();
+            match left {
+                Some(l) => leaf = l,
+                None => break,
+            }
+        }
+        let _guard = leaf.borrow_mut();
+        assert_eq!(bst.median_fast(), Some(499));
+        assert_eq!(bst.value_at_index(999), Some(999));
+        // range skips the subtrees outside of its bounds as well
+        assert_eq!(bst.range(500, 503).collect::<Vec<_>>(), vec![500, 501, 502, 503]);
+    }
+
+    #[test]
+    fn test_apply_ops_short_circuit() {
+        let mut bst = BinarySearchTree::new();
+        assert!(bst.apply_ops(&[TreeOp::Insert(5), TreeOp::Insert(3), TreeOp::Insert(8)]));
+        assert!(bst.is_valid());
+
+        // break the ordering by hand, the first op then reports the violation
+        // and the remaining ops are not applied
+        bst.root.as_ref().unwrap().borrow_mut().data = 1;
+        assert!(!bst.is_bst());
+        assert!(!bst.apply_ops(&[TreeOp::Insert(10), TreeOp::Insert(20)]));
+        assert!(bst.contains(10));
+        assert!(!bst.contains(20));
+    }
+
+    #[test]
+    fn test_catalan_tree_count() {
+        let expected = [1, 1, 2, 5, 14, 42, 132, 429, 1430, 4862];
+        for (n, c) in expected.iter().enumerate() {
+            assert_eq!(catalan_tree_count(n), *c);
+        }
+        assert_eq!(catalan_tree_count(69), 337485502510215975556783793455058624700);
+
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(bst.possible_shapes(), 1);
+        for v in [3, 1, 4, 5, 9].iter() {
+            bst.insert(*v);
+        }
+        assert_eq!(bst.possible_shapes(), 42);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_catalan_tree_count_overflow() {
+        catalan_tree_count(70);
+    }
+
+    #[test]
+    fn test_weighted_path_cost() {
+        let mut bst = BinarySearchTree::new();
+        for v in [4, 2, 6, 1, 3, 7].iter() {
+            bst.insert(*v);
+        }
+        // depths: 4 -> 1, 2 and 6 -> 2, 1, 3 and 7 -> 3
+        let freq: HashMap<i32, u64> = [(4, 1), (2, 2), (6, 3), (1, 4), (3, 5), (7, 6)]
+            .iter().copied().collect();
+        assert_eq!(weighted_path_cost(&bst, &freq), 1 + 2 * (2 + 3) + 3 * (4 + 5 + 6));
+
+        // values without a frequency are free
+        let freq: HashMap<i32, u64> = [(7, 10), (100, 50)].iter().copied().collect();
+        assert_eq!(weighted_path_cost(&bst, &freq), 30);
+        assert_eq!(weighted_path_cost(&BinarySearchTree::new(), &freq), 0);
+    }
+
+    #[test]
+    fn test_optimal_from() {
+        let tree = BinarySearchTree::optimal_from(&[(10, 34), (12, 8), (20, 50)]);
+        let freq: HashMap<i32, u64> = [(10, 34), (12, 8), (20, 50)].iter().copied().collect();
+        assert_eq!(tree.preorder(), vec![20, 10, 12]);
+        assert_eq!(weighted_path_cost(&tree, &freq), 142);
+
+        // skewed towards the small values
+        let pairs: Vec<(i32, u64)> = (0..63).map(|v| (v, if v < 4 { 1000 } else { 1 })).collect();
+        let freq: HashMap<i32, u64> = pairs.iter().copied().collect();
+        let mut shuffled = pairs.clone();
+        shuffled.reverse();
+        let optimal = BinarySearchTree::optimal_from(&shuffled);
+        let values: Vec<i32> = (0..63).collect();
+        let balanced = BinarySearchTree::try_from_sorted(&values).unwrap();
+        assert!(optimal.is_bst());
+        assert_eq!(optimal.len(), 63);
+        assert_eq!(optimal.to_vec(), values);
+        assert!(weighted_path_cost(&optimal, &freq) < weighted_path_cost(&balanced, &freq));
+
+        // repeated values add their frequencies up
+        let tree = BinarySearchTree::optimal_from(&[(1, 3), (2, 4), (1, 3)]);
+        assert_eq!(tree.preorder(), vec![1, 2]);
+        assert!(BinarySearchTree::<i32>::optimal_from(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_height_ratio_vs_optimal() {
+        assert_eq!(BinarySearchTree::<i32>::new().height_ratio_vs_optimal(), 1.0);
+
+        let mut chain = BinarySearchTree::new();
+        for v in 0..100 {
+            chain.insert(v);
+        }
+        // 100 values fit in 7 levels
+        assert_eq!(chain.height_ratio_vs_optimal(), 100.0 / 7.0);
+
+        let values: Vec<i32> = (0..100).collect();
+        let balanced = BinarySearchTree::try_from_sorted(&values).unwrap();
+        assert_eq!(balanced.height_ratio_vs_optimal(), 1.0);
+        let perfect = BinarySearchTree::try_from_sorted(&values[..63]).unwrap();
+        assert_eq!(perfect.height_ratio_vs_optimal(), 1.0);
+    }
+
+    #[test]
+    fn test_rebalance() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut bst = BinarySearchTree::new();
+        for v in 0..1000 {
+            bst.insert(v);
+        }
+        assert_eq!(bst.height(), 1000);
+        bst.rebalance();
+        // 1000 values fit in 10 levels
+        assert_eq!(bst.height(), 10);
+        assert_eq!(bst.to_vec(), (0..1000).collect::<Vec<_>>());
+        assert_eq!(bst.len(), 1000);
+        assert_eq!(bst.range_sum(100, 199), (100..200).sum::<i32>());
+        assert!(bst.is_valid());
+
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for n in 0..70 {
+            let mut bst = BinarySearchTree::new();
+            for _ in 0..n {
+                bst.insert(rng.gen_range(0, 1000));
+            }
+            let values = bst.to_vec();
+            bst.rebalance();
+            let len = values.len();
+            assert_eq!(bst.height(), (usize::BITS - len.leading_zeros()) as usize);
+            assert_eq!(bst.to_vec(), values);
+            assert_eq!(bst.len(), len);
+            for v in values.iter() {
+                assert!(bst.contains(*v));
+            }
+        }
+    }
+
+    #[test]
+    fn test_triples() {
+        use crate::avltree::AVLTree;
+        use crate::rbtree::RedBlackTree;
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let values: Vec<i32> = (0..300).map(|_| rng.gen_range(-1000, 1000)).collect();
+        let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+        let rebuilt = BinarySearchTree::from_triples(&bst.to_triples()).unwrap();
+        assert!(rebuilt.same_shape(&bst));
+        assert_eq!(rebuilt.preorder(), bst.preorder());
+        assert_eq!(rebuilt.len(), bst.len());
+        assert!(!rebuilt.same_shape(&BinarySearchTree::try_from_sorted(&bst.to_vec()).unwrap()));
+
+        // the balanced trees can be dumped into a plain tree of the same shape
+        let avl: AVLTree<i32> = values.iter().copied().collect();
+        assert!(BinarySearchTree::from_triples(&avl.to_triples()).unwrap().same_shape(&avl));
+        let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+        assert!(BinarySearchTree::from_triples(&rbt.to_triples()).unwrap().same_shape(&rbt));
+
+        assert!(BinarySearchTree::<i32>::from_triples(&[]).unwrap().is_empty());
+        // missing child entry
+        assert!(BinarySearchTree::from_triples(&[(2, Some(1), None)]).is_none());
+        // unreachable entry
+        assert!(BinarySearchTree::from_triples(&[(2, None, None), (1, None, None)]).is_none());
+        // duplicate entry
+        assert!(BinarySearchTree::from_triples(&[(2, None, None), (2, None, None)]).is_none());
+        // a child used twice
+        assert!(BinarySearchTree::from_triples(&[(2, Some(1), Some(1)), (1, None, None)]).is_none());
+        // a cycle back to the root
+        assert!(BinarySearchTree::from_triples(&[(2, None, Some(2))]).is_none());
+        // out of order
+        assert!(BinarySearchTree::from_triples(&[(2, Some(3), None), (3, None, None)]).is_none());
+    }
+
+    #[test]
+    fn test_is_shape_symmetric() {
+        let mut bst = BinarySearchTree::new();
+        assert!(bst.is_shape_symmetric());
+        for v in [8, 4, 12, 2, 6, 10, 14, 1, 3, 5, 7, 9, 11, 13, 15].iter() {
+            bst.insert(*v);
+            // only the complete levels are symmetric
+            assert_eq!(bst.is_shape_symmetric(), [1, 3, 7, 15].contains(&bst.len()));
+        }
+
+        //      4
+        //    /   \
+        //   2     6
+        //    \   /
+        //     3 5
+        let mut mirrored = BinarySearchTree::new();
+        for v in [4, 2, 6, 3, 5].iter() {
+            mirrored.insert(*v);
+        }
+        assert!(mirrored.is_shape_symmetric());
+
+        let mut lopsided = BinarySearchTree::new();
+        for v in [4, 2, 6, 1, 5].iter() {
+            lopsided.insert(*v);
+        }
+        assert_eq!(lopsided.len(), mirrored.len());
+        assert!(!lopsided.is_shape_symmetric());
+    }
+
+    #[test]
+    fn test_min_removals_to_balance() {
+        let mut bst = BinarySearchTree::new();
+        assert_eq!(bst.min_removals_to_balance(), 0);
+
+        // degenerate chain: root and one child is the most that can be kept
+        for v in 1..=100 {
+            bst.insert(v);
+        }
+        assert_eq!(bst.min_removals_to_balance(), 98);
+        assert_eq!(skewed(200_000).min_removals_to_balance(), 199_998);
+        assert_eq!(BinarySearchTreeNode::<i32>::max_balanced_height(4), 3);
+        assert_eq!(BinarySearchTreeNode::<i32>::max_balanced_height(6), 3);
+        assert_eq!(BinarySearchTreeNode::<i32>::max_balanced_height(7), 4);
+
+        //          5
+        //        /   \
+        //       3     8
+        //      / \
+        //     2   4
+        //    /
+        //   1
+        let mut bst = BinarySearchTree::new();
+        for v in [5, 3, 8, 2, 4, 1].iter() {
+            bst.insert(*v);
+        }
+        assert_eq!(bst.min_removals_to_balance(), 1);
+        bst.delete(1);
+        assert_eq!(bst.min_removals_to_balance(), 0);
+    }
+
+    #[test]
+    fn test_coalesce() {
+        /// Closed integer interval ordered by its start, then its end
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+        struct Interval(i32, i32);
+
+        let mut bst = BinarySearchTree::new();
+        for iv in [
+            Interval(10, 12), Interval(1, 3), Interval(20, 25), Interval(2, 5),
+            Interval(4, 6), Interval(11, 15), Interval(30, 31), Interval(26, 28),
+        ].iter() {
+            bst.insert(*iv);
+        }
+        bst.coalesce(|a, b| if b.0 <= a.1 {
+            Some(Interval(a.0, a.1.max(b.1)))
+        } else {
+            None
+        });
+        assert_eq!(bst.to_vec(), vec![
+            Interval(1, 6), Interval(10, 15), Interval(20, 25),
+            Interval(26, 28), Interval(30, 31),
+        ]);
+        assert_eq!(bst.len(), 5);
+        assert_eq!(bst.height(), 3);
+
+        // nothing to merge leaves the tree untouched
+        let shape = bst.to_debug_nested();
+        bst.coalesce(|_, _| None);
+        assert_eq!(bst.to_debug_nested(), shape);
+
+        let mut empty: BinarySearchTree<Interval> = BinarySearchTree::new();
+        empty.coalesce(|a, _| Some(*a));
+        assert!(empty.is_empty());
+
+        // the degenerate chain replaced by the merged tree is freed without
+        // recursion
+        let mut chain = skewed(200_000);
+        chain.coalesce(|a, b| if *a % 2 == 0 { Some(*b) } else { None });
+        assert_eq!(chain.len(), 100_000);
+        assert_eq!(chain.extremes(), Some((1, 199_999)));
+        assert_eq!(chain.height(), 17);
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        use std::io::Cursor;
+
+        let mut bst = BinarySearchTree::new();
+        for v in (0..100).rev() {
+            bst.insert(v * 3 - 150);
+        }
+        let mut cursor = Cursor::new(Vec::new());
+        bst.write_binary(&mut cursor).unwrap();
+        assert_eq!(cursor.get_ref().len(), 8 + 100 * 4);
+
+        cursor.set_position(0);
+        let copy = BinarySearchTree::<i32>::read_binary(&mut cursor).unwrap();
+        assert_eq!(copy.to_vec(), bst.to_vec());
+        assert_eq!(bst.height(), 100);
+        assert_eq!(copy.height(), 7);
+
+        let empty = BinarySearchTree::<u8>::new();
+        let mut buf = vec![];
+        empty.write_binary(&mut buf).unwrap();
+        let copy = BinarySearchTree::<u8>::read_binary(&mut Cursor::new(buf)).unwrap();
+        assert!(copy.is_empty());
+
+        // truncated and unsorted input
+        let mut buf = vec![];
+        bst.write_binary(&mut buf).unwrap();
+        buf.pop();
+        let err = BinarySearchTree::<i32>::read_binary(&mut Cursor::new(&buf)).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+        let buf = vec![2, 0, 0, 0, 0, 0, 0, 0, 5, 5];
+        let err = BinarySearchTree::<u8>::read_binary(&mut Cursor::new(buf)).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
     // test delete function
     //          5
     //        /   \
@@ -476,4 +2350,108 @@ mod test {
             assert_eq!(tree.len(), tree_size - i - 1);
         }
     }
+
+    #[test]
+    fn try_from_sorted() {
+        let tree = BinarySearchTree::try_from_sorted(&[1, 3, 5, 7, 9, 11]).unwrap();
+        assert_eq!(tree.to_vec(), vec![1, 3, 5, 7, 9, 11]);
+        assert_eq!(tree.len(), 6);
+        assert_eq!(tree.height(), 3);
+        assert!(tree.is_bst());
+
+        let empty: BinarySearchTree<i32> = BinarySearchTree::try_from_sorted(&[]).unwrap();
+        assert!(empty.is_empty());
+
+        assert_eq!(
+            BinarySearchTree::try_from_sorted(&[1, 3, 2, 4]).err(),
+            Some(BuildError::NotSorted { index: 2 })
+        );
+        assert_eq!(
+            BinarySearchTree::try_from_sorted(&[1, 2, 2, 4]).err(),
+            Some(BuildError::Duplicate { value: 2 })
+        );
+        assert_eq!(
+            BinarySearchTree::try_from_sorted(&[4, 1]).err().unwrap().to_string(),
+            "value at index 1 is out of order"
+        );
+    }
+
+    /// Link the values `0..n` by hand into a chain of right children,
+    /// inserting this many sorted values would take quadratic time
+    fn skewed(n: usize) -> BinarySearchTree<usize> {
+        let mut root = None;
+        for v in (0..n).rev() {
+            let node = BinarySearchTreeNode::new(v);
+            if let Some(node) = node.as_ref() {
+                node.borrow_mut().right = root;
+                node.borrow_mut().size = n - v;
+            }
+            root = node;
+        }
+        BinarySearchTree { root, history: None, comparator: None }
+    }
+
+    #[test]
+    fn insert_into_skewed_tree() {
+        // The new values go below the bottom of a chain 100000 nodes deep,
+        // which overflows the stack of the test thread when inserting
+        // recursively
+        let mut tree = skewed(100_000);
+        assert!(tree.insert(100_000));
+        assert!(!tree.insert(100_000));
+        assert!(!tree.insert(99_999));
+        assert!(tree.insert(100_001));
+        assert_eq!(tree.len(), 100_002);
+        assert_eq!(tree.height_bfs(), 100_002);
+        assert!(tree.iter().eq(0..100_002));
+    }
+
+    #[test]
+    fn clone_skewed_tree() {
+        // copying a chain 100000 nodes deep recursively overflows the stack
+        let tree = skewed(100_000);
+        let mut copy = tree.clone();
+        assert!(copy.insert(100_000));
+        assert_eq!(copy.len(), 100_001);
+        assert_eq!(copy.height_bfs(), 100_001);
+        assert_eq!(tree.len(), 100_000);
+        assert!(tree.iter().eq(copy.iter().take(100_000)));
+    }
+
+    #[test]
+    fn drop_skewed_tree() {
+        let tree = skewed(200_000);
+        assert_eq!(tree.len(), 200_000);
+        assert_eq!(tree.min(), Some(0));
+        drop(tree);
+
+        // the nodes left in a partly consumed iterator are freed the same way
+        let tree = skewed(200_000);
+        let bottom = {
+            let mut node = tree.root.clone().unwrap();
+            loop {
+                let right = node.borrow().right.clone();
+                match right {
+                    Some(r) => node = r,
+                    None => break,
+                }
+            }
+            Rc::downgrade(&node)
+        };
+        let mut iter = tree.into_iter();
+        assert_eq!(iter.next(), Some(0));
+        assert!(bottom.upgrade().is_some());
+        drop(iter);
+        assert!(bottom.upgrade().is_none());
+    }
+
+    #[test]
+    fn remove_where_on_skewed_tree() {
+        // the chain replaced by the rebuilt tree is freed without recursion
+        let mut tree = skewed(200_000);
+        assert_eq!(tree.remove_where(|v| *v == 0), 1);
+        assert_eq!(tree.len(), 199_999);
+        assert_eq!(tree.min(), Some(1));
+        assert_eq!(tree.height(), 18);
+    }
 }
@@ -18,7 +18,9 @@ fn avl_cli() {
         match operation.to_lowercase().trim() {
             "insert"  => {
                 let val = get_val("insert");
-                tree.insert(val);
+                if !tree.insert(val) {
+                    println!("{:?} is already present", val);
+                }
             },
             "delete" => {
                 let val = get_val("delete");
@@ -49,6 +51,7 @@ fn avl_cli() {
             "empty" => println!("Is the tree empty?: {:?}", tree.is_empty()),
             "print" => {print!("Your tree: ");
                 tree.print_inorder();},
+            "structure" => tree.print_structure(),
             "help" => list_of_operations(),
             "exit" => return,
             _ => println!("Command not recognized. Try 'help' for valid operations"),
@@ -69,7 +72,9 @@ fn rbt_cli() {
         match operation.to_lowercase().trim() {
             "insert"  => {
                 let val = get_val("insert");
-                tree.insert(val);
+                if !tree.insert(val) {
+                    println!("{:?} is already present", val);
+                }
             },
             "delete" => {
                 let val = get_val("delete");
@@ -100,6 +105,7 @@ fn rbt_cli() {
             "empty" => println!("Is the tree empty?: {:?}", tree.is_empty()),
             "print" => {print!("Your tree: ");
                 tree.print_inorder();},
+            "structure" => tree.print_structure(),
             "help" => list_of_operations(),
             "exit" => return,
             _ => println!("Command not recognized. Try 'help' for valid operations"),
@@ -120,7 +126,9 @@ fn bst_cli() {
         match operation.to_lowercase().trim() {
             "insert"  => {
                 let val = get_val("insert");
-                tree.insert(val);
+                if !tree.insert(val) {
+                    println!("{:?} is already present", val);
+                }
             },
             "delete" => {
                 let val = get_val("delete");
@@ -151,6 +159,7 @@ fn bst_cli() {
             "empty" => println!("Is the tree empty?: {:?}", tree.is_empty()),
             "print" => {print!("Your tree: ");
                 tree.print_inorder();},
+            "structure" => tree.print_structure(),
             "help" => list_of_operations(),
             "exit" => return,
             _ => println!("Command not recognized. Try 'help' for valid operations"),
@@ -229,7 +238,8 @@ pub fn list_of_operations(){
     println!("-contain");
     println!("    /");
     println!(" search  - check if the tree contains a certain value");
-    println!("-print   - print tree in order\n");
+    println!("-print   - print tree in order");
+    println!("-structure - print the shape of the tree\n");
     println!("-exit    - exit and erase current tree \n");
 }
 
@@ -238,7 +248,7 @@ pub fn hello(){
     println!(":::: Please enter the name of a tree followed by the wanted action and value or 'exit' to leave :::");
     println!("---------------------------------------------------------------------------------------------------\n");
     println!("Available trees: \n---------------- \n- AVL tree (avl) \n- Red-Black Tree (rbt)\n- Binary Search Tree (bst)\n");
-    println!("Availabe operations: \n------------------ \n- insert \n- delete \n- height \n- count \n- length \n- min \n- max \n- empty \n- contains/search \n- print\n");
+    println!("Availabe operations: \n------------------ \n- insert \n- delete \n- height \n- count \n- length \n- min \n- max \n- empty \n- contains/search \n- print \n- structure\n");
     println!("How to use the CLI: ");
     println!("-------------------");
 }
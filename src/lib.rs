@@ -73,6 +73,8 @@ pub mod rbtree;
 pub mod avltree;
 pub mod bstree;
 pub mod base;
+pub mod num;
+pub mod map;
 
 #[cfg(test)]
 mod tests;
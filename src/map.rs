@@ -0,0 +1,317 @@
+//! Key-value maps
+//!
+//! Each map keeps its keys in one of the trees, so a lookup costs the same
+//! as in the tree and the keys are balanced by the same logic. The values
+//! are stored next to the tree, every key pointing to the slot of its value.
+//! The slot holds a copy of the key as well, since the nodes of the trees
+//! can't hand out references to what they store.
+//!
+//! ```
+//! use trees::map::{AVLTreeMap, BinarySearchTreeMap, RedBlackTreeMap};
+//! ```
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use crate::avltree::AVLTree;
+use crate::base::QueryableTree;
+use crate::bstree::BinarySearchTree;
+use crate::rbtree::RedBlackTree;
+
+/// A key together with the slot of its value, ordered by the key only
+#[derive(Clone, Debug)]
+struct Entry<K> {
+    key: K,
+    slot: usize,
+}
+
+impl<K: Ord> PartialEq for Entry<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl<K: Ord> Eq for Entry<K> {}
+
+impl<K: Ord> PartialOrd for Entry<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> Ord for Entry<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+macro_rules! tree_map {
+    ($(#[$meta:meta])* $name:ident, $tree:ident) => {
+        $(#[$meta])*
+        pub struct $name<K: Ord + Clone + fmt::Debug, V> {
+            keys: $tree<Entry<K>>,
+            values: Vec<Option<(K, V)>>,
+            free: Vec<usize>,
+        }
+
+        impl<K: Ord + Clone + fmt::Debug, V> $name<K, V> {
+            /// Create an empty map
+            pub fn new() -> Self {
+                Self { keys: $tree::new(), values: vec![], free: vec![] }
+            }
+
+            /// Return the number of keys in the map
+            pub fn len(&self) -> usize {
+                self.keys.len()
+            }
+
+            /// Return true if the map holds no key
+            pub fn is_empty(&self) -> bool {
+                self.keys.is_empty()
+            }
+
+            /// Return the slot of the value stored for `key`
+            fn slot(&self, key: &K) -> Option<usize> {
+                self.keys.get(Entry { key: key.clone(), slot: 0 }).map(|entry| entry.slot)
+            }
+
+            /// Return the key and value in `slot`, which a key of the tree
+            /// points to
+            fn pair(&self, slot: usize) -> &(K, V) {
+                self.values[slot].as_ref().expect("every key of the tree has a value")
+            }
+
+            /// Move the values into consecutive slots and rebuild the tree of
+            /// keys pointing to them, so that the slots freed by `remove`
+            /// don't keep the storage at its largest size
+            fn compact(&mut self) {
+                let mut values = Vec::with_capacity(self.len());
+                let mut entries = Vec::with_capacity(self.len());
+                for entry in self.keys.iter() {
+                    values.push(self.values[entry.slot].take());
+                    entries.push(Entry { key: entry.key, slot: entries.len() });
+                }
+                self.keys = $tree::from_sorted(&entries);
+                self.values = values;
+                self.free.clear();
+            }
+
+            /// Insert a value for `key` and return the value it replaced, or
+            /// `None` if the key was not in the map yet. The key keeps its
+            /// node, so there is never more than one node per key.
+            pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+                if let Some(slot) = self.slot(&key) {
+                    let (_, old) = self.values[slot].replace((key, value))
+                        .expect("every key of the tree has a value");
+                    return Some(old);
+                }
+                let pair = Some((key.clone(), value));
+                let slot = match self.free.pop() {
+                    Some(slot) => {
+                        self.values[slot] = pair;
+                        slot
+                    }
+                    None => {
+                        self.values.push(pair);
+                        self.values.len() - 1
+                    }
+                };
+                self.keys.insert(Entry { key, slot });
+                None
+            }
+
+            /// Return the value stored for `key`, or `None` if the key is not
+            /// in the map
+            pub fn get(&self, key: &K) -> Option<&V> {
+                self.slot(key).map(|slot| &self.pair(slot).1)
+            }
+
+            /// Return the value stored for `key` for modification, or `None`
+            /// if the key is not in the map
+            pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+                let slot = self.slot(key)?;
+                self.values[slot].as_mut().map(|(_, value)| value)
+            }
+
+            /// Return true if the map holds a value for `key`
+            pub fn contains_key(&self, key: &K) -> bool {
+                self.slot(key).is_some()
+            }
+
+            /// Remove `key` from the map and return its value, or `None` if the
+            /// key was not in the map. Once more than half of the slots are
+            /// free, the values are moved together, which costs a rebuild of
+            /// the tree of keys.
+            pub fn remove(&mut self, key: &K) -> Option<V> {
+                let entry = self.keys.delete(Entry { key: key.clone(), slot: 0 })?;
+                let (_, value) = self.values[entry.slot].take()
+                    .expect("every key of the tree has a value");
+                self.free.push(entry.slot);
+                if self.free.len() > self.len() {
+                    self.compact();
+                }
+                Some(value)
+            }
+
+            /// Iterate the keys together with their values, in ascending order
+            /// of the keys
+            pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+                self.keys.iter().map(move |entry| {
+                    let (key, value) = self.pair(entry.slot);
+                    (key, value)
+                })
+            }
+        }
+
+        impl<K: Ord + Clone + fmt::Debug, V> Default for $name<K, V> {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+
+        impl<K: Ord + Clone + fmt::Debug, V: fmt::Debug> fmt::Debug for $name<K, V> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_map().entries(self.iter()).finish()
+            }
+        }
+    };
+}
+
+tree_map!(
+    /// A map whose keys are kept in a [BinarySearchTree](../bstree/struct.BinarySearchTree.html)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::map::BinarySearchTreeMap;
+    ///
+    /// let mut map = BinarySearchTreeMap::new();
+    /// println!("{:?}", map.insert(1, "one"));  // None
+    /// println!("{:?}", map.insert(1, "uno"));  // Some("one")
+    /// println!("{:?}", map.get(&1));  // Some("uno")
+    /// println!("{:?}", map.remove(&1));  // Some("uno")
+    /// println!("{:?}", map.get(&1));  // None
+    /// ```
+    BinarySearchTreeMap, BinarySearchTree
+);
+
+tree_map!(
+    /// A map whose keys are kept in an [AVLTree](../avltree/struct.AVLTree.html)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::map::AVLTreeMap;
+    ///
+    /// let mut map = AVLTreeMap::new();
+    /// println!("{:?}", map.insert(1, "one"));  // None
+    /// println!("{:?}", map.insert(1, "uno"));  // Some("one")
+    /// println!("{:?}", map.get(&1));  // Some("uno")
+    /// println!("{:?}", map.remove(&1));  // Some("uno")
+    /// println!("{:?}", map.get(&1));  // None
+    /// ```
+    AVLTreeMap, AVLTree
+);
+
+tree_map!(
+    /// A map whose keys are kept in a [RedBlackTree](../rbtree/struct.RedBlackTree.html)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::map::RedBlackTreeMap;
+    ///
+    /// let mut map = RedBlackTreeMap::new();
+    /// println!("{:?}", map.insert(1, "one"));  // None
+    /// println!("{:?}", map.insert(1, "uno"));  // Some("one")
+    /// println!("{:?}", map.get(&1));  // Some("uno")
+    /// println!("{:?}", map.remove(&1));  // Some("uno")
+    /// println!("{:?}", map.get(&1));  // None
+    /// ```
+    RedBlackTreeMap, RedBlackTree
+);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::BTreeMap;
+
+    macro_rules! check_map {
+        ($map:expr) => {{
+            let mut map = $map;
+            assert!(map.is_empty());
+            assert_eq!(map.insert(3, "three".to_string()), None);
+            assert_eq!(map.insert(1, "one".to_string()), None);
+            assert_eq!(map.insert(2, "two".to_string()), None);
+            assert_eq!(map.get(&1), Some(&"one".to_string()));
+            assert_eq!(map.get(&4), None);
+
+            // updating a key overwrites its value and keeps its node
+            assert_eq!(map.insert(1, "uno".to_string()), Some("one".to_string()));
+            assert_eq!(map.get(&1), Some(&"uno".to_string()));
+            assert_eq!(map.len(), 3);
+            assert_eq!(map.keys.iter().filter(|entry| entry.key == 1).count(), 1);
+            assert_eq!(map.values.len(), 3);
+            map.get_mut(&2).unwrap().push_str("!");
+            assert_eq!(
+                map.iter().map(|(k, v)| (*k, v.as_str())).collect::<Vec<_>>(),
+                vec![(1, "uno"), (2, "two!"), (3, "three")]
+            );
+
+            assert_eq!(map.remove(&2), Some("two!".to_string()));
+            assert_eq!(map.remove(&2), None);
+            assert!(!map.contains_key(&2));
+            assert_eq!(map.len(), 2);
+            // the freed slot is used again
+            assert_eq!(map.insert(5, "five".to_string()), None);
+            assert_eq!(map.values.len(), 3);
+            assert_eq!(format!("{:?}", map), r#"{1: "uno", 3: "three", 5: "five"}"#);
+
+            // random updates agree with the standard map
+            let mut expected: BTreeMap<_, _> = map.iter().map(|(k, v)| (*k, v.clone())).collect();
+            let mut rng = StdRng::from_seed([0u8; 32]);
+            for i in 0..2000 {
+                let k: i32 = rng.gen_range(0, 100);
+                if rng.gen_range(0, 3) == 0 {
+                    assert_eq!(map.remove(&k), expected.remove(&k));
+                } else {
+                    assert_eq!(map.insert(k, i.to_string()), expected.insert(k, i.to_string()));
+                }
+                assert_eq!(map.len(), expected.len());
+            }
+            assert!(map.iter().eq(expected.iter()));
+
+            // removing most keys gives the freed slots back
+            for k in 0..100 {
+                map.insert(k, k.to_string());
+            }
+            for k in 0..90 {
+                assert_eq!(map.remove(&k), Some(k.to_string()));
+                assert!(map.values.len() <= 2 * map.len() + 1);
+            }
+            assert_eq!(map.len(), 10);
+            assert!(map.values.len() < 20);
+            assert!(map.keys.is_valid());
+            let rest: Vec<(i32, String)> = (90..100).map(|k| (k, k.to_string())).collect();
+            assert!(map.iter().eq(rest.iter().map(|(k, v)| (k, v))));
+            assert_eq!(map.get(&95), Some(&"95".to_string()));
+        }};
+    }
+
+    #[test]
+    fn bst_map() {
+        check_map!(BinarySearchTreeMap::new());
+    }
+
+    #[test]
+    fn avl_map() {
+        check_map!(AVLTreeMap::new());
+    }
+
+    #[test]
+    fn rb_map() {
+        check_map!(RedBlackTreeMap::new());
+        assert!(RedBlackTreeMap::<i32, ()>::default().is_empty());
+    }
+}
@@ -0,0 +1,142 @@
+//! Numeric support for trees of integers and floats
+//!
+//! Some tree methods only make sense when the stored values are integers.
+//! They are available for every primitive integer type through the
+//! [Integer](trait.Integer.html) trait.
+//!
+//! Floats are not `Ord`, so they are stored wrapped in an
+//! [OrderedFloat](struct.OrderedFloat.html).
+//!
+//! ```
+//! use trees::num::{Integer, OrderedFloat};
+//! ```
+
+use std::cmp::Ordering;
+use std::convert::TryInto;
+use std::fmt;
+use std::ops::Add;
+
+/// Primitive integer types which can be stored in the trees
+pub trait Integer: Ord + Copy + fmt::Debug + Add<Output = Self> {
+    /// The value `0`
+    const ZERO: Self;
+
+    /// The value `1`
+    const ONE: Self;
+
+    /// Number of bytes used by the fixed-width encoding
+    const BYTES: usize;
+
+    /// Return the fixed-width little-endian encoding of the value
+    fn to_le_vec(self) -> Vec<u8>;
+
+    /// Decode a value from exactly [BYTES](#associatedconstant.BYTES)
+    /// little-endian bytes
+    fn from_le_slice(bytes: &[u8]) -> Self;
+
+    /// Return `self - rhs`, or `None` on overflow
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_integer {
+    ($($t:ty),*) => {
+        $(
+            impl Integer for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+                const BYTES: usize = std::mem::size_of::<$t>();
+
+                fn to_le_vec(self) -> Vec<u8> {
+                    self.to_le_bytes().to_vec()
+                }
+
+                fn from_le_slice(bytes: &[u8]) -> Self {
+                    <$t>::from_le_bytes(bytes.try_into().unwrap())
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// Return every integer from `lo` to `hi`, both included, in ascending order
+pub(crate) fn inclusive_range<T: Integer>(lo: T, hi: T) -> Vec<T> {
+    let mut values = vec![];
+    let mut v = lo;
+    while v <= hi {
+        values.push(v);
+        if v == hi {
+            break;
+        }
+        v = v + T::ONE;
+    }
+    values
+}
+
+/// A float with a total order, so that it can be stored in the trees.
+///
+/// Numbers compare like the float itself, so `-0.0` and `0.0` are equal.
+/// NaN is treated as the greatest value, greater than infinity, and every
+/// NaN is equal to every other.
+///
+/// # Example
+///
+/// ```
+/// use trees::bstree::BinarySearchTree;
+/// use trees::base::QueryableTree;
+/// use trees::num::OrderedFloat;
+///
+/// let mut bst = BinarySearchTree::new();
+/// for &v in [2.5, f64::NAN, -1.0, 0.5].iter() {
+///     bst.insert(OrderedFloat(v));
+/// }
+/// println!("{:?}", bst.min());  // Some(OrderedFloat(-1.0))
+/// println!("{:?}", bst.max());  // Some(OrderedFloat(NaN))
+/// assert!(bst.contains(OrderedFloat(0.5)));
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OrderedFloat<F>(pub F);
+
+macro_rules! impl_ordered_float {
+    ($($t:ty),*) => {
+        $(
+            impl Ord for OrderedFloat<$t> {
+                fn cmp(&self, other: &Self) -> Ordering {
+                    match (self.0.is_nan(), other.0.is_nan()) {
+                        (true, true) => Ordering::Equal,
+                        (true, false) => Ordering::Greater,
+                        (false, true) => Ordering::Less,
+                        (false, false) => self.0.partial_cmp(&other.0).unwrap(),
+                    }
+                }
+            }
+
+            impl PartialOrd for OrderedFloat<$t> {
+                fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                    Some(self.cmp(other))
+                }
+            }
+
+            impl PartialEq for OrderedFloat<$t> {
+                fn eq(&self, other: &Self) -> bool {
+                    self.cmp(other) == Ordering::Equal
+                }
+            }
+
+            impl Eq for OrderedFloat<$t> {}
+
+            impl From<$t> for OrderedFloat<$t> {
+                fn from(value: $t) -> Self {
+                    OrderedFloat(value)
+                }
+            }
+        )*
+    };
+}
+
+impl_ordered_float!(f32, f64);
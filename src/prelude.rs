@@ -11,4 +11,6 @@
 pub use crate::avltree::AVLTree;
 pub use crate::bstree::BinarySearchTree;
 pub use crate::rbtree::RedBlackTree;
-pub use crate::base::QueryableTree;
+pub use crate::base::{BuildError, QueryableTree, TreeOp};
+pub use crate::map::{AVLTreeMap, BinarySearchTreeMap, RedBlackTreeMap};
+pub use crate::num::OrderedFloat;
@@ -8,14 +8,17 @@
 //! use trees::base::QueryableTree;
 //! ```
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::fmt;
-use std::rc::Rc;
+use std::iter::FromIterator;
+use std::rc::{Rc, Weak};
 
-use crate::base::{QueryableTree, QueryableTreeNode};
+use crate::base::{self, QueryableTree, QueryableTreeNode, TreeOp, IntoIter};
+use crate::num::{self, Integer};
 
 type RcRefRBTNode<T> = Rc<RefCell<RedBlackTreeNode<T>>>;
 type RBNodeLink<T> = Option<RcRefRBTNode<T>>;
+type WeakRBNodeLink<T> = Weak<RefCell<RedBlackTreeNode<T>>>;
 
 /// Color representation for the [Node](struct.RedBlackTreeNode.html)
 /// of [RedBlackTree](struct.RedBlackTree.html) struct
@@ -28,17 +31,20 @@ pub enum NodeColor {
 }
 
 /// Node struct for [RedBlackTree](struct.RedBlackTree.html) struct
-pub struct RedBlackTreeNode<T: Ord + Copy + fmt::Debug> {
+pub struct RedBlackTreeNode<T: Ord + Clone + fmt::Debug> {
     /// Data stored in the node
     pub data: T,
     /// The color of the node
     pub color: NodeColor,
-    parent: RBNodeLink<T>,
+    // Weak, so that a child and its parent don't keep each other alive
+    parent: WeakRBNodeLink<T>,
     left: RBNodeLink<T>,
     right: RBNodeLink<T>,
+    size: usize,
+    sum: Cell<Option<T>>,
 }
 
-impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
+impl<T: Ord + Clone + fmt::Debug> RedBlackTreeNode<T> {
     // fn new(data: T) -> RcRefRBTNode<T> {
     //     Rc::new(RefCell::new(Self {
     //         data: data,
@@ -53,27 +59,85 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
         Rc::new(RefCell::new(Self {
             data: data,
             color,
-            parent,
+            parent: Self::downgrade(&parent),
             left: None,
             right: None,
+            size: 1,
+            sum: Cell::new(None),
         }))
     }
 
+    fn downgrade(link: &RBNodeLink<T>) -> WeakRBNodeLink<T> {
+        // Turn a parent link into the weak reference stored in the child.
+        link.as_ref().map_or_else(Weak::new, Rc::downgrade)
+    }
+
+    /// Copy the subtree rooted at `node` into freshly allocated nodes, each
+    /// copy pointing to the copy of its parent
+    fn deep_clone(node: &RcRefRBTNode<T>) -> RcRefRBTNode<T> {
+        base::clone_subtree(node, |node, parent| Self {
+            data: node.data.clone(),
+            color: node.color,
+            parent: parent.map_or_else(Weak::new, Rc::downgrade),
+            left: None,
+            right: None,
+            size: node.size,
+            sum: Cell::new(None),
+        })
+    }
+
+    /// Build a balanced subtree from sorted and deduplicated values. Every
+    /// path from the root down to an empty child then has the same number
+    /// of nodes up to one, so coloring the deepest level red, unless the
+    /// tree is perfect, gives every path the same number of black nodes.
+    fn from_sorted_values(values: &[T]) -> RBNodeLink<T> {
+        let n = values.len();
+        // n + 1 is a power of two exactly when the tree is perfect
+        let red_depth = if (n + 1).is_power_of_two() {
+            None
+        } else {
+            // the deepest level, floor(log2(n))
+            Some((usize::BITS - 1 - n.leading_zeros()) as usize)
+        };
+        Self::from_sorted(values, None, 0, red_depth)
+    }
+
+    /// Build the subtree at `depth` holding `values` below `parent`, see
+    /// [from_sorted_values](#method.from_sorted_values)
+    fn from_sorted(values: &[T], parent: RBNodeLink<T>, depth: usize, red_depth: Option<usize>) -> RBNodeLink<T> {
+        if values.is_empty() {
+            return None;
+        }
+        let mid = values.len() / 2;
+        let color = if red_depth == Some(depth) { NodeColor::Red } else { NodeColor::Black };
+        let node = Self::new(values[mid].clone(), color, parent);
+        let left = Self::from_sorted(&values[..mid], Some(node.clone()), depth + 1, red_depth);
+        let right = Self::from_sorted(&values[mid + 1..], Some(node.clone()), depth + 1, red_depth);
+        node.borrow_mut().left = left;
+        node.borrow_mut().right = right;
+        node.borrow_mut().size = values.len();
+        Some(node)
+    }
+
     // ------------------------------------------------------------------------
     // Here are some functions which are unique to red black tree
     
     /// Rotate the subtree rooted at this node to the right and
-    /// returns the new root to this subtree.
-    fn rotate_right(node: RcRefRBTNode<T>) -> RBNodeLink<T> {
-        let parent = node.borrow().parent.clone();
+    /// returns the new root to this subtree. When the node was the root of
+    /// the tree, `root` is moved to the new root, which would otherwise only
+    /// be held by the weak parent link of the old one.
+    fn rotate_right(node: RcRefRBTNode<T>, root: &mut RBNodeLink<T>) -> RBNodeLink<T> {
+        let parent = node.borrow().parent.upgrade();
         let left = node.borrow().left.clone();
         node.borrow_mut().left = left.clone().unwrap().borrow().right.clone();
         if node.borrow().left.is_some() {
             let left = node.borrow().left.clone().unwrap();
-            left.borrow_mut().parent = Some(node.clone());
+            left.borrow_mut().parent = Rc::downgrade(&node);
         }
-        node.borrow_mut().parent = left.clone();
+        node.borrow_mut().parent = Self::downgrade(&left);
         left.clone().unwrap().borrow_mut().right = Some(node.clone());
+        Self::update_size(&node);
+        Self::update_size(left.as_ref().unwrap());
         if parent.is_some() {
             let right = parent.clone().unwrap().borrow().right.clone();
             match right {
@@ -82,24 +146,29 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
                 }
                 _ => parent.clone().unwrap().borrow_mut().left = left.clone(),
             }
+        } else {
+            *root = left.clone();
         }
 
-        left.clone().unwrap().borrow_mut().parent = parent;
+        left.clone().unwrap().borrow_mut().parent = Self::downgrade(&parent);
         left
     }
 
     /// Rotate the subtree rooted at this node to the left and
-    /// return the new root to this subtree.
-    fn rotate_left(node: RcRefRBTNode<T>) -> RBNodeLink<T> {
-        let parent = node.borrow().parent.clone();
+    /// return the new root to this subtree, moving `root` like
+    /// [rotate_right](#method.rotate_right).
+    fn rotate_left(node: RcRefRBTNode<T>, root: &mut RBNodeLink<T>) -> RBNodeLink<T> {
+        let parent = node.borrow().parent.upgrade();
         let right = node.borrow().right.clone();
         node.borrow_mut().right = right.clone().unwrap().borrow().left.clone();
         if node.borrow().right.is_some() {
             let right = node.borrow().right.clone().unwrap();
-            right.borrow_mut().parent = Some(node.clone());
+            right.borrow_mut().parent = Rc::downgrade(&node);
         }
-        node.borrow_mut().parent = right.clone();
+        node.borrow_mut().parent = Self::downgrade(&right);
         right.clone().unwrap().borrow_mut().left = Some(node.clone());
+        Self::update_size(&node);
+        Self::update_size(right.as_ref().unwrap());
         if parent.is_some() {
             let left = parent.clone().unwrap().borrow().left.clone();
             match left {
@@ -108,57 +177,54 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
                 }
                 _ => parent.clone().unwrap().borrow_mut().right = right.clone(),
             }
+        } else {
+            *root = right.clone();
         }
 
-        right.clone().unwrap().borrow_mut().parent = parent;
+        right.clone().unwrap().borrow_mut().parent = Self::downgrade(&parent);
         right
     }
 
-    /// Insert data into the subtree rooted at self,performs any rotations
-    /// necessary to maintain banlance, and then returns the new root to this subtree.
-    fn insert(node: RcRefRBTNode<T>, data: T) -> RBNodeLink<T> {
-        let node_data = node.borrow().data;
-        if node_data == data {
-            return Some(node);
-        } else if node_data > data {
+    /// Insert data into the subtree rooted at self, and performs any rotations
+    /// necessary to maintain banlance, moving `root` if the root of the tree changes.
+    fn insert(node: RcRefRBTNode<T>, data: T, root: &mut RBNodeLink<T>) {
+        let node_data = node.borrow().data.clone();
+        // an equal value is already in the tree, so there is nothing to do
+        // when node_data == data
+        if node_data > data {
             let left = node.borrow().left.clone();
             match left {
                 Some(left) => {
-                    Self::insert(left, data);
+                    Self::insert(left, data, root);
                 }
                 None => {
                     node.borrow_mut().left =
                         Some(Self::new(data, NodeColor::Red, Some(node.clone())));
+                    Self::update_size_upward(node.clone());
                     let left = node.borrow().left.clone();
-                    Self::insert_repair(left.unwrap());
+                    Self::insert_repair(left.unwrap(), root);
                 }
             }
-        } else {
+        } else if node_data < data {
             let right = node.borrow().right.clone();
             match right {
                 Some(right) => {
-                    Self::insert(right, data);
+                    Self::insert(right, data, root);
                 }
                 None => {
                     node.borrow_mut().right =
                         Some(Self::new(data, NodeColor::Red, Some(node.clone())));
+                    Self::update_size_upward(node.clone());
                     let right = node.borrow().right.clone().unwrap();
-                    Self::insert_repair(right);
+                    Self::insert_repair(right, root);
                 }
             }
         }
-
-        let parent = node.borrow().parent.clone();
-        if parent.is_some() {
-            parent
-        } else {
-            Some(node)
-        }
     }
 
     /// Repair the coloring from inserting into a tree.
-    fn insert_repair(node: RcRefRBTNode<T>) {
-        let parent = node.borrow().parent.clone();
+    fn insert_repair(node: RcRefRBTNode<T>, root: &mut RBNodeLink<T>) {
+        let parent = node.borrow().parent.upgrade();
         match parent {
             //This node is the root,so it just needs to be black
             None => node.borrow_mut().color = NodeColor::Black,
@@ -171,25 +237,25 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
                 match Self::color(uncle.clone()) {
                     NodeColor::Black => {
                         if Self::is_left(node.clone()) && Self::is_right(parent.clone()) {
-                            Self::rotate_right(parent);
+                            Self::rotate_right(parent, root);
                             let right = node.borrow().right.clone();
-                            Self::insert_repair(right.unwrap())
+                            Self::insert_repair(right.unwrap(), root)
                         } else if Self::is_right(node.clone()) && Self::is_left(parent.clone()) {
-                            Self::rotate_left(parent);
+                            Self::rotate_left(parent, root);
                             let left = node.borrow().left.clone();
-                            Self::insert_repair(left.unwrap());
+                            Self::insert_repair(left.unwrap(), root);
                         } else if Self::is_left(node.clone()) {
                             let grandparent = Self::grandparent(node.clone());
-                            Self::rotate_right(grandparent.unwrap());
-                            let parent = node.borrow().parent.clone();
+                            Self::rotate_right(grandparent.unwrap(), root);
+                            let parent = node.borrow().parent.upgrade();
                             let parent = parent.unwrap();
                             parent.borrow_mut().color = NodeColor::Black;
                             let right = parent.borrow().right.clone();
                             right.unwrap().borrow_mut().color = NodeColor::Red;
                         } else {
                             let grandparent = Self::grandparent(node.clone());
-                            Self::rotate_left(grandparent.unwrap());
-                            let parent = node.borrow().parent.clone();
+                            Self::rotate_left(grandparent.unwrap(), root);
+                            let parent = node.borrow().parent.upgrade();
                             let parent = parent.unwrap();
                             parent.borrow_mut().color = NodeColor::Black;
                             let left = parent.borrow().left.clone();
@@ -201,16 +267,17 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
                         uncle.unwrap().borrow_mut().color = NodeColor::Black;
                         let grandparent = Self::grandparent(node.clone()).unwrap();
                         grandparent.borrow_mut().color = NodeColor::Red;
-                        Self::insert_repair(grandparent);
+                        Self::insert_repair(grandparent, root);
                     }
                 }
             }
         }
     }
 
-    /// Delete data from this tree
-    fn delete(node: RcRefRBTNode<T>, val: T) -> RBNodeLink<T> {
-        let node_data = node.borrow().data;
+    /// Delete data from this tree, moving `root` if the root of the tree changes,
+    /// and return the stored value which was removed
+    fn delete(node: RcRefRBTNode<T>, val: T, root: &mut RBNodeLink<T>) -> Option<T> {
+        let node_data = node.borrow().data.clone();
         if node_data == val {
             let left = node.borrow().left.clone();
             let right = node.borrow().right.clone();
@@ -220,13 +287,13 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
             //delete that.  
                 (Some(left), Some(_right)) => {
                     let v = Self::get_max(left.clone());
-                    node.borrow_mut().data = v;
-                    Self::delete(left, v);
+                    node.borrow_mut().data = v.clone();
+                    Self::delete(left, v, root);
                 }
             //This node has at most one non-None child,so we don't need to replace    
                 _ => {
                     if node.borrow().color == NodeColor::Red {
-                        let parent = node.borrow().parent.clone().unwrap();
+                        let parent = node.borrow().parent.upgrade().unwrap();
                     //This node is red, and its child is black
                     //The only way this happens to a node with one child
                     //if both children are None leaves,
@@ -236,23 +303,25 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
                         } else {
                             parent.borrow_mut().right = None;
                         }
+                        Self::update_size_upward(parent);
                     } else {
                         //The node is black
                         if left.is_none() && right.is_none() {
-                            let parent = node.borrow().parent.clone();
+                            let parent = node.borrow().parent.upgrade();
                             match parent {
-                                None => return None,
+                                None => *root = None,
                                 //This node and its child are black
                                 Some(_parent) => {
-                                    Self::delete_repair(node.clone());
-                                    let parent = node.borrow().parent.clone();
+                                    Self::delete_repair(node.clone(), root);
+                                    let parent = node.borrow().parent.upgrade();
                                     let parent = parent.unwrap();
                                     if Self::is_left(node.clone()) {
                                         parent.borrow_mut().left = None;
                                     } else {
                                         parent.borrow_mut().right = None;
                                     }
-                                    node.borrow_mut().parent = None;
+                                    node.borrow_mut().parent = Weak::new();
+                                    Self::update_size_upward(parent);
                                 }
                             }
                         }
@@ -260,7 +329,7 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
                         // Move the child node here and make it black  
                         else {
                             let child = left.unwrap_or_else(|| right.unwrap());
-                            let child_data = child.borrow().data;
+                            let child_data = child.borrow().data.clone();
                             let child_left = child.borrow().left.clone();
                             let child_right = child.borrow().right.clone();
                             node.borrow_mut().data = child_data;
@@ -268,53 +337,44 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
                             node.borrow_mut().right = child_right;
                             if node.borrow().left.is_some() {
                                 let left = node.borrow().left.clone().unwrap();
-                                left.borrow_mut().parent = Some(node.clone());
+                                left.borrow_mut().parent = Rc::downgrade(&node);
                             }
                             if node.borrow().right.is_some() {
                                 let right = node.borrow().right.clone().unwrap();
-                                right.borrow_mut().parent = Some(node.clone());
+                                right.borrow_mut().parent = Rc::downgrade(&node);
                             }
+                            Self::update_size_upward(node.clone());
                         }
                     }
                 }
             }
+            Some(node_data)
         } else if node_data > val {
             let left = node.borrow().left.clone();
-            if left.is_some() {
-                Self::delete(left.unwrap(), val);
-            }
+            left.and_then(|left| Self::delete(left, val, root))
         } else {
             let right = node.borrow().right.clone();
-            if right.is_some() {
-                Self::delete(right.unwrap(), val);
-            }
-        }
-
-        let parent = node.borrow().parent.clone();
-        if parent.is_some() {
-            parent
-        } else {
-            Some(node)
+            right.and_then(|right| Self::delete(right, val, root))
         }
     }
 
     /// Repair the coloring of the tree that may have been messed up.
-    fn delete_repair(node: RcRefRBTNode<T>) {
+    fn delete_repair(node: RcRefRBTNode<T>, root: &mut RBNodeLink<T>) {
         let node_sibling = Self::sibling(node.clone());
         if Self::color(node_sibling.clone()) == NodeColor::Red {
             let node_sibling = node_sibling.unwrap();
             node_sibling.borrow_mut().color = NodeColor::Black;
-            let parent = node.borrow().parent.clone().unwrap();
+            let parent = node.borrow().parent.upgrade().unwrap();
             parent.borrow_mut().color = NodeColor::Red;
             if Self::is_left(node.clone()) {
-                Self::rotate_left(parent);
+                Self::rotate_left(parent, root);
             } else {
-                Self::rotate_right(parent);
+                Self::rotate_right(parent, root);
             }
         }
 
         let node_sibling = Self::sibling(node.clone());
-        let parent = node.borrow().parent.clone();
+        let parent = node.borrow().parent.upgrade();
         if Self::color(parent.clone()) == NodeColor::Black
             && Self::color(node_sibling.clone()) == NodeColor::Black
         {   //fixed
@@ -325,7 +385,7 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
             let right = node_sibling.borrow().right.clone();
             if Self::color(left) == NodeColor::Black && Self::color(right) == NodeColor::Black {
                 node_sibling.borrow_mut().color = NodeColor::Red;
-                Self::delete_repair(parent.unwrap());
+                Self::delete_repair(parent.unwrap(), root);
                 return;
             }
         }
@@ -334,7 +394,7 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
         }
      }
         let node_sibling = Self::sibling(node.clone());
-        let parent = node.borrow().parent.clone();
+        let parent = node.borrow().parent.upgrade();
         if Self::color(parent.clone()) == NodeColor::Red
             && Self::color(node_sibling.clone()) == NodeColor::Black
         {
@@ -355,7 +415,7 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
             let right = node_sibling.borrow().right.clone();
             if Self::color(right.clone()) == NodeColor::Black && Self::color(left) == NodeColor::Red
             {
-                Self::rotate_right(node_sibling);
+                Self::rotate_right(node_sibling, root);
                 let node_sibling = Self::sibling(node.clone());
                 let node_sibling = node_sibling.unwrap();
                 node_sibling.borrow_mut().color = NodeColor::Black;
@@ -373,7 +433,7 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
             if Self::color(right.clone()) == NodeColor::Red
                 && Self::color(left.clone()) == NodeColor::Black
             {
-                Self::rotate_left(node_sibling);
+                Self::rotate_left(node_sibling, root);
                 let node_sibling = Self::sibling(node.clone());
                 let node_sibling = node_sibling.unwrap();
                 node_sibling.borrow_mut().color = NodeColor::Black;
@@ -388,10 +448,10 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
             let node_sibling = node_sibling.unwrap();
             let right = node_sibling.borrow().right.clone();
             if Self::color(right.clone()) == NodeColor::Red {
-                let parent = node.borrow().parent.clone();
-                Self::rotate_left(parent.unwrap());
+                let parent = node.borrow().parent.upgrade();
+                Self::rotate_left(parent.unwrap(), root);
                 let grandparent = Self::grandparent(node.clone()).unwrap();
-                let parent = node.borrow().parent.clone();
+                let parent = node.borrow().parent.upgrade();
                 let parent = parent.unwrap();
                 grandparent.borrow_mut().color = parent.borrow().color;
                 parent.borrow_mut().color = NodeColor::Black;
@@ -405,9 +465,9 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
             let node_sibling = node_sibling.unwrap();
             let left = node_sibling.borrow().left.clone();
             if Self::color(left.clone()) == NodeColor::Red {
-                Self::rotate_right(parent.clone().unwrap());
+                Self::rotate_right(parent.clone().unwrap(), root);
                 let grandparent = Self::grandparent(node.clone()).unwrap();
-                let parent = node.borrow().parent.clone();
+                let parent = node.borrow().parent.upgrade();
                 let parent = parent.unwrap();
                 grandparent.borrow_mut().color = parent.borrow().color;
                 parent.borrow_mut().color = NodeColor::Black;
@@ -416,7 +476,6 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
             }
         }
     }
-    #[allow(dead_code)]
      /// Check the coloring of the tree, and return true if the tree
      /// is colored in a way which matches these 5 Properties:
      ///   1. Each node is either red or black
@@ -516,7 +575,7 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
     fn search(node: RcRefRBTNode<T>, v: T) -> RBNodeLink<T> {
          //Search through the trees for data, returning its node if it is 
         //found and None otherwise.
-        let node_data = node.borrow().data;
+        let node_data = node.borrow().data.clone();
         if node_data == v {
             Some(node)
         } else if v > node_data {
@@ -539,21 +598,21 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
        match node.borrow().right.clone() {
            // go as far right as possible
            Some(right) => Self::get_max(right),
-           None => node.borrow().data,
+           None => node.borrow().data.clone(),
        }
    }
 
     fn grandparent(node: RcRefRBTNode<T>) -> RBNodeLink<T> {
         // Get the current node's grandparent, or None if it does not exist.
-        match node.borrow().parent.clone() {
-            Some(parent) => parent.borrow().parent.clone(),
+        match node.borrow().parent.upgrade() {
+            Some(parent) => parent.borrow().parent.upgrade(),
             _ => None,
         }
     }
 
     fn sibling(node: RcRefRBTNode<T>) -> RBNodeLink<T> {
         // Get the current node's sibling, or None if it does not exist.
-        match node.borrow().parent.clone() {
+        match node.borrow().parent.upgrade() {
             None => None,
             Some(parent) => {
                 let left = parent.borrow().left.clone();
@@ -567,7 +626,7 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
 
     fn is_left(node: RcRefRBTNode<T>) -> bool {
         // Return true if the node is the left child of its parent.
-        match node.borrow().parent.clone() {
+        match node.borrow().parent.upgrade() {
             Some(parent) => match parent.borrow().left.clone() {
                 Some(left) => Rc::ptr_eq(&left, &node),
                 None => false,
@@ -578,7 +637,7 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
 
     fn is_right(node: RcRefRBTNode<T>) -> bool {
         // Return true if the node is the right child of its parent.
-        match node.borrow().parent.clone() {
+        match node.borrow().parent.upgrade() {
             Some(parent) => match parent.borrow().right.clone() {
                 Some(right) => Rc::ptr_eq(&right, &node),
                 None => false,
@@ -587,6 +646,24 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
         }
     }
 
+    fn update_size(node: &RcRefRBTNode<T>) {
+        // Recompute the size of the subtree from the sizes of the children,
+        // and drop the cached sum which is recomputed on demand.
+        let left_size = node.borrow().left.as_ref().map_or(0, |l| l.borrow().size);
+        let right_size = node.borrow().right.as_ref().map_or(0, |r| r.borrow().size);
+        node.borrow_mut().size = left_size + right_size + 1;
+        node.borrow().sum.set(None);
+    }
+
+    fn update_size_upward(node: RcRefRBTNode<T>) {
+        // Recompute the sizes from this node up to the root.
+        let mut node = Some(node);
+        while let Some(n) = node {
+            Self::update_size(&n);
+            node = n.borrow().parent.upgrade();
+        }
+    }
+
     fn color(node: RBNodeLink<T>) -> NodeColor {
         // Return the color of a node,allowing for none leaves.
         match node {
@@ -600,8 +677,8 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
             (None, None) => true,
             (Some(_), None) | (None, Some(_)) => false,
             (Some(left), Some(right)) => {
-                let left_data = left.borrow().data;
-                let right_data = right.borrow().data;
+                let left_data = left.borrow().data.clone();
+                let right_data = right.borrow().data.clone();
                 //Test if 2 trees are equal
                 if left_data == right_data {
                     let left_left = left.borrow().left.clone();
@@ -618,7 +695,7 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
 
     #[allow(dead_code)]
     fn preorder_traverse(node: RcRefRBTNode<T>, container: &mut Vec<T>) {
-        container.push(node.borrow().data);
+        container.push(node.borrow().data.clone());
         let left = node.borrow().left.clone();
         if left.is_some() {
             Self::preorder_traverse(left.unwrap(), container);
@@ -634,12 +711,107 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
         if left.is_some() {
             Self::inorder_traverse(left.unwrap(), container);
         }
-        container.push(node.borrow().data);
+        container.push(node.borrow().data.clone());
         let right = node.borrow().right.clone();
         if right.is_some() {
             Self::inorder_traverse(right.unwrap(), container);
         }
     }
+    fn inorder_colored_traverse(node: RcRefRBTNode<T>, container: &mut Vec<(T, NodeColor)>) {
+        let left = node.borrow().left.clone();
+        if let Some(left) = left {
+            Self::inorder_colored_traverse(left, container);
+        }
+        container.push((node.borrow().data.clone(), node.borrow().color));
+        let right = node.borrow().right.clone();
+        if let Some(right) = right {
+            Self::inorder_colored_traverse(right, container);
+        }
+    }
+    #[cfg(feature = "serde")]
+    fn preorder_colored_traverse(node: RcRefRBTNode<T>, container: &mut Vec<(T, NodeColor)>) {
+        container.push((node.borrow().data.clone(), node.borrow().color));
+        let left = node.borrow().left.clone();
+        if let Some(left) = left {
+            Self::preorder_colored_traverse(left, container);
+        }
+        let right = node.borrow().right.clone();
+        if let Some(right) = right {
+            Self::preorder_colored_traverse(right, container);
+        }
+    }
+    #[cfg(feature = "serde")]
+    fn from_preorder(pairs: &[(T, NodeColor)]) -> Option<RBNodeLink<T>> {
+        // Rebuild the tree whose values and colors are listed in preorder, in
+        // one pass over the pairs. `path` holds the nodes which may still get
+        // a right child together with their depths, and every later value
+        // must be larger than `lower`, the last node a right child went to.
+        // Return None if the values are not the preorder of a binary search
+        // tree, or if the tree gets deeper than a red-black tree of as many
+        // nodes can be, so that checking its colors can't recurse too deep.
+        use std::cmp::Ordering;
+
+        let mut pairs_iter = pairs.iter();
+        let (data, color) = match pairs_iter.next() {
+            Some(pair) => pair,
+            None => return Some(None),
+        };
+        // a red-black tree of n nodes is at most 2 log2(n + 1) levels deep
+        let max_depth = 2 * (usize::BITS - pairs.len().leading_zeros()) as usize;
+        let root = Self::new(data.clone(), *color, None);
+        let mut nodes = vec![root.clone()];
+        let mut path = vec![(root.clone(), 1)];
+        let mut lower: Option<RcRefRBTNode<T>> = None;
+        for (data, color) in pairs_iter {
+            if lower.as_ref().is_some_and(|low| *data <= low.borrow().data) {
+                return None;
+            }
+            let mut popped = None;
+            while let Some((top, _)) = path.last() {
+                let order = data.cmp(&top.borrow().data);
+                match order {
+                    Ordering::Greater => popped = path.pop(),
+                    Ordering::Equal => return None,
+                    Ordering::Less => break,
+                }
+            }
+            let (parent, depth, is_right) = match popped {
+                Some((parent, depth)) => (parent, depth, true),
+                None => {
+                    let (parent, depth) = path.last()?;
+                    (parent.clone(), *depth, false)
+                }
+            };
+            if depth + 1 > max_depth {
+                return None;
+            }
+            let node = Self::new(data.clone(), *color, Some(parent.clone()));
+            if is_right {
+                parent.borrow_mut().right = Some(node.clone());
+                lower = Some(parent);
+            } else {
+                parent.borrow_mut().left = Some(node.clone());
+            }
+            nodes.push(node.clone());
+            path.push((node, depth + 1));
+        }
+        // in reverse preorder the children come before their parents
+        for node in nodes.iter().rev() {
+            Self::update_size(node);
+        }
+        Some(Some(root))
+    }
+    fn count_color_changes(node: RcRefRBTNode<T>) -> usize {
+        let color = node.borrow().color;
+        let mut count = 0;
+        for child in [node.borrow().left.clone(), node.borrow().right.clone()].iter().flatten() {
+            if child.borrow().color != color {
+                count += 1;
+            }
+            count += Self::count_color_changes(child.clone());
+        }
+        count
+    }
     #[allow(dead_code)]
     fn postorder_traverse(node: RcRefRBTNode<T>, container: &mut Vec<T>) {
         let left = node.borrow().left.clone();
@@ -650,67 +822,61 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTreeNode<T> {
         if right.is_some() {
             Self::postorder_traverse(right.unwrap(), container);
         }
-        container.push(node.borrow().data);
+        container.push(node.borrow().data.clone());
     }
 
-    fn clear(&mut self) {
-        self.parent = None;
-        match self.left.take() {
-            None => {},
-            Some(node) => {
-                node.borrow_mut().clear();
-            }
-        }
-        self.left = None;
-        match self.right.take() {
-            None => {},
-            Some(node) => {
-                node.borrow_mut().clear();
-            }
-        }
-        self.right = None;
-    }
 }
 
 /// An implementation of [Red-black Tree](https://en.wikipedia.org/wiki/Red%E2%80%93black_tree)
-pub struct RedBlackTree<T: Ord + Copy + fmt::Debug> {
+pub struct RedBlackTree<T: Ord + Clone + fmt::Debug> {
     root: RBNodeLink<T>,
+    history: Option<Vec<T>>,
 }
 
-impl<T: Ord + Copy + fmt::Debug> Drop for RedBlackTree<T> {
-    fn drop(&mut self) {
-        match self.root.take() {
-            Some(node) => node.borrow_mut().clear(),
-            None => return
-        }
-    }
-}
-
-impl<T: Ord + Copy + fmt::Debug> Drop for RedBlackTreeNode<T> {
+impl<T: Ord + Clone + fmt::Debug> Drop for RedBlackTree<T> {
     fn drop(&mut self) {
         self.clear();
     }
 }
 
-impl<T: Ord + Copy + fmt::Debug> QueryableTreeNode<T> for RedBlackTreeNode<T> {
+impl<T: Ord + Clone + fmt::Debug> QueryableTreeNode<T> for RedBlackTreeNode<T> {
     fn get_left(&self) -> &RBNodeLink<T> {
         return &self.left;
     }
     fn get_right(&self) -> &RBNodeLink<T> {
         return &self.right;
     }
+    fn get_left_mut(&mut self) -> &mut RBNodeLink<T> {
+        &mut self.left
+    }
+    fn get_right_mut(&mut self) -> &mut RBNodeLink<T> {
+        &mut self.right
+    }
     fn get_data(&self) -> T {
-        return self.data;
+        return self.data.clone();
+    }
+    fn len(&self) -> usize {
+        self.size
+    }
+    fn get_sum_cache(&self) -> Option<&Cell<Option<T>>> {
+        Some(&self.sum)
+    }
+    fn label(&self) -> String {
+        format!("{:?} ({:?})", self.data, self.color)
     }
 }
 
-impl<T: Ord + Copy + fmt::Debug> QueryableTree<T, RedBlackTreeNode<T>> for RedBlackTree<T> {
+impl<T: Ord + Clone + fmt::Debug> QueryableTree<T, RedBlackTreeNode<T>> for RedBlackTree<T> {
     fn get_root(&self) -> &RBNodeLink<T> {
         &self.root
     }
+
+    fn is_valid(&self) -> bool {
+        self.is_bst() && self.is_valid_rb()
+    }
 }
 
-impl<T: Ord + Copy + fmt::Debug> RedBlackTree<T> {
+impl<T: Ord + Clone + fmt::Debug> RedBlackTree<T> {
     /// Create a new Red-black Tree
     ///
     /// # Example
@@ -721,7 +887,42 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTree<T> {
     /// let mut rbt: RedBlackTree<i32> = RedBlackTree::new();
     /// ```
     pub fn new() -> Self {
-        Self { root: None }
+        Self { root: None, history: None }
+    }
+
+    /// Create a new Red-black Tree which records every value passed to
+    /// [insert](#method.insert), see [insertion_history](#method.insertion_history)
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    ///
+    /// let mut rbt: RedBlackTree<i32> = RedBlackTree::with_history();
+    /// ```
+    pub fn with_history() -> Self {
+        Self { root: None, history: Some(vec![]) }
+    }
+
+    /// Build a balanced tree from values in strictly increasing order in O(n),
+    /// taking the middle value as the root of every subtree, so the height is
+    /// the smallest possible. The nodes on the deepest
+    /// level are colored red unless the tree is perfect, all the others
+    /// black, which satisfies the red-black properties.
+    ///
+    /// The slice must be sorted and deduplicated, which is not checked.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let rbt = RedBlackTree::from_sorted(&[1, 2, 3, 4, 5, 6, 7]);
+    /// println!("{}", rbt.height());  // 3
+    /// ```
+    pub fn from_sorted(values: &[T]) -> Self {
+        Self { root: RedBlackTreeNode::from_sorted_values(values), history: None }
     }
 
     // /// Create a new Red-black Tree
@@ -745,7 +946,8 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTree<T> {
     //     }
     // }
 
-    /// Insert a new value to the tree
+    /// Insert a new value to the tree, returning `true` if it was added and
+    /// `false` if an equal value was already present
     ///
     /// # Example
     ///
@@ -753,27 +955,325 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTree<T> {
     /// use trees::rbtree::RedBlackTree;
     ///
     /// let mut rbt = RedBlackTree::new();
-    /// rbt.insert(1);
+    /// assert!(rbt.insert(1));
+    /// assert!(!rbt.insert(1));
     /// ```
-    pub fn insert(&mut self, val: T) {
+    pub fn insert(&mut self, val: T) -> bool {
+        if let Some(history) = self.history.as_mut() {
+            history.push(val.clone());
+        }
+        let len = self.len();
         match self.root.clone() {
-            Some(root) => {
-                let r = RedBlackTreeNode::insert(root, val);
-                self.root = r;
-            }
+            Some(root) => RedBlackTreeNode::insert(root, val, &mut self.root),
             None => {
                 self.root = Some(Rc::new(RefCell::new(RedBlackTreeNode {
                     data: val,
                     color: NodeColor::Black,
-                    parent: None,
+                    parent: Weak::new(),
                     left: None,
                     right: None,
+                    size: 1,
+                    sum: Cell::new(None),
                 })));
             }
         }
+        self.len() > len
+    }
+
+    /// Delete a value from the tree, returning the stored value which was
+    /// removed or `None` if there was no equal value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// assert_eq!(rbt.delete(1), None);
+    /// rbt.insert(1);
+    /// assert_eq!(rbt.delete(1), Some(1));
+    /// ```
+    pub fn delete(&mut self, val: T) -> Option<T> {
+        let root = self.root.clone()?;
+        RedBlackTreeNode::delete(root, val, &mut self.root)
+    }
+
+    /// Remove every value from the tree, keeping the tree itself for reuse.
+    /// The nodes are freed one at a time, so clearing a large tree can't
+    /// overflow the stack. A recorded
+    /// insertion history is kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// rbt.insert(1);
+    /// rbt.insert(2);
+    /// rbt.clear();
+    /// assert!(rbt.is_empty());
+    /// ```
+    pub fn clear(&mut self) {
+        let mut stack: Vec<_> = self.root.take().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            let mut node = node.borrow_mut();
+            stack.extend(node.left.take());
+            stack.extend(node.right.take());
+        }
+    }
+
+    /// Consume the tree and return its values in ascending order, like
+    /// [BinarySearchTree::into_sorted_vec](../bstree/struct.BinarySearchTree.html#method.into_sorted_vec)
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        self.into_iter().collect()
+    }
+
+    /// Swap the two children of every node, like
+    /// [BinarySearchTree::mirror](../bstree/struct.BinarySearchTree.html#method.mirror).
+    /// The colors and parents of the nodes stay as they were.
+    pub fn mirror(&mut self) {
+        base::mirror_subtree::<T, _>(&self.root);
+    }
+
+    /// Remove the smallest value from the tree and return it, or `None` if
+    /// the tree is empty.
+    ///
+    /// The value goes through the normal [delete](#method.delete), so the
+    /// red-black properties are kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// rbt.insert(2);
+    /// rbt.insert(1);
+    /// assert_eq!(rbt.pop_min(), Some(1));
+    /// assert_eq!(rbt.pop_min(), Some(2));
+    /// assert_eq!(rbt.pop_min(), None);
+    /// ```
+    pub fn pop_min(&mut self) -> Option<T> {
+        let min = self.min()?;
+        self.delete(min.clone());
+        Some(min)
+    }
+
+    /// Insert `val` and pop the smallest value once the tree holds more
+    /// than `window`, like
+    /// [BinarySearchTree::insert_windowed](../bstree/struct.BinarySearchTree.html#method.insert_windowed)
+    pub fn insert_windowed(&mut self, val: T, window: usize) -> Option<T> {
+        self.insert(val);
+        if self.len() > window {
+            self.pop_min()
+        } else {
+            None
+        }
+    }
+
+    /// Remove the largest value from the tree and return it, or `None` if
+    /// the tree is empty.
+    ///
+    /// Like [pop_min](#method.pop_min), this goes through the normal
+    /// [delete](#method.delete).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// rbt.insert(1);
+    /// rbt.insert(2);
+    /// assert_eq!(rbt.pop_max(), Some(2));
+    /// assert_eq!(rbt.pop_max(), Some(1));
+    /// assert_eq!(rbt.pop_max(), None);
+    /// ```
+    pub fn pop_max(&mut self) -> Option<T> {
+        let max = self.max()?;
+        self.delete(max.clone());
+        Some(max)
+    }
+
+    /// Move every value not less than `key` into a new tree and return it,
+    /// keeping the smaller values. Both halves are rebuilt balanced and validly colored from
+    /// their sorted values. The returned tree doesn't record any history.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut rbt: RedBlackTree<i32> = (1..=6).collect();
+    /// let upper = rbt.split_off(4);
+    /// println!("{:?}", rbt.to_vec());  // [1, 2, 3]
+    /// println!("{:?}", upper.to_vec());  // [4, 5, 6]
+    /// ```
+    pub fn split_off(&mut self, key: T) -> Self {
+        let mut lower = self.to_vec();
+        let upper = lower.split_off(lower.partition_point(|v| *v < key));
+        self.clear();
+        self.root = RedBlackTreeNode::from_sorted_values(&lower);
+        Self::from_sorted(&upper)
+    }
+
+    /// Return the values passed to [insert](#method.insert) in call order,
+    /// or `None` if the tree was not created by
+    /// [with_history](#method.with_history).
+    ///
+    /// Every call is recorded, including values which were already in the
+    /// tree and therefore did not change it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    ///
+    /// let mut rbt = RedBlackTree::with_history();
+    /// rbt.insert(2);
+    /// rbt.insert(1);
+    /// rbt.insert(2);
+    /// assert_eq!(rbt.insertion_history(), Some(&[2, 1, 2][..]));
+    /// assert_eq!(RedBlackTree::<i32>::new().insertion_history(), None);
+    /// ```
+    pub fn insertion_history(&self) -> Option<&[T]> {
+        self.history.as_deref()
+    }
+
+    /// Delete every value for which `pred` returns `true`, and return how
+    /// many values were deleted
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// for v in 0..10 {
+    ///     rbt.insert(v);
+    /// }
+    /// assert_eq!(rbt.remove_where(|v| v % 3 == 0), 4);
+    /// assert_eq!(rbt.to_vec(), vec![1, 2, 4, 5, 7, 8]);
+    /// ```
+    pub fn remove_where<F: Fn(&T) -> bool>(&mut self, pred: F) -> usize {
+        let values: Vec<T> = self.iter().collect();
+        let survivors: Vec<T> = values.iter().filter(|v| !pred(v)).cloned().collect();
+        let removed = values.len() - survivors.len();
+        if removed > 0 {
+            self.clear();
+            self.root = RedBlackTreeNode::from_sorted_values(&survivors);
+        }
+        removed
+    }
+
+    /// Apply `ops` in order and return whether the tree was still
+    /// [valid](../base/trait.QueryableTree.html#method.is_valid) after every
+    /// step. Stops at the first step which leaves the tree invalid.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    /// use trees::base::TreeOp;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// assert!(rbt.apply_ops(&[TreeOp::Insert(2), TreeOp::Insert(1), TreeOp::Delete(2)]));
+    /// ```
+    pub fn apply_ops(&mut self, ops: &[TreeOp<T>]) -> bool {
+        for op in ops {
+            match op {
+                TreeOp::Insert(val) => { self.insert(val.clone()); }
+                TreeOp::Delete(val) => { self.delete(val.clone()); }
+            }
+            if !self.is_valid() {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Return true if the coloring of the tree satisfies the red-black
+    /// properties: every node is red or black, the root is black, the empty
+    /// leaves count as black, a red node has no red child, and every path
+    /// from a node down to an empty leaf crosses the same number of black
+    /// nodes. The empty tree is valid. The search ordering is not checked
+    /// here, [is_valid](../base/trait.QueryableTree.html#method.is_valid)
+    /// checks both.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// for v in 0..100 {
+    ///     rbt.insert(v);
+    /// }
+    /// for v in (0..100).step_by(3) {
+    ///     rbt.delete(v);
+    /// }
+    /// assert!(rbt.is_valid_rb());
+    /// ```
+    pub fn is_valid_rb(&self) -> bool {
+        self.root.as_ref().is_none_or(
+            |root| RedBlackTreeNode::check_color_properties(root.clone())
+        )
+    }
+
+    /// Return the black-height of the tree, the number of black nodes on
+    /// any path from the root down to a leaf, or None if two such paths
+    /// cross a different number of black nodes. The empty leaves are not
+    /// counted, so the empty tree has a black-height of 0.
+    ///
+    /// Only the equal number of black nodes is checked, the other coloring
+    /// properties like a black root or no red child below a red node are
+    /// not: a tree with a red root still has a black-height. Use
+    /// [is_valid_rb](#method.is_valid_rb) to check all of them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// println!("{:?}", rbt.black_height());  // Some(0)
+    /// for v in 0..7 {
+    ///     rbt.insert(v);
+    /// }
+    /// println!("{:?}", rbt.black_height());  // Some(2)
+    /// ```
+    pub fn black_height(&self) -> Option<usize> {
+        RedBlackTreeNode::black_height(self.root.clone()).map(|height| height - 1)
+    }
+
+    /// Iterate the values together with the color of their nodes, in
+    /// [inorder](https://en.wikipedia.org/wiki/Tree_traversal#In-order_(LNR))
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::{RedBlackTree, NodeColor};
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// rbt.insert(1);
+    /// rbt.insert(0);
+    /// let colored: Vec<_> = rbt.iter_colored().collect();
+    /// assert_eq!(colored, vec![(0, NodeColor::Red), (1, NodeColor::Black)]);
+    /// ```
+    pub fn iter_colored(&self) -> impl Iterator<Item = (T, NodeColor)> {
+        let mut container = vec![];
+        if let Some(root) = self.root.clone() {
+            RedBlackTreeNode::inorder_colored_traverse(root, &mut container);
+        }
+        container.into_iter()
     }
 
-    /// Delete a value from the tree
+    /// Return the number of parent-child edges whose two nodes have
+    /// different colors
     ///
     /// # Example
     ///
@@ -781,29 +1281,362 @@ impl<T: Ord + Copy + fmt::Debug> RedBlackTree<T> {
     /// use trees::rbtree::RedBlackTree;
     ///
     /// let mut rbt = RedBlackTree::new();
-    /// rbt.delete(1);
+    /// rbt.insert(2);
+    /// rbt.insert(1);
+    /// rbt.insert(3);
+    /// assert_eq!(rbt.color_change_edges(), 2);
     /// ```
-    pub fn delete(&mut self, val: T) {
+    pub fn color_change_edges(&self) -> usize {
         match self.root.clone() {
-            Some(root) => {
-                let r = RedBlackTreeNode::delete(root, val);
-                self.root = r;
+            None => 0,
+            Some(root) => RedBlackTreeNode::count_color_changes(root),
+        }
+    }
+
+    /// Return the number of red and black nodes in the tree, as
+    /// `(red, black)`. The root of a valid tree is black, so a non-empty
+    /// tree always has at least one black node.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// rbt.insert(2);
+    /// rbt.insert(1);
+    /// rbt.insert(3);
+    /// assert_eq!(rbt.color_counts(), (2, 1));
+    /// ```
+    pub fn color_counts(&self) -> (usize, usize) {
+        self.iter_colored().fold((0, 0), |(red, black), (_, color)| match color {
+            NodeColor::Red => (red + 1, black),
+            NodeColor::Black => (red, black + 1),
+        })
+    }
+
+    /// Color the root black if it is red (property 2), and return whether
+    /// its color was changed. The rest of the tree is left untouched.
+    ///
+    /// Recoloring the root never breaks the other properties, so this
+    /// repairs a tree whose root was colored by hand without rebuilding it.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// rbt.insert(1);
+    /// assert!(!rbt.normalize_root_color());
+    /// ```
+    pub fn normalize_root_color(&mut self) -> bool {
+        match self.root.as_ref() {
+            Some(root) if root.borrow().color == NodeColor::Red => {
+                root.borrow_mut().color = NodeColor::Black;
+                true
             }
-            None => (),
+            _ => false,
         }
     }
+
     #[allow(dead_code)]
     fn is_equal(&self, other: &RedBlackTree<T>) -> bool {
         RedBlackTreeNode::is_equal(self.root.clone(), other.root.clone())
     }
 }
 
+impl<T: Integer> RedBlackTree<T> {
+    /// Insert every integer from `lo` to `hi`, both included. Nothing is
+    /// inserted if `lo > hi`.
+    ///
+    /// The range is merged with the values already in the tree and the whole
+    /// tree is rebuilt like [from_sorted](#method.from_sorted), which takes
+    /// `O(n + k)` time for `n` values and `k` new ones.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// rbt.insert(0);
+    /// rbt.insert_range(3, 5);
+    /// assert_eq!(rbt.to_vec(), vec![0, 3, 4, 5]);
+    /// ```
+    pub fn insert_range(&mut self, lo: T, hi: T) {
+        let block = num::inclusive_range(lo, hi);
+        if block.is_empty() {
+            return;
+        }
+        if let Some(history) = self.history.as_mut() {
+            history.extend(block.iter().copied());
+        }
+        let values = base::merge_sorted(self.iter(), block, T::cmp);
+        self.clear();
+        self.root = RedBlackTreeNode::from_sorted_values(&values);
+    }
+}
+
+/// Content equality with a [BinarySearchTree](../bstree/struct.BinarySearchTree.html)
+impl<T: Ord + Clone + fmt::Debug> PartialEq<crate::bstree::BinarySearchTree<T>> for RedBlackTree<T> {
+    fn eq(&self, other: &crate::bstree::BinarySearchTree<T>) -> bool {
+        base::same_values(self, other)
+    }
+}
+
+/// Content equality with an [AVLTree](../avltree/struct.AVLTree.html)
+impl<T: Ord + Clone + fmt::Debug> PartialEq<crate::avltree::AVLTree<T>> for RedBlackTree<T> {
+    fn eq(&self, other: &crate::avltree::AVLTree<T>) -> bool {
+        base::same_values(self, other)
+    }
+}
+
+/// Trees are equal when they hold the same values, even if their shapes
+/// or colors differ, see
+/// [BinarySearchTree](../bstree/struct.BinarySearchTree.html).
+///
+/// # Example
+///
+/// ```
+/// use trees::prelude::*;
+///
+/// let a: RedBlackTree<i32> = vec![1, 2, 3].into_iter().collect();
+/// let b: RedBlackTree<i32> = vec![3, 2, 1].into_iter().collect();
+/// let avl: AVLTree<i32> = vec![2, 1, 3].into_iter().collect();
+/// assert!(a == b);
+/// assert!(a == avl);
+/// ```
+impl<T: Ord + Clone + fmt::Debug> PartialEq<RedBlackTree<T>> for RedBlackTree<T> {
+    fn eq(&self, other: &RedBlackTree<T>) -> bool {
+        base::same_values(self, other)
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> Eq for RedBlackTree<T> {}
+
+impl<T: Ord + Clone + fmt::Debug> Default for RedBlackTree<T> {
+    /// Create an empty tree, same as [new](#method.new)
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> Clone for RedBlackTree<T> {
+    /// Copy the tree into freshly allocated nodes with the same colors, so
+    /// changing the copy leaves the original alone
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// rbt.insert(1);
+    /// let mut copy = rbt.clone();
+    /// copy.insert(2);
+    /// assert_eq!(rbt.to_vec(), vec![1]);
+    /// ```
+    fn clone(&self) -> Self {
+        Self {
+            root: self.root.as_ref().map(RedBlackTreeNode::deep_clone),
+            history: self.history.clone(),
+        }
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> IntoIterator for RedBlackTree<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T, RedBlackTreeNode<T>>;
+
+    /// Consume the tree and iterate its values in ascending order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// rbt.insert(2);
+    /// rbt.insert(1);
+    /// let values: Vec<_> = rbt.into_iter().collect();
+    /// assert_eq!(values, vec![1, 2]);
+    /// ```
+    fn into_iter(mut self) -> Self::IntoIter {
+        IntoIter::new(self.root.take())
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> FromIterator<T> for RedBlackTree<T> {
+    /// Create a tree by inserting the values one by one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let rbt: RedBlackTree<i32> = vec![5, 3, 8].into_iter().collect();
+    /// assert_eq!(rbt.to_vec(), vec![3, 5, 8]);
+    /// ```
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut tree = Self::new();
+        tree.extend(iter);
+        tree
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> Extend<T> for RedBlackTree<T> {
+    /// Insert the values one by one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use trees::rbtree::RedBlackTree;
+    /// use trees::base::QueryableTree;
+    ///
+    /// let mut rbt = RedBlackTree::new();
+    /// rbt.insert(4);
+    /// rbt.extend(vec![1, 2, 3]);
+    /// assert_eq!(rbt.to_vec(), vec![1, 2, 3, 4]);
+    /// ```
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for val in iter {
+            self.insert(val);
+        }
+    }
+}
+
+impl<T: Ord + Clone + fmt::Debug> fmt::Display for RedBlackTree<T> {
+    /// Format the values in ascending order within braces, like a
+    /// [BinarySearchTree](../bstree/struct.BinarySearchTree.html)
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        base::fmt_values(self.iter(), f)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for NodeColor {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            NodeColor::Red => serializer.serialize_unit_variant("NodeColor", 0, "Red"),
+            NodeColor::Black => serializer.serialize_unit_variant("NodeColor", 1, "Black"),
+        }
+    }
+}
+
+/// Serialized as the sequence of `(value, color)` pairs in preorder, which
+/// is enough to restore the exact shape and coloring of the tree
+#[cfg(feature = "serde")]
+impl<T: Ord + Clone + fmt::Debug + serde::Serialize> serde::Serialize for RedBlackTree<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut container = vec![];
+        if let Some(root) = self.root.clone() {
+            RedBlackTreeNode::preorder_colored_traverse(root, &mut container);
+        }
+        serializer.collect_seq(container)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for NodeColor {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::{self, EnumAccess, VariantAccess, Visitor};
+
+        const VARIANTS: &[&str] = &["Red", "Black"];
+
+        /// Variant name of a color, which may also be given by its index
+        struct Tag(NodeColor);
+
+        impl<'de> serde::Deserialize<'de> for Tag {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct TagVisitor;
+
+                impl<'de> Visitor<'de> for TagVisitor {
+                    type Value = Tag;
+
+                    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        f.write_str("`Red` or `Black`")
+                    }
+
+                    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Tag, E> {
+                        match v {
+                            0 => Ok(Tag(NodeColor::Red)),
+                            1 => Ok(Tag(NodeColor::Black)),
+                            _ => Err(E::invalid_value(de::Unexpected::Unsigned(v), &self)),
+                        }
+                    }
+
+                    fn visit_str<E: de::Error>(self, v: &str) -> Result<Tag, E> {
+                        match v {
+                            "Red" => Ok(Tag(NodeColor::Red)),
+                            "Black" => Ok(Tag(NodeColor::Black)),
+                            _ => Err(E::unknown_variant(v, VARIANTS)),
+                        }
+                    }
+                }
+
+                deserializer.deserialize_identifier(TagVisitor)
+            }
+        }
+
+        struct ColorVisitor;
+
+        impl<'de> Visitor<'de> for ColorVisitor {
+            type Value = NodeColor;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a node color")
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<NodeColor, A::Error> {
+                let (tag, variant) = data.variant::<Tag>()?;
+                variant.unit_variant()?;
+                Ok(tag.0)
+            }
+        }
+
+        deserializer.deserialize_enum("NodeColor", VARIANTS, ColorVisitor)
+    }
+}
+
+/// Deserialized from the sequence of `(value, color)` pairs written by
+/// `Serialize`, restoring the exact shape and coloring. If the pairs do not
+/// describe a valid red-black tree, the values are inserted one by one
+/// instead, which rebalances the tree.
+#[cfg(feature = "serde")]
+impl<'de, T: Ord + Clone + fmt::Debug + serde::Deserialize<'de>> serde::Deserialize<'de> for RedBlackTree<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let pairs = Vec::<(T, NodeColor)>::deserialize(deserializer)?;
+        if let Some(root) = RedBlackTreeNode::from_preorder(&pairs) {
+            let tree = Self { root, history: None };
+            if tree.is_valid() {
+                return Ok(tree);
+            }
+        }
+        Ok(pairs.into_iter().map(|(v, _)| v).collect())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use rand::{rngs::StdRng, SeedableRng};
     use rand::seq::SliceRandom;
 
+    #[test]
+    fn from_sorted_values_rbt() {
+        for n in 0..130 {
+            let values: Vec<i32> = (0..n).collect();
+            let rbt = RedBlackTree { root: RedBlackTreeNode::from_sorted_values(&values), history: None };
+            assert!(rbt.is_valid(), "invalid tree for {} values", n);
+            assert_eq!(rbt.to_vec(), values);
+            assert_eq!(rbt.len(), n as usize);
+        }
+    }
+
     #[test]
     //""Test that the rotate_left and rotate_right functions work."""
     // Make a tree to test on
@@ -890,7 +1723,7 @@ mod test {
 
         {
             let root = tree.root.clone().unwrap();
-            tree.root = RedBlackTreeNode::rotate_left(root);
+            RedBlackTreeNode::rotate_left(root, &mut tree.root);
         }
         assert!(tree.is_equal(&left_rot))
     }
@@ -1012,6 +1845,295 @@ mod test {
         assert_eq!(container, vec![-16, 8, 20, 24, 22, 16, 0]);
     }
 
+    #[test]
+    fn tree_traversal_public() {
+        // Same tree as in tree_traversal(), through the QueryableTree methods
+        let mut tree = RedBlackTree::new();
+        assert_eq!(tree.preorder(), Vec::<i32>::new());
+        assert_eq!(tree.postorder(), Vec::<i32>::new());
+        tree.insert(0);
+        [-16, 16, 8, 24, 20, 22].iter().for_each(|v| {
+            tree.insert(*v);
+        });
+        assert_eq!(tree.to_vec(), vec![-16, 0, 8, 16, 20, 22, 24]);
+        assert_eq!(tree.preorder(), vec![0, -16, 16, 8, 22, 20, 24]);
+        assert_eq!(tree.postorder(), vec![-16, 8, 20, 24, 22, 16, 0]);
+    }
+
+    #[test]
+    fn color_change_edges() {
+        // Same tree as in the insert() test
+        //            0B
+        //          /    \
+        //       -8B      8R
+        //               /  \
+        //             4B    11B
+        //                  /   \
+        //                10R   12R
+        let mut tree = RedBlackTree::new();
+        assert_eq!(tree.color_change_edges(), 0);
+        tree.insert(0);
+        assert_eq!(tree.color_change_edges(), 0);
+        [8, -8, 4, 12, 10, 11].iter().for_each(|v| {
+            tree.insert(*v);
+        });
+        assert_eq!(tree.to_debug_nested(), "(0 (-8 ..)(8 (4 ..)(11 (10 ..)(12 ..))))");
+        assert_eq!(tree.color_change_edges(), 5);
+
+        // inserting level by level leaves the leaves red below black parents
+        let mut tree = RedBlackTree::new();
+        for v in [4, 2, 6, 1, 3, 5, 7].iter() {
+            tree.insert(*v);
+        }
+        let red: Vec<_> = tree.iter_colored()
+            .filter(|(_, c)| *c == NodeColor::Red)
+            .map(|(v, _)| v)
+            .collect();
+        assert_eq!(red, vec![1, 3, 5, 7]);
+        assert_eq!(tree.color_change_edges(), 4);
+    }
+
+    #[test]
+    fn normalize_root_color() {
+        let mut tree = RedBlackTree::new();
+        assert!(!tree.normalize_root_color());
+
+        // red root with two black children, otherwise valid
+        let root = RedBlackTreeNode::new(0, NodeColor::Red, None);
+        root.borrow_mut().left = Some(RedBlackTreeNode::new(
+            -10,
+            NodeColor::Black,
+            Some(root.clone()),
+        ));
+        root.borrow_mut().right = Some(RedBlackTreeNode::new(
+            10,
+            NodeColor::Black,
+            Some(root.clone()),
+        ));
+        root.borrow_mut().size = 3;
+        tree.root = Some(root.clone());
+        assert!(!RedBlackTreeNode::check_color_properties(root.clone()));
+        assert!(!tree.is_valid());
+
+        assert!(tree.normalize_root_color());
+        assert_eq!(root.borrow().color, NodeColor::Black);
+        assert!(RedBlackTreeNode::check_color_properties(root));
+        assert!(tree.is_valid());
+        assert!(!tree.normalize_root_color());
+
+        tree.insert(5);
+        assert!(tree.is_valid());
+    }
+
+    #[test]
+    fn is_valid_rb() {
+        use rand::Rng;
+
+        let mut tree = RedBlackTree::new();
+        assert!(tree.is_valid_rb());
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..2000 {
+            let v: i32 = rng.gen_range(0, 200);
+            if rng.gen_range(0, 3) == 0 {
+                tree.delete(v);
+            } else {
+                tree.insert(v);
+            }
+            assert!(tree.is_valid_rb());
+        }
+
+        // a red root
+        let root = tree.root.clone().unwrap();
+        root.borrow_mut().color = NodeColor::Red;
+        assert!(!tree.is_valid_rb());
+        root.borrow_mut().color = NodeColor::Black;
+        assert!(tree.is_valid_rb());
+
+        // flipping the color of a child of the root changes the number of
+        // black nodes on the paths through it only
+        let left = root.borrow().left.clone().unwrap();
+        let color = left.borrow().color;
+        left.borrow_mut().color = match color {
+            NodeColor::Red => NodeColor::Black,
+            NodeColor::Black => NodeColor::Red,
+        };
+        assert!(!tree.is_valid_rb());
+        left.borrow_mut().color = color;
+        assert!(tree.is_valid_rb());
+    }
+
+    #[test]
+    fn black_height() {
+        let mut tree = RedBlackTree::new();
+        assert_eq!(tree.black_height(), Some(0));
+        tree.insert(0);
+        assert_eq!(tree.black_height(), Some(1));
+        // Same tree as in the insert() test, every path crosses the root
+        // and one of the black nodes -8, 4 or 11
+        for &v in [8, -8, 4, 12, 10, 11].iter() {
+            tree.insert(v);
+        }
+        assert_eq!(tree.black_height(), Some(2));
+
+        // making a black leaf red shortens the paths through it only
+        let root = tree.root.clone().unwrap();
+        let left = root.borrow().left.clone().unwrap();
+        left.borrow_mut().color = NodeColor::Red;
+        assert_eq!(tree.black_height(), None);
+        left.borrow_mut().color = NodeColor::Black;
+        assert_eq!(tree.black_height(), Some(2));
+
+        // a red root breaks another property, which is not checked here,
+        // and leaves one black node less on every path
+        root.borrow_mut().color = NodeColor::Red;
+        assert_eq!(tree.black_height(), Some(1));
+        assert!(!tree.is_valid_rb());
+    }
+
+    #[test]
+    fn iter_colored() {
+        // Same tree as in the insert() test
+        let mut tree = RedBlackTree::new();
+        assert_eq!(tree.iter_colored().count(), 0);
+        tree.insert(0);
+        [8, -8, 4, 12, 10, 11].iter().for_each(|v| {
+            tree.insert(*v);
+        });
+        assert_eq!(tree.root.clone().unwrap().borrow().color, NodeColor::Black);
+
+        let colored: Vec<_> = tree.iter_colored().collect();
+        assert_eq!(colored, vec![
+            (-8, NodeColor::Black),
+            (0, NodeColor::Black),
+            (4, NodeColor::Black),
+            (8, NodeColor::Red),
+            (10, NodeColor::Red),
+            (11, NodeColor::Black),
+            (12, NodeColor::Red),
+        ]);
+    }
+
+    #[test]
+    fn color_counts() {
+        let mut tree = RedBlackTree::new();
+        assert_eq!(tree.color_counts(), (0, 0));
+        tree.insert(0);
+        assert_eq!(tree.color_counts(), (0, 1));
+        // Same tree as in the insert() test
+        for &v in [8, -8, 4, 12, 10, 11].iter() {
+            tree.insert(v);
+        }
+        assert_eq!(tree.color_counts(), (3, 4));
+
+        // sequential inserts leave only 7 and 9 red
+        let mut tree = RedBlackTree::new();
+        for v in 0..10 {
+            tree.insert(v);
+        }
+        assert!(tree.is_valid_rb());
+        assert_eq!(tree.color_counts(), (2, 8));
+    }
+
+    #[test]
+    fn remove_where() {
+        let mut tree = RedBlackTree::new();
+        assert_eq!(tree.remove_where(|_| true), 0);
+        for v in 0..100 {
+            tree.insert(v);
+        }
+        assert_eq!(tree.remove_where(|v| v % 2 == 1), 50);
+        assert_eq!(tree.to_vec(), (0..100).step_by(2).collect::<Vec<_>>());
+        assert!(RedBlackTreeNode::check_color_properties(tree.root.clone().unwrap()));
+        assert_eq!(tree.height(), 6);
+        assert_eq!(tree.remove_where(|v| v % 2 == 1), 0);
+        assert_eq!(tree.remove_where(|_| true), 50);
+        assert!(tree.is_empty());
+    }
+
+    #[test]
+    fn into_iter() {
+        use std::rc::Rc;
+
+        let mut tree = RedBlackTree::new();
+        for v in 0..100 {
+            tree.insert(v);
+        }
+        let root = Rc::downgrade(tree.root.as_ref().unwrap());
+        let root_value = root.upgrade().unwrap().borrow().data;
+        let min = {
+            let mut node = tree.root.clone().unwrap();
+            loop {
+                let left = node.borrow().left.clone();
+                match left {
+                    Some(l) => node = l,
+                    None => break,
+                }
+            }
+            Rc::downgrade(&node)
+        };
+
+        let mut iter = tree.into_iter();
+        assert_eq!(iter.next(), Some(0));
+        // the yielded node is freed even though its children point back to it
+        assert!(min.upgrade().is_none());
+        assert!(root.upgrade().is_some());
+        let values: Vec<_> = iter.by_ref().take_while(|v| *v <= root_value).collect();
+        assert_eq!(values, (1..=root_value).collect::<Vec<_>>());
+        assert!(root.upgrade().is_none());
+        drop(iter);
+    }
+
+    #[test]
+    fn nodes_freed_on_drop() {
+        use rand::Rng;
+        use std::rc::Rc;
+
+        let mut tree = RedBlackTree::new();
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        for _ in 0..1000 {
+            let v: i32 = rng.gen_range(0, 200);
+            if rng.gen_range(0, 3) == 0 {
+                tree.delete(v);
+            } else {
+                tree.insert(v);
+            }
+        }
+
+        // only the link from the parent, or the tree for the root, owns a
+        // node, however many rotations moved it around
+        let mut nodes = vec![];
+        let mut stack: Vec<_> = tree.root.clone().into_iter().collect();
+        while let Some(node) = stack.pop() {
+            stack.extend(node.borrow().left.clone());
+            stack.extend(node.borrow().right.clone());
+            assert_eq!(Rc::strong_count(&node), 2);
+            nodes.push(Rc::downgrade(&node));
+        }
+        assert_eq!(nodes.len(), tree.len());
+
+        drop(tree);
+        assert!(nodes.iter().all(|node| node.upgrade().is_none()));
+
+        // dropping the nodes without going through clear() frees them too
+        let mut tree: RedBlackTree<i32> = (0..100).collect();
+        let root = tree.root.take().unwrap();
+        let min = {
+            let mut node = root.clone();
+            loop {
+                let left = node.borrow().left.clone();
+                match left {
+                    Some(l) => node = l,
+                    None => break,
+                }
+            }
+            Rc::downgrade(&node)
+        };
+        drop(tree);
+        assert!(min.upgrade().is_some());
+        drop(root);
+        assert!(min.upgrade().is_none());
+    }
+
     #[test]
     fn max() {
         // Test the get_max functions in the tree.
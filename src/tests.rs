@@ -0,0 +1,1961 @@
+use crate::prelude::*;
+
+#[test]
+fn insertion_history() {
+    let order = [5, 3, 8, 3, 1, 5, 9];
+
+    let mut bst = BinarySearchTree::with_history();
+    let mut avl = AVLTree::with_history();
+    let mut rbt = RedBlackTree::with_history();
+    for v in order.iter() {
+        bst.insert(*v);
+        avl.insert(*v);
+        rbt.insert(*v);
+    }
+    // duplicates are recorded as attempts even though they are ignored
+    assert_eq!(bst.insertion_history(), Some(&order[..]));
+    assert_eq!(avl.insertion_history(), Some(&order[..]));
+    assert_eq!(rbt.insertion_history(), Some(&order[..]));
+    assert_eq!(bst.len(), 5);
+    assert_eq!(avl.len(), 5);
+    assert_eq!(rbt.len(), 5);
+
+    // deletes are not part of the history
+    bst.delete(5);
+    avl.delete(5);
+    rbt.delete(5);
+    assert_eq!(bst.insertion_history(), Some(&order[..]));
+    assert_eq!(avl.insertion_history(), Some(&order[..]));
+    assert_eq!(rbt.insertion_history(), Some(&order[..]));
+
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    bst.insert(1);
+    avl.insert(1);
+    rbt.insert(1);
+    assert_eq!(bst.insertion_history(), None);
+    assert_eq!(avl.insertion_history(), None);
+    assert_eq!(rbt.insertion_history(), None);
+}
+
+mod boundary {
+    use crate::prelude::*;
+    use crate::base::QueryableTreeNode;
+
+    fn check<N, Q>(tree: &Q)
+    where
+        N: QueryableTreeNode<i32>,
+        Q: QueryableTree<i32, N>,
+    {
+        match (tree.min(), tree.max()) {
+            (Some(min), Some(max)) => {
+                assert_eq!(tree.successor(max), None);
+                assert_eq!(tree.predecessor(min), None);
+                assert_eq!(tree.floor(min - 1), None);
+                assert_eq!(tree.ceiling(max + 1), None);
+                assert_eq!(tree.floor(min), Some(min));
+                assert_eq!(tree.ceiling(max), Some(max));
+                assert_eq!(tree.successor(min - 1), Some(min));
+                assert_eq!(tree.predecessor(max + 1), Some(max));
+                assert_eq!(tree.floor(i32::MAX), Some(max));
+                assert_eq!(tree.ceiling(i32::MIN), Some(min));
+            }
+            _ => {
+                for v in [i32::MIN, -1, 0, 1, i32::MAX].iter() {
+                    assert_eq!(tree.successor(*v), None);
+                    assert_eq!(tree.predecessor(*v), None);
+                    assert_eq!(tree.floor(*v), None);
+                    assert_eq!(tree.ceiling(*v), None);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn empty() {
+        check(&BinarySearchTree::new());
+        check(&AVLTree::new());
+        check(&RedBlackTree::new());
+    }
+
+    #[test]
+    fn single_element() {
+        let mut bst = BinarySearchTree::new();
+        let mut avl = AVLTree::new();
+        let mut rbt = RedBlackTree::new();
+        bst.insert(7);
+        avl.insert(7);
+        rbt.insert(7);
+        check(&bst);
+        check(&avl);
+        check(&rbt);
+        assert_eq!(bst.successor(7), None);
+        assert_eq!(avl.predecessor(7), None);
+        assert_eq!(rbt.floor(7), Some(7));
+        assert_eq!(rbt.ceiling(7), Some(7));
+    }
+
+    #[test]
+    fn multiple_elements() {
+        let mut bst = BinarySearchTree::new();
+        let mut avl = AVLTree::new();
+        let mut rbt = RedBlackTree::new();
+        for v in [50, 20, 80, 10, 30, 70, 90, 60].iter() {
+            bst.insert(*v);
+            avl.insert(*v);
+            rbt.insert(*v);
+        }
+        check(&bst);
+        check(&avl);
+        check(&rbt);
+    }
+
+    #[test]
+    fn present_and_absent_keys() {
+        let mut bst = BinarySearchTree::new();
+        let mut avl = AVLTree::new();
+        let mut rbt = RedBlackTree::new();
+        for v in [50, 20, 80, 10, 30, 70, 90, 60].iter() {
+            bst.insert(*v);
+            avl.insert(*v);
+            rbt.insert(*v);
+        }
+        // (value, predecessor, successor)
+        let cases = [
+            (50, Some(30), Some(60)),
+            (20, Some(10), Some(30)),
+            (60, Some(50), Some(70)),
+            (30, Some(20), Some(50)),
+            (55, Some(50), Some(60)),
+            (31, Some(30), Some(50)),
+            (69, Some(60), Some(70)),
+            (10, None, Some(20)),
+            (90, Some(80), None),
+            (9, None, Some(10)),
+            (91, Some(90), None),
+        ];
+        for (value, pred, succ) in cases.iter() {
+            assert_eq!(bst.predecessor(*value), *pred);
+            assert_eq!(avl.predecessor(*value), *pred);
+            assert_eq!(rbt.predecessor(*value), *pred);
+            assert_eq!(bst.successor(*value), *succ);
+            assert_eq!(avl.successor(*value), *succ);
+            assert_eq!(rbt.successor(*value), *succ);
+        }
+    }
+
+    #[test]
+    fn against_btreeset() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        use std::collections::BTreeSet;
+
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let reference: BTreeSet<i32> = (0..200).map(|_| rng.gen_range(0, 1000)).collect();
+        let bst: BinarySearchTree<i32> = reference.iter().copied().collect();
+        let avl: AVLTree<i32> = reference.iter().copied().collect();
+        let rbt: RedBlackTree<i32> = reference.iter().copied().collect();
+        for value in -1..1001 {
+            let succ = reference.range(value + 1..).next().copied();
+            let pred = reference.range(..value).next_back().copied();
+            assert_eq!(bst.successor(value), succ);
+            assert_eq!(avl.successor(value), succ);
+            assert_eq!(rbt.successor(value), succ);
+            assert_eq!(bst.predecessor(value), pred);
+            assert_eq!(avl.predecessor(value), pred);
+            assert_eq!(rbt.predecessor(value), pred);
+        }
+    }
+}
+
+#[test]
+fn content_hash() {
+    let mut ascending = BinarySearchTree::new();
+    let mut shuffled = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for v in 0..20 {
+        ascending.insert(v);
+        avl.insert(v);
+    }
+    for v in [10, 3, 15, 0, 19, 7, 12, 1, 18, 5, 14, 2, 9, 17, 4, 11, 6, 16, 8, 13].iter() {
+        shuffled.insert(*v);
+        rbt.insert(*v);
+    }
+    assert_ne!(ascending.height(), shuffled.height());
+    let hash = ascending.content_hash();
+    assert_eq!(shuffled.content_hash(), hash);
+    assert_eq!(avl.content_hash(), hash);
+    assert_eq!(rbt.content_hash(), hash);
+
+    shuffled.delete(7);
+    shuffled.insert(70);
+    assert_ne!(shuffled.content_hash(), hash);
+    shuffled.delete(70);
+    assert_ne!(shuffled.content_hash(), hash);
+    shuffled.insert(7);
+    assert_eq!(shuffled.content_hash(), hash);
+
+    assert_ne!(BinarySearchTree::<i32>::new().content_hash(), hash);
+}
+
+#[test]
+fn median_fast() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    assert_eq!(bst.median_fast(), None);
+    assert_eq!(avl.median_fast(), None);
+    assert_eq!(rbt.median_fast(), None);
+    for _ in 0..2000 {
+        let v: i32 = rng.gen_range(0, 500);
+        if rng.gen_bool(0.7) {
+            bst.insert(v);
+            avl.insert(v);
+            rbt.insert(v);
+        } else {
+            bst.delete(v);
+            avl.delete(v);
+            rbt.delete(v);
+        }
+        let median = bst.median();
+        assert_eq!(bst.median_fast(), median);
+        assert_eq!(avl.median_fast(), median);
+        assert_eq!(rbt.median_fast(), median);
+    }
+    let values = bst.to_vec();
+    assert_eq!(bst.len(), values.len());
+    assert_eq!(avl.len(), values.len());
+    assert_eq!(rbt.len(), values.len());
+    for (i, v) in values.iter().enumerate() {
+        assert_eq!(bst.value_at_index(i), Some(*v));
+        assert_eq!(avl.value_at_index(i), Some(*v));
+        assert_eq!(rbt.value_at_index(i), Some(*v));
+    }
+    assert_eq!(rbt.value_at_index(values.len()), None);
+}
+
+mod apply_ops {
+    use crate::prelude::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn scripted() {
+        use TreeOp::*;
+        let ops = [
+            Insert(50), Insert(20), Insert(80), Insert(10), Insert(30),
+            Insert(20), Delete(50), Delete(99), Insert(25), Delete(10),
+            Delete(20), Insert(90), Insert(85), Delete(80), Delete(30),
+        ];
+        let mut bst = BinarySearchTree::new();
+        let mut avl = AVLTree::new();
+        let mut rbt = RedBlackTree::new();
+        assert!(bst.apply_ops(&ops));
+        assert!(avl.apply_ops(&ops));
+        assert!(rbt.apply_ops(&ops));
+        let expected = vec![25, 85, 90];
+        assert_eq!(bst.to_vec(), expected);
+        assert_eq!(avl.to_vec(), expected);
+        assert_eq!(rbt.to_vec(), expected);
+        assert!(bst.apply_ops(&[]));
+    }
+
+    #[test]
+    fn random() {
+        let mut rng = StdRng::from_seed([0u8; 32]);
+        let mut reference = BTreeSet::new();
+        let mut bst = BinarySearchTree::new();
+        let mut avl = AVLTree::new();
+        let mut rbt = RedBlackTree::new();
+        for _ in 0..50 {
+            let ops: Vec<TreeOp<i32>> = (0..40).map(|_| {
+                let v = rng.gen_range(0, 200);
+                if rng.gen_bool(0.6) {
+                    TreeOp::Insert(v)
+                } else {
+                    TreeOp::Delete(v)
+                }
+            }).collect();
+            for op in ops.iter() {
+                match *op {
+                    TreeOp::Insert(v) => reference.insert(v),
+                    TreeOp::Delete(v) => reference.remove(&v),
+                };
+            }
+            assert!(bst.apply_ops(&ops));
+            assert!(avl.apply_ops(&ops));
+            assert!(rbt.apply_ops(&ops));
+            let expected: Vec<i32> = reference.iter().copied().collect();
+            assert_eq!(bst.to_vec(), expected);
+            assert_eq!(avl.to_vec(), expected);
+            assert_eq!(rbt.to_vec(), expected);
+        }
+    }
+}
+
+#[test]
+fn iter() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    assert_eq!(bst.iter().next(), None);
+    assert_eq!(avl.iter().next(), None);
+    assert_eq!(rbt.iter().next(), None);
+    for v in [50, 20, 80, 10, 30, 70, 90, 60, 20].iter() {
+        bst.insert(*v);
+        avl.insert(*v);
+        rbt.insert(*v);
+    }
+    let expected = vec![10, 20, 30, 50, 60, 70, 80, 90];
+    assert_eq!(bst.iter().collect::<Vec<_>>(), expected);
+    assert_eq!(avl.iter().collect::<Vec<_>>(), expected);
+    assert_eq!(rbt.iter().collect::<Vec<_>>(), expected);
+
+    let mut sum = 0;
+    for v in rbt.iter() {
+        sum += v;
+    }
+    assert_eq!(sum, expected.iter().sum::<i32>());
+
+    // lazy: taking a prefix does not visit the rest
+    assert_eq!(avl.iter().take(2).collect::<Vec<_>>(), vec![10, 20]);
+
+    // deep right-skewed tree
+    let mut skewed = BinarySearchTree::new();
+    for v in 0..5000 {
+        skewed.insert(v);
+    }
+    assert!(skewed.iter().eq(0..5000));
+}
+
+#[test]
+fn height_bfs() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    for size in [0, 1, 2, 10, 100, 1000].iter() {
+        let mut bst = BinarySearchTree::new();
+        let mut avl = AVLTree::new();
+        let mut rbt = RedBlackTree::new();
+        for _ in 0..*size {
+            let v: i32 = rng.gen_range(0, 10000);
+            bst.insert(v);
+            avl.insert(v);
+            rbt.insert(v);
+        }
+        assert_eq!(bst.height_bfs(), bst.height());
+        assert_eq!(avl.height_bfs(), avl.height());
+        assert_eq!(rbt.height_bfs(), rbt.height());
+    }
+
+    let mut skewed = BinarySearchTree::new();
+    for v in 0..5000 {
+        skewed.insert(v);
+    }
+    assert_eq!(skewed.height_bfs(), 5000);
+}
+
+#[test]
+fn into_iter() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for v in [50, 20, 80, 10, 30, 70, 90, 60, 20].iter() {
+        bst.insert(*v);
+        avl.insert(*v);
+        rbt.insert(*v);
+    }
+    let expected = vec![10, 20, 30, 50, 60, 70, 80, 90];
+    assert_eq!(bst.into_iter().collect::<Vec<_>>(), expected);
+    assert_eq!(avl.into_iter().collect::<Vec<_>>(), expected);
+    let mut values = vec![];
+    for v in rbt {
+        values.push(v);
+    }
+    assert_eq!(values, expected);
+
+    assert_eq!(BinarySearchTree::<i32>::new().into_iter().next(), None);
+    assert_eq!(AVLTree::<i32>::new().into_iter().next(), None);
+    assert_eq!(RedBlackTree::<i32>::new().into_iter().next(), None);
+}
+
+#[test]
+fn from_iter() {
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand::seq::SliceRandom;
+
+    let mut values: Vec<i32> = (0..100).collect();
+    values.shuffle(&mut StdRng::from_seed([0u8; 32]));
+
+    let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let avl = values.iter().copied().collect::<AVLTree<_>>();
+    let rbt: RedBlackTree<i32> = values.into_iter().collect();
+    assert!(bst.iter().eq(0..100));
+    assert!(avl.iter().eq(0..100));
+    assert!(rbt.iter().eq(0..100));
+
+    let empty: RedBlackTree<i32> = std::iter::empty().collect();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn to_debug_nested() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    assert_eq!(bst.to_debug_nested(), ".");
+    for v in [1, 2, 3].iter() {
+        bst.insert(*v);
+        avl.insert(*v);
+        rbt.insert(*v);
+    }
+    assert_eq!(bst.to_debug_nested(), "(1 .(2 .(3 ..)))");
+    assert_eq!(avl.to_debug_nested(), "(2 (1 ..)(3 ..))");
+    assert_eq!(rbt.to_debug_nested(), "(2 (1 ..)(3 ..))");
+    rbt.insert(0);
+    assert_eq!(rbt.to_debug_nested(), "(2 (1 (0 ..).)(3 ..))");
+}
+
+#[test]
+fn preorder_postorder() {
+    //          5
+    //        /   \
+    //       3     8
+    //      / \     \
+    //     1   4     9
+    let mut bst = BinarySearchTree::new();
+    for v in [5, 3, 8, 1, 4, 9].iter() {
+        bst.insert(*v);
+    }
+    assert_eq!(bst.preorder(), vec![5, 3, 1, 4, 8, 9]);
+    assert_eq!(bst.postorder(), vec![1, 4, 3, 9, 8, 5]);
+
+    // inserting the preorder sequence rebuilds the same shape
+    let rebuilt: BinarySearchTree<i32> = bst.preorder().into_iter().collect();
+    assert_eq!(rebuilt.to_debug_nested(), bst.to_debug_nested());
+
+    let avl: AVLTree<i32> = (1..=7).collect();
+    assert_eq!(avl.preorder(), vec![4, 2, 1, 3, 6, 5, 7]);
+    assert_eq!(avl.postorder(), vec![1, 3, 2, 5, 7, 6, 4]);
+}
+
+#[test]
+fn level_order() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    assert_eq!(bst.level_order(), Vec::<Vec<i32>>::new());
+    assert_eq!(avl.level_order(), Vec::<Vec<i32>>::new());
+    assert_eq!(rbt.level_order(), Vec::<Vec<i32>>::new());
+    for v in 1..=10 {
+        bst.insert(v);
+        avl.insert(v);
+        rbt.insert(v);
+    }
+    assert_eq!(bst.level_order().len(), 10);
+    assert_eq!(avl.level_order(), vec![
+        vec![4],
+        vec![2, 8],
+        vec![1, 3, 6, 9],
+        vec![5, 7, 10],
+    ]);
+    assert_eq!(rbt.level_order(), vec![
+        vec![4],
+        vec![2, 6],
+        vec![1, 3, 5, 8],
+        vec![7, 9],
+        vec![10],
+    ]);
+    // the same values in different shapes
+    assert_eq!(avl.to_vec(), rbt.to_vec());
+    assert_ne!(avl.level_order(), rbt.level_order());
+}
+
+#[test]
+fn prefix_sum_up_to() {
+    let bst: BinarySearchTree<i64> = [5, 2, 8, 1, 9, 3, 7, 10, 4, 6].iter().copied().collect();
+    let avl: AVLTree<i64> = (1..=10).collect();
+    let rbt: RedBlackTree<i64> = (1..=10).rev().collect();
+    for (value, expected) in [(5, 15), (0, 0), (1, 1), (10, 55), (100, 55), (-3, 0)].iter() {
+        assert_eq!(bst.prefix_sum_up_to(*value), *expected);
+        assert_eq!(avl.prefix_sum_up_to(*value), *expected);
+        assert_eq!(rbt.prefix_sum_up_to(*value), *expected);
+    }
+    assert_eq!(BinarySearchTree::<u8>::new().prefix_sum_up_to(10), 0);
+
+    let sparse: AVLTree<i32> = (0..20).map(|v| v * 5).collect();
+    assert_eq!(sparse.prefix_sum_up_to(12), 15);
+    assert_eq!(sparse.prefix_sum_up_to(-12), 0);
+}
+
+#[test]
+fn iter_rev() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut rbt = RedBlackTree::new();
+    assert_eq!(rbt.iter().next_back(), None);
+    for _ in 0..500 {
+        rbt.insert(rng.gen_range(0, 1000));
+    }
+    let mut descending = rbt.to_vec();
+    descending.reverse();
+    assert_eq!(rbt.iter().rev().collect::<Vec<_>>(), descending);
+    assert_eq!(rbt.iter().rev().take(3).collect::<Vec<_>>(), descending[..3].to_vec());
+
+    // both ends meet in the middle without repeating values
+    let avl: AVLTree<i32> = (0..9).collect();
+    let mut iter = avl.iter();
+    assert_eq!(iter.len(), 9);
+    assert_eq!(iter.next(), Some(0));
+    assert_eq!(iter.next_back(), Some(8));
+    assert_eq!(iter.next_back(), Some(7));
+    assert_eq!(iter.len(), 6);
+    assert_eq!(iter.collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6]);
+
+    let bst: BinarySearchTree<i32> = (0..5).collect();
+    let mut iter = bst.iter();
+    let mut values = vec![];
+    while let Some(v) = iter.next() {
+        values.push(v);
+        values.extend(iter.next_back());
+    }
+    assert_eq!(values, vec![0, 4, 1, 3, 2]);
+}
+
+#[test]
+fn range_sum() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut reference = BTreeSet::new();
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for _ in 0..1000 {
+        let v: i64 = rng.gen_range(-200, 200);
+        if rng.gen_bool(0.6) {
+            reference.insert(v);
+            bst.insert(v);
+            avl.insert(v);
+            rbt.insert(v);
+        } else {
+            reference.remove(&v);
+            bst.delete(v);
+            avl.delete(v);
+            rbt.delete(v);
+        }
+        let lo = rng.gen_range(-250, 250);
+        let hi = rng.gen_range(-250, 250);
+        let expected: i64 = reference.iter().filter(|v| lo <= **v && **v <= hi).sum();
+        assert_eq!(bst.range_sum(lo, hi), expected);
+        assert_eq!(avl.range_sum(lo, hi), expected);
+        assert_eq!(rbt.range_sum(lo, hi), expected);
+        let expected: i64 = reference.iter().filter(|v| **v <= hi).sum();
+        assert_eq!(bst.prefix_sum_up_to(hi), expected);
+        assert_eq!(avl.prefix_sum_up_to(hi), expected);
+        assert_eq!(rbt.prefix_sum_up_to(hi), expected);
+    }
+
+    let mut bst: BinarySearchTree<i64> = [10, 4, 2, 6, 16, 12, 18].iter().copied().collect();
+    assert_eq!(bst.range_sum(5, 12), 28);
+    bst.map_root(|v| v + 1);
+    assert_eq!(bst.range_sum(5, 12), 29);
+    bst.remove_where(|v| *v > 11);
+    assert_eq!(bst.range_sum(5, 12), 17);
+}
+
+#[test]
+fn morris_inorder() {
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand::seq::SliceRandom;
+
+    let mut values: Vec<i32> = (0..500).collect();
+    values.shuffle(&mut StdRng::from_seed([0u8; 32]));
+    let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let avl: AVLTree<i32> = values.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    let shapes = [bst.to_debug_nested(), avl.to_debug_nested(), rbt.to_debug_nested()];
+    assert_eq!(bst.morris_inorder(), bst.to_vec());
+    assert_eq!(avl.morris_inorder(), avl.to_vec());
+    assert_eq!(rbt.morris_inorder(), rbt.to_vec());
+    // the threads are removed again
+    assert_eq!(shapes, [bst.to_debug_nested(), avl.to_debug_nested(), rbt.to_debug_nested()]);
+    assert!(rbt.is_valid());
+
+    assert_eq!(BinarySearchTree::<i32>::new().morris_inorder(), Vec::<i32>::new());
+
+    // left-skewed: every node is threaded once
+    let skewed: BinarySearchTree<i32> = (0..5000).rev().collect();
+    let shape = skewed.preorder();
+    assert!(skewed.morris_inorder().into_iter().eq(0..5000));
+    assert_eq!(skewed.preorder(), shape);
+}
+
+#[test]
+fn percentile() {
+    let bst: BinarySearchTree<i32> = (0..100).collect();
+    let avl: AVLTree<i32> = (0..100).collect();
+    let rbt: RedBlackTree<i32> = (0..100).collect();
+    for (p, expected) in [(0.0, 0), (0.5, 50), (0.25, 25), (0.99, 98), (1.0, 99)].iter() {
+        assert_eq!(bst.percentile(*p), Some(*expected));
+        assert_eq!(avl.percentile(*p), Some(*expected));
+        assert_eq!(rbt.percentile(*p), Some(*expected));
+    }
+    assert_eq!(avl.percentile(0.0), avl.min());
+    assert_eq!(avl.percentile(1.0), avl.max());
+    assert_eq!(rbt.percentile(-0.1), None);
+    assert_eq!(rbt.percentile(1.1), None);
+    assert_eq!(rbt.percentile(f64::NAN), None);
+    assert_eq!(RedBlackTree::<i32>::new().percentile(0.5), None);
+}
+
+#[test]
+fn order_statistics() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    for size in [0, 1, 10, 300].iter() {
+        let values: Vec<i32> = (0..*size).map(|_| rng.gen_range(0, 1000)).collect();
+        let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+        let avl: AVLTree<i32> = values.iter().copied().collect();
+        let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+        let mut sorted = values.clone();
+        sorted.sort();
+        sorted.dedup();
+
+        assert_eq!(bst.kth_smallest(0), None);
+        for k in 1..=sorted.len() + 1 {
+            let expected = sorted.get(k - 1).copied();
+            assert_eq!(bst.kth_smallest(k), expected);
+            assert_eq!(avl.kth_smallest(k), expected);
+            assert_eq!(rbt.kth_smallest(k), expected);
+        }
+        for value in -1..1001 {
+            let expected = sorted.iter().filter(|v| **v < value).count();
+            assert_eq!(bst.rank(value), expected);
+            assert_eq!(avl.rank(value), expected);
+            assert_eq!(rbt.rank(value), expected);
+        }
+    }
+}
+
+#[test]
+fn clusters() {
+    let values = [20, 1, 11, 3, 10, 2];
+    let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let avl: AVLTree<i32> = values.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    let expected = vec![vec![1, 2, 3], vec![10, 11], vec![20]];
+    assert_eq!(bst.clusters(2), expected);
+    assert_eq!(avl.clusters(2), expected);
+    assert_eq!(rbt.clusters(2), expected);
+
+    assert_eq!(bst.clusters(0), vec![vec![1], vec![2], vec![3], vec![10], vec![11], vec![20]]);
+    assert_eq!(bst.clusters(9), vec![vec![1, 2, 3, 10, 11, 20]]);
+    assert_eq!(BinarySearchTree::<i32>::new().clusters(2), Vec::<Vec<i32>>::new());
+
+    // the gap between the extremes does not fit in the type
+    let wide: AVLTree<i8> = [-128, 127, 126].iter().copied().collect();
+    assert_eq!(wide.clusters(127), vec![vec![-128], vec![126, 127]]);
+    let unsigned: AVLTree<u8> = [0, 255].iter().copied().collect();
+    assert_eq!(unsigned.clusters(255), vec![vec![0, 255]]);
+}
+
+#[test]
+fn range() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let values: Vec<i32> = (0..300).map(|_| rng.gen_range(0, 1000)).collect();
+    let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let avl: AVLTree<i32> = values.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    for _ in 0..200 {
+        let lo = rng.gen_range(-10, 1010);
+        let hi = rng.gen_range(-10, 1010);
+        let expected: Vec<i32> = bst.iter().filter(|v| lo <= *v && *v <= hi).collect();
+        assert_eq!(bst.range(lo, hi).collect::<Vec<_>>(), expected);
+        assert_eq!(avl.range(lo, hi).collect::<Vec<_>>(), expected);
+        assert_eq!(rbt.range(lo, hi).collect::<Vec<_>>(), expected);
+    }
+
+    let rbt: RedBlackTree<i32> = (0..10).map(|v| v * 10).collect();
+    assert_eq!(rbt.range(20, 40).collect::<Vec<_>>(), vec![20, 30, 40]);
+    assert_eq!(rbt.range(15, 45).collect::<Vec<_>>(), vec![20, 30, 40]);
+    assert_eq!(rbt.range(30, 30).collect::<Vec<_>>(), vec![30]);
+    assert_eq!(rbt.range(31, 39).next(), None);
+    assert_eq!(rbt.range(40, 20).next(), None);
+    assert_eq!(rbt.range(i32::MIN, i32::MAX).count(), 10);
+    assert_eq!(RedBlackTree::<i32>::new().range(0, 10).next(), None);
+}
+
+#[test]
+fn count_in_range() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let values: Vec<i32> = (0..300).map(|_| rng.gen_range(0, 1000)).collect();
+    let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let avl: AVLTree<i32> = values.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    for _ in 0..500 {
+        let lo = rng.gen_range(-10, 1010);
+        let hi = rng.gen_range(-10, 1010);
+        let expected = bst.iter().filter(|v| lo <= *v && *v <= hi).count();
+        assert_eq!(bst.count_in_range(lo, hi), expected);
+        assert_eq!(avl.count_in_range(lo, hi), expected);
+        assert_eq!(rbt.count_in_range(lo, hi), expected);
+    }
+    let v = bst.min().unwrap();
+    assert_eq!(avl.count_in_range(v, v), 1);
+    assert_eq!(avl.count_in_range(i32::MIN, i32::MAX), avl.len());
+    assert_eq!(avl.count_in_range(10, 9), 0);
+    assert_eq!(AVLTree::<i32>::new().count_in_range(0, 10), 0);
+}
+
+#[test]
+fn pop_min() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let values: Vec<i32> = (0..300).map(|_| rng.gen_range(0, 1000)).collect();
+    let mut bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let mut avl: AVLTree<i32> = values.iter().copied().collect();
+    let mut rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    let expected = bst.to_vec();
+    for v in expected.iter() {
+        assert_eq!(bst.pop_min(), Some(*v));
+        assert_eq!(avl.pop_min(), Some(*v));
+        assert_eq!(rbt.pop_min(), Some(*v));
+        assert!(avl.is_valid());
+        assert!(rbt.is_valid());
+    }
+    assert!(bst.is_empty());
+    assert!(avl.is_empty());
+    assert!(rbt.is_empty());
+    assert_eq!(bst.pop_min(), None);
+    assert_eq!(avl.pop_min(), None);
+    assert_eq!(rbt.pop_min(), None);
+}
+
+#[test]
+fn pop_max() {
+    use rand::{rngs::StdRng, SeedableRng};
+    use rand::seq::SliceRandom;
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut values: Vec<i32> = (0..1000).collect();
+    values.shuffle(&mut rng);
+    let mut bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let mut avl: AVLTree<i32> = values.iter().copied().collect();
+    let mut rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    for v in (0..1000).rev() {
+        assert_eq!(bst.pop_max(), Some(v));
+        assert_eq!(avl.pop_max(), Some(v));
+        assert_eq!(rbt.pop_max(), Some(v));
+        assert!(avl.is_valid());
+        assert!(rbt.is_valid());
+    }
+    assert_eq!(bst.pop_max(), None);
+    assert_eq!(avl.pop_max(), None);
+    assert_eq!(rbt.pop_max(), None);
+}
+
+/// Ordered by `key` only, so equal values can still carry different tags
+#[derive(Debug, Clone, Copy)]
+struct Keyed {
+    key: i32,
+    tag: char,
+}
+
+impl PartialEq for Keyed {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for Keyed {}
+
+impl PartialOrd for Keyed {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Keyed {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key.cmp(&other.key)
+    }
+}
+
+#[test]
+fn get() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for v in [5, 2, 8, 1, 9, 3].iter() {
+        bst.insert(*v);
+        avl.insert(*v);
+        rbt.insert(*v);
+    }
+    for v in 0..11 {
+        let expected = if bst.contains(v) { Some(v) } else { None };
+        assert_eq!(bst.get(v), expected);
+        assert_eq!(avl.get(v), expected);
+        assert_eq!(rbt.get(v), expected);
+    }
+    assert_eq!(BinarySearchTree::<i32>::new().get(1), None);
+
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for (key, tag) in [(2, 'b'), (1, 'a'), (3, 'c')].iter() {
+        bst.insert(Keyed { key: *key, tag: *tag });
+        avl.insert(Keyed { key: *key, tag: *tag });
+        rbt.insert(Keyed { key: *key, tag: *tag });
+    }
+    let probe = Keyed { key: 3, tag: '?' };
+    assert_eq!(bst.get(probe).map(|k| k.tag), Some('c'));
+    assert_eq!(avl.get(probe).map(|k| k.tag), Some('c'));
+    assert_eq!(rbt.get(probe).map(|k| k.tag), Some('c'));
+    assert_eq!(bst.get(Keyed { key: 4, tag: 'c' }), None);
+}
+
+#[test]
+fn owned_values() {
+    let words = ["pear", "apple", "fig", "kiwi", "banana", "cherry", "date"];
+    let mut bst: BinarySearchTree<String> = BinarySearchTree::new();
+    let mut avl: AVLTree<String> = AVLTree::new();
+    let mut rbt: RedBlackTree<String> = RedBlackTree::new();
+    for w in words.iter() {
+        bst.insert(w.to_string());
+        avl.insert(w.to_string());
+        rbt.insert(w.to_string());
+    }
+    let mut sorted: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+    sorted.sort();
+    assert_eq!(bst.to_vec(), sorted);
+    assert_eq!(avl.to_vec(), sorted);
+    assert_eq!(rbt.to_vec(), sorted);
+    assert!(avl.is_valid());
+    assert!(rbt.is_valid());
+    assert_eq!(bst.min(), Some("apple".to_string()));
+    assert_eq!(rbt.max(), Some("pear".to_string()));
+    assert!(avl.contains("kiwi".to_string()));
+
+    bst.delete("fig".to_string());
+    avl.delete("fig".to_string());
+    rbt.delete("fig".to_string());
+    sorted.retain(|w| w != "fig");
+    assert_eq!(bst.iter().collect::<Vec<_>>(), sorted);
+    assert_eq!(avl.into_iter().collect::<Vec<_>>(), sorted);
+    assert_eq!(rbt.pop_min(), Some("apple".to_string()));
+    assert!(rbt.is_valid());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialize() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for v in [2, 1, 3, 4].iter() {
+        bst.insert(*v);
+        avl.insert(*v);
+        rbt.insert(*v);
+    }
+    assert_eq!(serde_json::to_string(&bst).unwrap(), "[1,2,3,4]");
+    assert_eq!(serde_json::to_string(&avl).unwrap(), "[1,2,3,4]");
+    assert_eq!(
+        serde_json::to_string(&rbt).unwrap(),
+        r#"[[2,"Black"],[1,"Black"],[3,"Black"],[4,"Red"]]"#
+    );
+    assert_eq!(serde_json::to_string(&RedBlackTree::<i32>::new()).unwrap(), "[]");
+}
+
+#[test]
+fn extremes() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    assert_eq!(bst.extremes(), None);
+    assert_eq!(avl.extremes(), None);
+    assert_eq!(rbt.extremes(), None);
+    for _ in 0..200 {
+        let v: i32 = rng.gen_range(-500, 500);
+        bst.insert(v);
+        avl.insert(v);
+        rbt.insert(v);
+        let expected = Some((bst.min().unwrap(), bst.max().unwrap()));
+        assert_eq!(bst.extremes(), expected);
+        assert_eq!(avl.extremes(), expected);
+        assert_eq!(rbt.extremes(), expected);
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn deserialize() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let values: Vec<i32> = (0..300).map(|_| rng.gen_range(0, 1000)).collect();
+    let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let avl: AVLTree<i32> = values.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+
+    let bst2: BinarySearchTree<i32> = serde_json::from_str(&serde_json::to_string(&bst).unwrap()).unwrap();
+    assert_eq!(bst2.to_vec(), bst.to_vec());
+    assert_eq!(bst2.len(), bst.len());
+    assert!(bst2.height() < bst.height());
+
+    let avl2: AVLTree<i32> = serde_json::from_str(&serde_json::to_string(&avl).unwrap()).unwrap();
+    assert_eq!(avl2.to_vec(), avl.to_vec());
+    assert_eq!(avl2.len(), avl.len());
+    assert!(avl2.is_valid());
+
+    let json = serde_json::to_string(&rbt).unwrap();
+    let rbt2: RedBlackTree<i32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(rbt2.to_debug_nested(), rbt.to_debug_nested());
+    assert_eq!(rbt2.iter_colored().collect::<Vec<_>>(), rbt.iter_colored().collect::<Vec<_>>());
+    assert_eq!(rbt2.len(), rbt.len());
+    assert!(rbt2.is_valid());
+    assert_eq!(serde_json::to_string(&rbt2).unwrap(), json);
+    // the parents and sizes are restored too
+    let mut rbt2 = rbt2;
+    for v in values.iter().take(100) {
+        rbt2.delete(*v);
+        rbt2.insert(v + 1000);
+        assert!(rbt2.is_valid());
+    }
+    assert_eq!(rbt2.len(), rbt2.iter().count());
+
+    // the colors do not make a valid red-black tree, so it is rebalanced
+    let rbt3: RedBlackTree<i32> =
+        serde_json::from_str(r#"[[1,"Black"],[2,"Black"],[3,"Black"]]"#).unwrap();
+    assert_eq!(rbt3.to_vec(), vec![1, 2, 3]);
+    assert!(rbt3.is_valid());
+
+    // neither a preorder nor free of duplicates, so also rebalanced
+    let rbt4: RedBlackTree<i32> =
+        serde_json::from_str(r#"[[2,"Black"],[3,"Red"],[1,"Red"]]"#).unwrap();
+    assert_eq!(rbt4.to_vec(), vec![1, 2, 3]);
+    assert!(rbt4.is_valid());
+    let rbt5: RedBlackTree<i32> =
+        serde_json::from_str(r#"[[2,"Black"],[1,"Red"],[2,"Red"]]"#).unwrap();
+    assert_eq!(rbt5.to_vec(), vec![1, 2]);
+
+    // a long chain is turned down before it is checked recursively
+    let chain: Vec<_> = (0..100_000).map(|v| (v, crate::rbtree::NodeColor::Black)).collect();
+    let rbt6: RedBlackTree<i32> = serde_json::from_str(&serde_json::to_string(&chain).unwrap()).unwrap();
+    assert_eq!(rbt6.len(), 100_000);
+    assert!(rbt6.is_valid());
+
+    let empty: RedBlackTree<i32> = serde_json::from_str("[]").unwrap();
+    assert!(empty.is_empty());
+    assert!(serde_json::from_str::<RedBlackTree<i32>>(r#"[[1,"Blue"]]"#).is_err());
+}
+
+#[test]
+fn equal_by_key() {
+    let records = [(3, 'c'), (1, 'a'), (2, 'b'), (5, 'e')];
+    let renamed = [(5, 'v'), (2, 'w'), (3, 'x'), (1, 'y')];
+    let bst: BinarySearchTree<(i32, char)> = records.iter().copied().collect();
+    let avl: AVLTree<(i32, char)> = records.iter().copied().collect();
+    let rbt: RedBlackTree<(i32, char)> = records.iter().copied().collect();
+    let bst2: BinarySearchTree<(i32, char)> = renamed.iter().copied().collect();
+    let avl2: AVLTree<(i32, char)> = renamed.iter().copied().collect();
+    let rbt2: RedBlackTree<(i32, char)> = renamed.iter().copied().collect();
+    assert!(bst.equal_by_key(&bst2, |r| r.0));
+    assert!(avl.equal_by_key(&avl2, |r| r.0));
+    assert!(rbt.equal_by_key(&rbt2, |r| r.0));
+    assert!(!bst.equal_by_key(&bst2, |r| r.1));
+    assert!(!avl.equal_by_key(&avl2, |r| *r));
+
+    // the keys are compared as a multiset
+    let pairs: BinarySearchTree<(i32, char)> = [(1, 'a'), (1, 'b')].iter().copied().collect();
+    let other: BinarySearchTree<(i32, char)> = [(1, 'a'), (2, 'b')].iter().copied().collect();
+    assert!(!pairs.equal_by_key(&other, |r| r.0));
+    assert!(pairs.equal_by_key(&other, |r| r.0 > 0));
+    assert!(!bst.equal_by_key(&other, |r| r.0));
+}
+
+#[test]
+fn to_structure_string() {
+    fn lines(rows: &[&str]) -> String {
+        rows.iter().map(|row| format!("{}\n", row)).collect()
+    }
+
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    assert_eq!(bst.to_structure_string(), "");
+    assert_eq!(avl.to_structure_string(), "");
+    assert_eq!(rbt.to_structure_string(), "");
+    for v in [4, 2, 6, 1, 3, 7].iter() {
+        bst.insert(*v);
+        avl.insert(*v);
+        rbt.insert(*v);
+    }
+    let expected = lines(&[
+        "    ┌── 7",
+        "┌── 6",
+        "4",
+        "│   ┌── 3",
+        "└── 2",
+        "    └── 1",
+    ]);
+    assert_eq!(bst.to_structure_string(), expected);
+    assert_eq!(avl.to_structure_string(), expected);
+    assert_eq!(rbt.to_structure_string(), lines(&[
+        "    ┌── 7 (Red)",
+        "┌── 6 (Black)",
+        "4 (Black)",
+        "│   ┌── 3 (Red)",
+        "└── 2 (Black)",
+        "    └── 1 (Red)",
+    ]));
+
+    bst.insert(5);
+    assert_eq!(bst.to_structure_string(), lines(&[
+        "    ┌── 7",
+        "┌── 6",
+        "│   └── 5",
+        "4",
+        "│   ┌── 3",
+        "└── 2",
+        "    └── 1",
+    ]));
+}
+
+#[test]
+fn merge_iter() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let a: Vec<i32> = (0..200).map(|_| rng.gen_range(0, 300)).collect();
+    let b: Vec<i32> = (0..100).map(|_| rng.gen_range(100, 500)).collect();
+    let mut union: BinarySearchTree<i32> = a.iter().copied().collect();
+    union.extend(b.iter().copied());
+    let expected = union.to_vec();
+
+    let bst_a: BinarySearchTree<i32> = a.iter().copied().collect();
+    let bst_b: BinarySearchTree<i32> = b.iter().copied().collect();
+    let avl_a: AVLTree<i32> = a.iter().copied().collect();
+    let avl_b: AVLTree<i32> = b.iter().copied().collect();
+    let rbt_a: RedBlackTree<i32> = a.iter().copied().collect();
+    let rbt_b: RedBlackTree<i32> = b.iter().copied().collect();
+    assert_eq!(bst_a.merge_iter(&bst_b).collect::<Vec<_>>(), expected);
+    assert_eq!(bst_b.merge_iter(&bst_a).collect::<Vec<_>>(), expected);
+    assert_eq!(avl_a.merge_iter(&avl_b).collect::<Vec<_>>(), expected);
+    assert_eq!(rbt_a.merge_iter(&rbt_b).collect::<Vec<_>>(), expected);
+
+    let empty = BinarySearchTree::new();
+    assert_eq!(bst_a.merge_iter(&empty).collect::<Vec<_>>(), bst_a.to_vec());
+    assert_eq!(empty.merge_iter(&bst_a).collect::<Vec<_>>(), bst_a.to_vec());
+    assert_eq!(empty.merge_iter(&empty).next(), None);
+}
+
+#[test]
+fn display() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    assert_eq!(bst.to_string(), "{}");
+    assert_eq!(avl.to_string(), "{}");
+    assert_eq!(rbt.to_string(), "{}");
+    for v in [3, 0, 5, 1].iter() {
+        bst.insert(*v);
+        avl.insert(*v);
+        rbt.insert(*v);
+    }
+    assert_eq!(format!("{}", bst), "{0, 1, 3, 5}");
+    assert_eq!(format!("{}", avl), "{0, 1, 3, 5}");
+    assert_eq!(format!("{}", rbt), "{0, 1, 3, 5}");
+
+    let words: BinarySearchTree<&str> = ["b", "a"].iter().copied().collect();
+    assert_eq!(words.to_string(), r#"{"a", "b"}"#);
+}
+
+#[test]
+fn first_divergence() {
+    let mut bst_a: BinarySearchTree<i32> = (0..50).collect();
+    let mut bst_b: BinarySearchTree<i32> = (0..50).rev().collect();
+    let mut avl_a: AVLTree<i32> = (0..50).collect();
+    let mut avl_b: AVLTree<i32> = (0..50).rev().collect();
+    let mut rbt_a: RedBlackTree<i32> = (0..50).collect();
+    let mut rbt_b: RedBlackTree<i32> = (0..50).rev().collect();
+    assert_eq!(bst_a.first_divergence(&bst_b), None);
+    assert_eq!(avl_a.first_divergence(&avl_b), None);
+    assert_eq!(rbt_a.first_divergence(&rbt_b), None);
+
+    // agree on 0..=20, then `a` misses 21 and `b` misses 30
+    bst_a.delete(21);
+    avl_a.delete(21);
+    rbt_a.delete(21);
+    bst_b.delete(30);
+    avl_b.delete(30);
+    rbt_b.delete(30);
+    assert_eq!(bst_a.first_divergence(&bst_b), Some(21));
+    assert_eq!(bst_b.first_divergence(&bst_a), Some(21));
+    assert_eq!(avl_a.first_divergence(&avl_b), Some(21));
+    assert_eq!(rbt_a.first_divergence(&rbt_b), Some(21));
+
+    bst_a.insert(21);
+    avl_a.insert(21);
+    rbt_a.insert(21);
+    assert_eq!(bst_a.first_divergence(&bst_b), Some(30));
+    assert_eq!(avl_a.first_divergence(&avl_b), Some(30));
+    assert_eq!(rbt_b.first_divergence(&rbt_a), Some(30));
+
+    // one tree is a prefix of the other
+    bst_b.insert(30);
+    bst_b.insert(50);
+    assert_eq!(bst_a.first_divergence(&bst_b), Some(50));
+    assert_eq!(BinarySearchTree::new().first_divergence(&bst_b), Some(0));
+}
+
+#[test]
+fn deep_clone() {
+    let values = [50, 20, 80, 10, 30, 70, 90, 60, 65];
+    let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let avl: AVLTree<i32> = values.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    let (bst_shape, avl_shape, rbt_shape) = (bst.to_debug_nested(), avl.to_debug_nested(), rbt.to_debug_nested());
+    let rbt_colors: Vec<_> = rbt.iter_colored().collect();
+
+    let mut bst2 = bst.clone();
+    let mut avl2 = avl.clone();
+    let mut rbt2 = rbt.clone();
+    assert_eq!(bst2.to_debug_nested(), bst_shape);
+    assert_eq!(avl2.to_debug_nested(), avl_shape);
+    assert_eq!(rbt2.to_debug_nested(), rbt_shape);
+    assert_eq!(rbt2.iter_colored().collect::<Vec<_>>(), rbt_colors);
+
+    for v in [66, 67, 68, 1, 2].iter() {
+        bst2.insert(*v);
+        avl2.insert(*v);
+        rbt2.insert(*v);
+    }
+    bst2.delete(50);
+    avl2.delete(50);
+    rbt2.delete(50);
+    assert!(avl2.is_valid());
+    assert!(rbt2.is_valid());
+    assert_eq!(rbt2.len(), values.len() + 4);
+    assert_eq!(bst.to_debug_nested(), bst_shape);
+    assert_eq!(avl.to_debug_nested(), avl_shape);
+    assert_eq!(rbt.to_debug_nested(), rbt_shape);
+    assert_eq!(rbt.iter_colored().collect::<Vec<_>>(), rbt_colors);
+    assert_eq!(bst.len(), values.len());
+    assert!(rbt.is_valid());
+
+    // the copy keeps working after the original is gone
+    drop(rbt);
+    assert_eq!(rbt2.pop_min(), Some(1));
+    assert!(rbt2.is_valid());
+}
+
+#[test]
+fn insert_range() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    bst.insert_range(1, 100);
+    avl.insert_range(1, 100);
+    rbt.insert_range(1, 100);
+    assert_eq!(bst.len(), 100);
+    assert_eq!(avl.len(), 100);
+    assert_eq!(rbt.len(), 100);
+    assert_eq!(bst.extremes(), Some((1, 100)));
+    assert_eq!(avl.extremes(), Some((1, 100)));
+    assert_eq!(rbt.extremes(), Some((1, 100)));
+    assert_eq!(bst.height(), 7);
+    assert!(avl.is_valid());
+    assert!(rbt.is_valid());
+
+    // overlapping the values already there, and an empty range
+    bst.insert_range(90, 120);
+    avl.insert_range(90, 120);
+    rbt.insert_range(90, 120);
+    avl.insert_range(5, 4);
+    assert_eq!(bst.to_vec(), (1..=120).collect::<Vec<_>>());
+    assert_eq!(avl.to_vec(), (1..=120).collect::<Vec<_>>());
+    assert_eq!(rbt.to_vec(), (1..=120).collect::<Vec<_>>());
+    assert!(avl.is_valid());
+    assert!(rbt.is_valid());
+    assert_eq!(avl.prefix_sum_up_to(120), (1..=120).sum::<i32>());
+
+    // values on both sides of and inside the range are merged with it
+    let mut rbt = RedBlackTree::with_history();
+    for v in [0, 12, 15, 30] {
+        rbt.insert(v);
+    }
+    rbt.insert_range(10, 20);
+    let mut expected = vec![0];
+    expected.extend(10..=20);
+    expected.push(30);
+    assert_eq!(rbt.to_vec(), expected);
+    assert!(rbt.is_valid());
+    assert_eq!(rbt.insertion_history().map(<[i32]>::len), Some(15));
+
+    // the range may end at the largest value of the type
+    let mut small = AVLTree::new();
+    small.insert_range(250u8, 255);
+    assert_eq!(small.to_vec(), vec![250, 251, 252, 253, 254, 255]);
+}
+
+#[test]
+fn content_equality() {
+    let bst: BinarySearchTree<i32> = (0..20).collect();
+    let avl: AVLTree<i32> = (0..20).rev().collect();
+    let rbt: RedBlackTree<i32> = (0..20).collect();
+    assert_ne!(bst.to_debug_nested(), avl.to_debug_nested());
+    assert!(bst == avl);
+    assert!(avl == rbt);
+    assert!(rbt == bst);
+    assert!(bst == bst.clone());
+    assert!(rbt == rbt.clone());
+
+    let mut other = avl.clone();
+    other.delete(7);
+    assert!(avl != other);
+    assert!(bst != other);
+    other.insert(70);
+    assert!(rbt != other);
+    other.delete(70);
+    other.insert(7);
+    assert!(rbt == other);
+    assert!(BinarySearchTree::<i32>::new() == RedBlackTree::new());
+    assert!(BinarySearchTree::<i32>::new() != bst);
+}
+
+#[test]
+fn most_critical_node() {
+    // a small left side and a chain of 20 values on the right side
+    let mut bst = BinarySearchTree::new();
+    assert_eq!(bst.most_critical_node(), None);
+    bst.insert(10);
+    assert_eq!(bst.most_critical_node(), None);
+    for v in [5, 3, 7].iter().copied().chain(20..40) {
+        bst.insert(v);
+    }
+    assert_eq!(bst.most_critical_node(), Some(20));
+    for v in 0..3 {
+        bst.insert(v);
+    }
+    assert_eq!(bst.most_critical_node(), Some(20));
+
+    // both sides of a perfect tree are equally large, so the left one wins
+    let rbt: RedBlackTree<i32> = [4, 2, 6, 1, 3, 5, 7].iter().copied().collect();
+    assert_eq!(rbt.most_critical_node(), Some(2));
+    let avl: AVLTree<i32> = (0..15).collect();
+    assert_eq!(avl.most_critical_node(), Some(3));
+    let avl: AVLTree<i32> = (0..16).collect();
+    assert_eq!(avl.most_critical_node(), Some(11));
+}
+
+#[test]
+fn default() {
+    #[derive(Default)]
+    struct Index {
+        bst: BinarySearchTree<i32>,
+        avl: AVLTree<i32>,
+        rbt: RedBlackTree<i32>,
+    }
+
+    assert!(BinarySearchTree::<i32>::default().is_empty());
+    assert!(AVLTree::<i32>::default().is_empty());
+    assert!(RedBlackTree::<i32>::default().is_empty());
+    let index = Index::default();
+    assert!(index.bst.is_empty() && index.avl.is_empty() && index.rbt.is_empty());
+}
+
+#[test]
+fn to_heap_array() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let values: Vec<i32> = (0..200).map(|_| rng.gen_range(-1000, 1000)).collect();
+    let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let avl: AVLTree<i32> = values.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    for heap in [bst.to_heap_array(), avl.to_heap_array(), rbt.to_heap_array()].iter() {
+        for i in 1..heap.len() {
+            assert!(heap[(i - 1) / 2] <= heap[i]);
+        }
+        let mut sorted = heap.clone();
+        sorted.sort();
+        assert_eq!(sorted, bst.to_vec());
+    }
+    assert!(BinarySearchTree::<i32>::new().to_heap_array().is_empty());
+}
+
+#[test]
+fn clear() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for i in 0..2000 {
+        bst.insert(i);
+        avl.insert(i);
+        rbt.insert(i);
+    }
+    bst.clear();
+    avl.clear();
+    rbt.clear();
+    assert!(bst.is_empty() && avl.is_empty() && rbt.is_empty());
+    assert_eq!((bst.len(), avl.len(), rbt.len()), (0, 0, 0));
+
+    bst.insert(3);
+    avl.insert(3);
+    rbt.insert(3);
+    assert_eq!((bst.to_vec(), avl.to_vec(), rbt.to_vec()), (vec![3], vec![3], vec![3]));
+    assert!(rbt.is_valid());
+}
+
+#[test]
+fn len_tracks_inserts_and_deletes() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+    use std::collections::BTreeSet;
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    let mut expected = BTreeSet::new();
+    for _ in 0..2000 {
+        // a small key range makes duplicate inserts and missing deletes common
+        let v: i32 = rng.gen_range(0, 100);
+        if rng.gen_range(0, 3) == 0 {
+            bst.delete(v);
+            avl.delete(v);
+            rbt.delete(v);
+            expected.remove(&v);
+        } else {
+            bst.insert(v);
+            avl.insert(v);
+            rbt.insert(v);
+            expected.insert(v);
+        }
+        assert_eq!(bst.len(), expected.len());
+        assert_eq!(avl.len(), expected.len());
+        assert_eq!(rbt.len(), expected.len());
+    }
+
+    // deleting from an empty tree leaves the length at zero
+    let mut bst = BinarySearchTree::new();
+    bst.insert(1);
+    bst.insert(1);
+    assert_eq!(bst.len(), 1);
+    bst.delete(1);
+    bst.delete(1);
+    assert_eq!(bst.len(), 0);
+}
+
+#[test]
+fn tightest_range_with_count() {
+    // three clusters, the middle one being the densest
+    let values = vec![0, 4, 8, 12, 100, 101, 103, 104, 200, 202, 205];
+    let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let avl: AVLTree<i32> = values.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    for (range, k) in [
+        (bst.tightest_range_with_count(4), 4),
+        (avl.tightest_range_with_count(4), 4),
+        (rbt.tightest_range_with_count(4), 4),
+    ].iter() {
+        assert_eq!(*range, Some((100, 104)));
+        let (lo, hi) = range.unwrap();
+        assert_eq!(bst.iter().filter(|v| lo <= *v && *v <= hi).count(), *k);
+    }
+    assert_eq!(bst.tightest_range_with_count(1), Some((0, 0)));
+    assert_eq!(bst.tightest_range_with_count(2), Some((100, 101)));
+    assert_eq!(bst.tightest_range_with_count(5), Some((12, 104)));
+    assert_eq!(bst.tightest_range_with_count(values.len()), Some((0, 205)));
+    assert_eq!(bst.tightest_range_with_count(values.len() + 1), None);
+    assert_eq!(bst.tightest_range_with_count(0), None);
+
+    // 0 - (-128) overflows i8, so the upper window wins
+    let tree: BinarySearchTree<i8> = vec![-128, 0, 127].into_iter().collect();
+    assert_eq!(tree.tightest_range_with_count(2), Some((0, 127)));
+    let tree: BinarySearchTree<i8> = vec![-128, 127].into_iter().collect();
+    assert_eq!(tree.tightest_range_with_count(2), Some((-128, 127)));
+}
+
+#[test]
+fn insert_reports_new_values() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for &v in [5, 3, 8, 1, 4, 7, 9].iter() {
+        assert!(bst.insert(v));
+        assert!(avl.insert(v));
+        assert!(rbt.insert(v));
+    }
+    for &v in [5, 1, 9, 4].iter() {
+        assert!(!bst.insert(v));
+        assert!(!avl.insert(v));
+        assert!(!rbt.insert(v));
+    }
+    assert_eq!((bst.len(), avl.len(), rbt.len()), (7, 7, 7));
+
+    // a deleted value can be added again
+    rbt.delete(5);
+    assert!(rbt.insert(5));
+
+    // equality follows the comparator of the tree
+    let mut by_abs = BinarySearchTree::with_comparator(|a: &i32, b: &i32| a.abs().cmp(&b.abs()));
+    assert!(by_abs.insert(-2));
+    assert!(!by_abs.insert(2));
+}
+
+#[test]
+fn delete_returns_removed_value() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    assert_eq!((bst.delete(1), avl.delete(1), rbt.delete(1)), (None, None, None));
+    for &v in [5, 2, 8, 1, 9, 3].iter() {
+        bst.insert(v);
+        avl.insert(v);
+        rbt.insert(v);
+    }
+    for &v in [5, 1, 9].iter() {
+        assert_eq!(bst.delete(v), Some(v));
+        assert_eq!(avl.delete(v), Some(v));
+        assert_eq!(rbt.delete(v), Some(v));
+    }
+    for &v in [5, 4, 10].iter() {
+        assert_eq!(bst.delete(v), None);
+        assert_eq!(avl.delete(v), None);
+        assert_eq!(rbt.delete(v), None);
+    }
+    assert_eq!((bst.len(), avl.len(), rbt.len()), (3, 3, 3));
+
+    // the stored value is returned, not the one used for the lookup
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for (key, tag) in [(1, 'a'), (2, 'b'), (3, 'c')].iter() {
+        bst.insert(Keyed { key: *key, tag: *tag });
+        avl.insert(Keyed { key: *key, tag: *tag });
+        rbt.insert(Keyed { key: *key, tag: *tag });
+    }
+    let probe = Keyed { key: 2, tag: '?' };
+    assert_eq!(bst.delete(probe).map(|k| k.tag), Some('b'));
+    assert_eq!(avl.delete(probe).map(|k| k.tag), Some('b'));
+    assert_eq!(rbt.delete(probe).map(|k| k.tag), Some('b'));
+    assert_eq!(bst.len(), 2);
+}
+
+#[test]
+fn deepest_leaf_count() {
+    // a perfect tree of height 4 has all 8 leaves at depth 3
+    let perfect: Vec<i32> = vec![8, 4, 12, 2, 6, 10, 14, 1, 3, 5, 7, 9, 11, 13, 15];
+    let bst: BinarySearchTree<i32> = perfect.iter().copied().collect();
+    let avl: AVLTree<i32> = perfect.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = perfect.iter().copied().collect();
+    for histogram in [bst.leaf_depth_histogram(), avl.leaf_depth_histogram(), rbt.leaf_depth_histogram()].iter() {
+        assert_eq!(histogram.iter().collect::<Vec<_>>(), vec![(&3, &8)]);
+    }
+    assert_eq!((bst.deepest_leaf_count(), avl.deepest_leaf_count(), rbt.deepest_leaf_count()), (8, 8, 8));
+
+    // a degenerate chain has one leaf, at the bottom
+    let chain: BinarySearchTree<i32> = (1..=15).collect();
+    assert_eq!(chain.leaf_depth_histogram().iter().collect::<Vec<_>>(), vec![(&14, &1)]);
+    assert_eq!(chain.deepest_leaf_count(), 1);
+
+    assert!(BinarySearchTree::<i32>::new().leaf_depth_histogram().is_empty());
+    assert_eq!(BinarySearchTree::<i32>::new().deepest_leaf_count(), 0);
+}
+
+#[test]
+fn iter_bfs() {
+    let perfect: Vec<i32> = vec![8, 4, 12, 2, 6, 10, 14, 1, 3, 5, 7, 9, 11, 13, 15];
+    let bst: BinarySearchTree<i32> = perfect.iter().copied().collect();
+    let avl: AVLTree<i32> = perfect.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = perfect.iter().copied().collect();
+    assert_eq!(bst.iter_bfs().take(3).collect::<Vec<_>>(), vec![8, 4, 12]);
+    assert_eq!(avl.iter_bfs().take(7).collect::<Vec<_>>(), vec![8, 4, 12, 2, 6, 10, 14]);
+    assert_eq!(rbt.iter_bfs().next(), Some(8));
+
+    // walked to the end it agrees with the eager level order
+    let flattened: Vec<i32> = bst.level_order().into_iter().flatten().collect();
+    assert_eq!(bst.iter_bfs().collect::<Vec<_>>(), flattened);
+    assert_eq!(bst.iter_bfs().collect::<Vec<_>>(), perfect);
+    let chain: BinarySearchTree<i32> = (1..=5).collect();
+    assert_eq!(chain.iter_bfs().collect::<Vec<_>>(), vec![1, 2, 3, 4, 5]);
+    assert_eq!(BinarySearchTree::<i32>::new().iter_bfs().next(), None);
+}
+
+#[test]
+fn split_off() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let values: Vec<i32> = (0..300).map(|_| rng.gen_range(-1000, 1000)).collect();
+    let original: BinarySearchTree<i32> = values.iter().copied().collect();
+    let sorted = original.to_vec();
+    for &key in [-2000, -1000, -3, 0, sorted[17], 999, 2000].iter() {
+        let mut bst: BinarySearchTree<i32> = values.iter().copied().collect();
+        let mut avl: AVLTree<i32> = values.iter().copied().collect();
+        let mut rbt: RedBlackTree<i32> = values.iter().copied().collect();
+        let (bst_upper, avl_upper, rbt_upper) = (bst.split_off(key), avl.split_off(key), rbt.split_off(key));
+        let lower: Vec<i32> = sorted.iter().copied().filter(|v| *v < key).collect();
+        let upper: Vec<i32> = sorted.iter().copied().filter(|v| *v >= key).collect();
+        for (low, high) in [(bst.to_vec(), bst_upper.to_vec()), (avl.to_vec(), avl_upper.to_vec()), (rbt.to_vec(), rbt_upper.to_vec())].iter() {
+            assert_eq!(*low, lower);
+            assert_eq!(*high, upper);
+        }
+        assert!(avl.is_valid() && avl_upper.is_valid());
+        assert!(rbt.is_valid() && rbt_upper.is_valid());
+        assert_eq!(bst.len() + bst_upper.len(), sorted.len());
+
+        // the halves keep working as normal trees
+        let mut rbt_upper = rbt_upper;
+        for v in upper.iter().step_by(3) {
+            rbt_upper.delete(*v);
+            rbt.insert(*v);
+        }
+        assert!(rbt.is_valid() && rbt_upper.is_valid());
+    }
+}
+
+#[test]
+fn iter_with_spine_flag() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let values: Vec<i32> = (0..200).map(|_| rng.gen_range(-1000, 1000)).collect();
+    let bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let avl: AVLTree<i32> = values.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    let (min, max) = (bst.min().unwrap(), bst.max().unwrap());
+    for flagged in [
+        bst.iter_with_spine_flag().collect::<Vec<_>>(),
+        avl.iter_with_spine_flag().collect::<Vec<_>>(),
+        rbt.iter_with_spine_flag().collect::<Vec<_>>(),
+    ].iter() {
+        assert_eq!(flagged.iter().map(|f| f.0).collect::<Vec<_>>(), bst.to_vec());
+        assert_eq!(flagged.first(), Some(&(min, true, false)));
+        assert_eq!(flagged.last(), Some(&(max, false, true)));
+        // only the root is on both spines
+        assert_eq!(flagged.iter().filter(|f| f.1 && f.2).count(), 1);
+    }
+
+    // in a chain of increasing values every node is on the right spine
+    let chain: BinarySearchTree<i32> = (1..=4).collect();
+    let flagged: Vec<(i32, bool, bool)> = chain.iter_with_spine_flag().collect();
+    assert_eq!(flagged, vec![(1, true, true), (2, false, true), (3, false, true), (4, false, true)]);
+    assert_eq!(BinarySearchTree::<i32>::new().iter_with_spine_flag().count(), 0);
+}
+
+#[test]
+fn from_sorted() {
+    for n in 0..300 {
+        let values: Vec<i32> = (0..n).map(|v| v * 3 - 100).collect();
+        let bst = BinarySearchTree::from_sorted(&values);
+        let avl = AVLTree::from_sorted(&values);
+        let rbt = RedBlackTree::from_sorted(&values);
+        // ceil(log2(n + 1)) levels are needed for n values
+        let minimal = (usize::BITS - (n as usize).leading_zeros()) as usize;
+        assert_eq!((bst.height(), avl.height(), rbt.height()), (minimal, minimal, minimal));
+        assert_eq!(bst.to_vec(), values);
+        assert_eq!(avl.to_vec(), values);
+        assert_eq!(rbt.to_vec(), values);
+        assert_eq!((bst.len(), avl.len(), rbt.len()), (values.len(), values.len(), values.len()));
+        assert!(avl.is_valid());
+        assert!(rbt.is_valid(), "invalid coloring for {} values", n);
+    }
+
+    // the trees built this way keep working as usual
+    let values: Vec<i32> = (0..100).collect();
+    let mut avl = AVLTree::from_sorted(&values);
+    let mut rbt = RedBlackTree::from_sorted(&values);
+    for v in (0..100).step_by(3) {
+        avl.delete(v);
+        rbt.delete(v);
+        avl.insert(v + 1000);
+        rbt.insert(v + 1000);
+    }
+    assert!(avl.is_valid() && rbt.is_valid());
+    assert_eq!(avl.to_vec(), rbt.to_vec());
+}
+
+#[test]
+fn built_from_disjoint() {
+    let evens: Vec<i32> = (0..50).map(|v| v * 2).collect();
+    let odds: Vec<i32> = (0..50).map(|v| v * 2 + 1).collect();
+    let bst: BinarySearchTree<i32> = evens.iter().chain(odds.iter()).copied().collect();
+    let avl: AVLTree<i32> = evens.iter().chain(odds.iter()).copied().collect();
+    let rbt: RedBlackTree<i32> = evens.iter().chain(odds.iter()).copied().collect();
+    assert!(bst.built_from_disjoint(&evens, &odds));
+    assert!(avl.built_from_disjoint(&odds, &evens));
+    assert!(rbt.built_from_disjoint(&evens, &odds));
+
+    // overlapping sources merge into fewer values than they hold together
+    let low: Vec<i32> = (0..60).collect();
+    let high: Vec<i32> = (40..100).collect();
+    let merged: BinarySearchTree<i32> = low.iter().chain(high.iter()).copied().collect();
+    assert_eq!(merged.to_vec(), (0..100).collect::<Vec<_>>());
+    assert!(!merged.built_from_disjoint(&low, &high));
+
+    // a duplicate within a source can't hide an extra value in the tree
+    let tree: BinarySearchTree<i32> = vec![1, 2].into_iter().collect();
+    assert!(!tree.built_from_disjoint(&[1, 1], &[]));
+    assert!(!tree.built_from_disjoint(&[1], &[3]));
+    assert!(!tree.built_from_disjoint(&[1], &[]));
+    assert!(BinarySearchTree::<i32>::new().built_from_disjoint(&[], &[]));
+}
+
+#[test]
+fn diameter() {
+    assert_eq!(BinarySearchTree::<i32>::new().diameter(), 0);
+    let single: BinarySearchTree<i32> = vec![1].into_iter().collect();
+    assert_eq!(single.diameter(), 0);
+
+    // a perfect tree of height 3, from leaf to leaf over the root
+    let perfect: Vec<i32> = vec![4, 2, 6, 1, 3, 5, 7];
+    let bst: BinarySearchTree<i32> = perfect.iter().copied().collect();
+    let avl: AVLTree<i32> = perfect.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = perfect.iter().copied().collect();
+    assert_eq!((bst.diameter(), avl.diameter(), rbt.diameter()), (4, 4, 4));
+
+    //        10
+    //       /
+    //      5
+    //     / \
+    //    2   8
+    //   /     \
+    //  1       9
+    // the longest path, 1 to 9, doesn't pass through the root
+    let lopsided: BinarySearchTree<i32> = vec![10, 5, 2, 8, 1, 9].into_iter().collect();
+    assert_eq!(lopsided.diameter(), 4);
+
+    // a degenerate chain is a single path
+    let chain: BinarySearchTree<i32> = (0..50).collect();
+    assert_eq!(chain.diameter(), chain.height() - 1);
+}
+
+#[test]
+fn subtree_index_range() {
+    //           8
+    //        /     \
+    //       4       12
+    //      / \     /  \
+    //     2   6   10   14
+    //    / \ / \  / \  / \
+    //   1  3 5 7 9 11 13 15
+    let perfect: Vec<i32> = vec![8, 4, 12, 2, 6, 10, 14, 1, 3, 5, 7, 9, 11, 13, 15];
+    let bst: BinarySearchTree<i32> = perfect.iter().copied().collect();
+    let avl: AVLTree<i32> = perfect.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = perfect.iter().copied().collect();
+    for tree_ranges in [
+        [bst.subtree_index_range(12), bst.subtree_index_range(6), bst.subtree_index_range(8)],
+        [avl.subtree_index_range(12), avl.subtree_index_range(6), avl.subtree_index_range(8)],
+        [rbt.subtree_index_range(12), rbt.subtree_index_range(6), rbt.subtree_index_range(8)],
+    ].iter() {
+        assert_eq!(*tree_ranges, [Some((8, 14)), Some((4, 6)), Some((0, 14))]);
+    }
+    assert_eq!(bst.subtree_index_range(9), Some((8, 8)));
+    assert_eq!(bst.subtree_index_range(16), None);
+
+    // the root of a subtree sits within the range it covers
+    let sorted = bst.to_vec();
+    for v in perfect.iter() {
+        let (first, last) = bst.subtree_index_range(*v).unwrap();
+        assert!(first <= bst.rank(*v) && bst.rank(*v) <= last);
+        assert_eq!(sorted[bst.rank(*v)], *v);
+    }
+    assert_eq!(BinarySearchTree::<i32>::new().subtree_index_range(1), None);
+}
+
+#[test]
+fn width() {
+    assert_eq!(BinarySearchTree::<i32>::new().width(), 0);
+    let single: AVLTree<i32> = vec![1].into_iter().collect();
+    assert_eq!(single.width(), 1);
+
+    // sorted inserts: a chain for the plain tree, bushy for the balanced ones
+    let bst: BinarySearchTree<i32> = (0..31).collect();
+    let avl: AVLTree<i32> = (0..31).collect();
+    let rbt: RedBlackTree<i32> = (0..31).collect();
+    assert_eq!(bst.width(), 1);
+    assert_eq!(avl.width(), 16);
+    assert!(rbt.width() > 1);
+    assert_eq!(rbt.width(), rbt.level_order().iter().map(|level| level.len()).max().unwrap());
+
+    // the widest level is not necessarily the last one
+    //      4
+    //     / \
+    //    2   6
+    //         \
+    //          7
+    let tree: BinarySearchTree<i32> = vec![4, 2, 6, 7].into_iter().collect();
+    assert_eq!(tree.width(), 2);
+}
+
+#[test]
+fn insert_windowed() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for v in 0..1000 {
+        let expected = if v >= 10 { Some(v - 10) } else { None };
+        assert_eq!(bst.insert_windowed(v, 10), expected);
+        assert_eq!(avl.insert_windowed(v, 10), expected);
+        assert_eq!(rbt.insert_windowed(v, 10), expected);
+    }
+    let last: Vec<i32> = (990..1000).collect();
+    assert_eq!((bst.to_vec(), avl.to_vec(), rbt.to_vec()), (last.clone(), last.clone(), last));
+    assert!(avl.is_valid() && rbt.is_valid());
+
+    // a value smaller than everything in a full window is evicted right away
+    assert_eq!(avl.insert_windowed(5, 10), Some(5));
+    // a duplicate doesn't grow the tree, so nothing is evicted
+    assert_eq!(avl.insert_windowed(995, 10), None);
+    assert_eq!(avl.len(), 10);
+}
+
+#[test]
+fn count_at_depth() {
+    let perfect: Vec<i32> = (1..64).collect();
+    let bst = BinarySearchTree::from_sorted(&perfect);
+    let avl = AVLTree::from_sorted(&perfect);
+    let rbt = RedBlackTree::from_sorted(&perfect);
+    for k in 0..6 {
+        assert_eq!(bst.count_at_depth(k), 1 << k);
+        assert_eq!(avl.count_at_depth(k), 1 << k);
+        assert_eq!(rbt.count_at_depth(k), 1 << k);
+    }
+    assert_eq!(bst.count_at_depth(6), 0);
+    assert_eq!(bst.count_at_depth(100), 0);
+
+    // a zigzag is skewed, with one node on every level
+    let zigzag: BinarySearchTree<i32> = vec![10, 1, 9, 2, 8, 3].into_iter().collect();
+    for k in 0..6 {
+        assert_eq!(zigzag.count_at_depth(k), 1);
+    }
+    assert_eq!(zigzag.count_at_depth(6), 0);
+    assert_eq!(BinarySearchTree::<i32>::new().count_at_depth(0), 0);
+}
+
+#[test]
+fn depth_of() {
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for v in 0..100 {
+        bst.insert(v);
+        avl.insert(v);
+        rbt.insert(v);
+    }
+    // sorted inserts make the plain tree a chain, the value is its depth
+    for v in 0..100 {
+        assert_eq!(bst.depth_of(v), Some(v as usize));
+        assert!(avl.depth_of(v).unwrap() < avl.height());
+        assert!(rbt.depth_of(v).unwrap() < rbt.height());
+    }
+    assert!(avl.depth_of(99).unwrap() < bst.depth_of(99).unwrap());
+    let avl_total: usize = (0..100).map(|v| avl.depth_of(v).unwrap()).sum();
+    let bst_total: usize = (0..100).map(|v| bst.depth_of(v).unwrap()).sum();
+    assert!(avl_total < bst_total);
+
+    assert_eq!(avl.depth_of(avl.get_root().as_ref().unwrap().borrow().data), Some(0));
+    assert_eq!((bst.depth_of(100), avl.depth_of(-1), rbt.depth_of(1000)), (None, None, None));
+    assert_eq!(BinarySearchTree::<i32>::new().depth_of(1), None);
+}
+
+#[test]
+fn lca() {
+    //           8
+    //        /     \
+    //       4       12
+    //      / \     /  \
+    //     2   6   10   14
+    //    / \ / \  / \  / \
+    //   1  3 5 7 9 11 13 15
+    let perfect: Vec<i32> = vec![8, 4, 12, 2, 6, 10, 14, 1, 3, 5, 7, 9, 11, 13, 15];
+    let bst: BinarySearchTree<i32> = perfect.iter().copied().collect();
+    let avl: AVLTree<i32> = perfect.iter().copied().collect();
+    let rbt: RedBlackTree<i32> = perfect.iter().copied().collect();
+    for &(a, b, lca) in [(1, 3, 2), (3, 5, 4), (1, 15, 8), (9, 11, 10), (13, 9, 12), (7, 7, 7)].iter() {
+        assert_eq!(bst.lca(a, b), Some(lca));
+        assert_eq!(avl.lca(a, b), Some(lca));
+        assert_eq!(rbt.lca(a, b), Some(lca));
+    }
+    // one value is an ancestor of the other
+    assert_eq!(bst.lca(4, 7), Some(4));
+    assert_eq!(bst.lca(15, 12), Some(12));
+    assert_eq!(bst.lca(8, 1), Some(8));
+
+    assert_eq!(bst.lca(1, 16), None);
+    assert_eq!(bst.lca(0, 3), None);
+    assert_eq!(BinarySearchTree::<i32>::new().lca(1, 1), None);
+}
+
+#[test]
+fn mirror() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let values: Vec<i32> = (0..200).map(|_| rng.gen_range(-1000, 1000)).collect();
+    let mut bst: BinarySearchTree<i32> = values.iter().copied().collect();
+    let mut avl: AVLTree<i32> = values.iter().copied().collect();
+    let mut rbt: RedBlackTree<i32> = values.iter().copied().collect();
+    let (bst_shape, avl_shape, rbt_shape) = (bst.to_structure_string(), avl.to_structure_string(), rbt.to_structure_string());
+    let mut triples = bst.to_triples();
+    triples.sort_by_key(|t| t.0);
+    let mut descending = bst.to_vec();
+    descending.reverse();
+
+    bst.mirror();
+    avl.mirror();
+    rbt.mirror();
+    assert_eq!(bst.to_vec(), descending);
+    assert_eq!(avl.to_vec(), descending);
+    assert_eq!(rbt.to_vec(), descending);
+    assert_eq!(bst.len(), descending.len());
+    assert!(!bst.is_valid());
+    // every node has its two children swapped
+    let mut mirrored = bst.to_triples();
+    mirrored.sort_by_key(|t| t.0);
+    for (value, left, right) in mirrored {
+        assert_eq!(triples.binary_search_by_key(&value, |t| t.0).map(|i| (triples[i].2, triples[i].1)), Ok((left, right)));
+    }
+
+    bst.mirror();
+    avl.mirror();
+    rbt.mirror();
+    assert_eq!(bst.to_structure_string(), bst_shape);
+    assert_eq!(avl.to_structure_string(), avl_shape);
+    assert_eq!(rbt.to_structure_string(), rbt_shape);
+    assert!(bst.is_valid() && avl.is_valid() && rbt.is_valid());
+
+    // a symmetric shape stays the same shape
+    let mut perfect = BinarySearchTree::from_sorted(&[1, 2, 3, 4, 5, 6, 7]);
+    let before = perfect.clone();
+    perfect.mirror();
+    assert!(perfect.same_shape(&before));
+    assert_eq!(perfect.iter_bfs().collect::<Vec<_>>(), vec![4, 6, 2, 7, 5, 3, 1]);
+}
+
+#[test]
+fn is_valid_bst() {
+    use crate::base::QueryableTreeNode;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for _ in 0..3000 {
+        let v: i32 = rng.gen_range(0, 300);
+        if rng.gen_range(0, 3) == 0 {
+            bst.delete(v);
+            avl.delete(v);
+            rbt.delete(v);
+        } else {
+            bst.insert(v);
+            avl.insert(v);
+            rbt.insert(v);
+        }
+        assert!(bst.is_valid_bst() && avl.is_valid_bst() && rbt.is_valid_bst());
+    }
+
+    //      5
+    //     / \
+    //    2   8
+    //     \
+    //      3
+    let mut tree: BinarySearchTree<i32> = vec![5, 2, 8, 3].into_iter().collect();
+    assert!(tree.is_valid_bst());
+    let three = tree.get_root().as_ref().unwrap().borrow().get_left().as_ref().unwrap()
+        .borrow().get_right().clone().unwrap();
+    // still in order below its parent 2, but not below the root 5
+    three.borrow_mut().data = 6;
+    assert!(!tree.is_valid_bst());
+    assert!(!tree.is_bst());
+    three.borrow_mut().data = 4;
+    assert!(tree.is_valid_bst());
+    // equal to an ancestor is out of place too
+    three.borrow_mut().data = 5;
+    assert!(!tree.is_valid_bst());
+    three.borrow_mut().data = 3;
+    tree.insert(1);
+    assert!(tree.is_valid_bst());
+    assert!(BinarySearchTree::<i32>::new().is_valid_bst());
+}
+
+#[test]
+fn float_values() {
+    let sorted: Vec<_> = (0..50).map(|i| OrderedFloat(i as f64 * 0.25 - 3.0)).collect();
+    let bst = BinarySearchTree::from_sorted(&sorted);
+    let avl = AVLTree::from_sorted(&sorted);
+    let rbt = RedBlackTree::from_sorted(&sorted);
+    assert_eq!(bst.to_vec(), sorted);
+    assert_eq!(avl.to_vec(), sorted);
+    assert_eq!(rbt.to_vec(), sorted);
+    assert!(bst.is_valid() && avl.is_valid() && rbt.is_valid());
+    assert!(BinarySearchTree::try_from_sorted(&sorted).is_ok());
+
+    let mut bst = BinarySearchTree::new();
+    for &v in [1.5, -0.0, f64::NAN, f64::NEG_INFINITY, 0.0, f64::INFINITY, -f64::NAN].iter() {
+        bst.insert(OrderedFloat(v));
+    }
+    // 0.0 equals -0.0, and every NaN equals the other one
+    assert_eq!(bst.len(), 5);
+    assert_eq!(bst.min(), Some(OrderedFloat(f64::NEG_INFINITY)));
+    assert!(bst.max().unwrap().0.is_nan());
+    assert!(bst.contains(OrderedFloat(0.0)));
+    assert_eq!(bst.delete(OrderedFloat(f64::NAN)).map(|v| v.0.is_nan()), Some(true));
+    assert_eq!(bst.max(), Some(OrderedFloat(f64::INFINITY)));
+    assert_eq!(bst.delete(OrderedFloat(1.5)), Some(OrderedFloat(1.5)));
+    assert_eq!(bst.delete(OrderedFloat(1.5)), None);
+    assert_eq!(
+        bst.to_vec().iter().map(|v| v.0).collect::<Vec<f64>>(),
+        vec![f64::NEG_INFINITY, -0.0, f64::INFINITY]
+    );
+
+    let mut avl = AVLTree::new();
+    for i in (0..100).rev() {
+        avl.insert(OrderedFloat(i as f32 / 10.0));
+    }
+    assert!(avl.is_valid());
+    assert_eq!(avl.min(), Some(OrderedFloat(0.0)));
+    assert_eq!(avl.max(), Some(OrderedFloat(9.9)));
+}
+
+#[test]
+fn into_sorted_vec() {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    let mut rng = StdRng::from_seed([0u8; 32]);
+    let values: Vec<i32> = (0..500).map(|_| rng.gen_range(-1000, 1000)).collect();
+    let mut bst = BinarySearchTree::new();
+    let mut avl = AVLTree::new();
+    let mut rbt = RedBlackTree::new();
+    for &v in values.iter() {
+        bst.insert(v);
+        avl.insert(v);
+        rbt.insert(v);
+    }
+    let mut sorted = values.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(bst.into_sorted_vec(), sorted);
+    assert_eq!(avl.into_sorted_vec(), sorted);
+    assert_eq!(rbt.into_sorted_vec(), sorted);
+
+    assert!(BinarySearchTree::<i32>::new().into_sorted_vec().is_empty());
+}